@@ -0,0 +1,66 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use gobx::{GobWriter, Value};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Counts allocations so the bench can report how many one encode performs.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ENTRIES: usize = 10_000;
+
+// Mixed value types keep the map as map[interface{}]interface{}, so every key and
+// element goes through the interface path.
+fn interface_map() -> Value {
+    let mut map = BTreeMap::new();
+    for i in 0..ENTRIES {
+        let v = if i % 2 == 0 { Value::Int(i as i64) } else { Value::String(format!("value-{}", i)) };
+        map.insert(Value::String(format!("key-{}", i)), v);
+    }
+    Value::Map(map)
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let value = interface_map();
+    let mut writer = GobWriter::new(std::io::sink());
+    // The first encode sends the type definition and warms the scratch buffers.
+    writer.encode(&value).unwrap();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    writer.encode(&value).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("steady-state encode of a {}-entry interface map: {} allocations", ENTRIES, allocations);
+
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Elements(ENTRIES as u64));
+    group.bench_function("interface_map_10k", |b| {
+        let mut writer = GobWriter::new(std::io::sink());
+        b.iter(|| writer.encode(black_box(&value)).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode);
+criterion_main!(benches);
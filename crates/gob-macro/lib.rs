@@ -3,33 +3,131 @@ use quote::quote;
 use syn::punctuated::Punctuated;
 use syn::{parse_macro_input, DeriveInput, Meta, Token, Data, Fields};
 use darling::{FromMeta, FromAttributes, ast::NestedMeta};
+use darling::util::{Flag, SpannedValue};
 
 #[derive(Debug, FromMeta)]
 struct GobArgs {
     #[darling(default)]
     id: Option<i64>,
+    // Go type the struct is sent as instead of a struct: "map[K]V", or "[]Elem" for a
+    // struct wrapping one Vec
     #[darling(default)]
-    interpret_as: Option<String>,
-    // type alias name
+    interpret_as: Option<SpannedValue<String>>,
+    // Go type name, sent in type definitions; defaults to the Rust identifier
     #[darling(default)]
     name: Option<String>,
+    // Wire form of a fieldless enum: "int" (the default) or "string"
+    #[darling(default)]
+    repr: Option<SpannedValue<String>>,
+    // Case convention for the Go names of fields (and string enum variants) without their
+    // own #[gob(name)]: "PascalCase", "camelCase", "snake_case" or "SCREAMING_SNAKE_CASE"
+    #[darling(default)]
+    rename_all: Option<SpannedValue<String>>,
+    // How map-mode decode matches keys to field names: "exact" (the default) or
+    // "case_insensitive", which also ignores underscores
+    #[darling(default)]
+    match_fields: Option<SpannedValue<String>>,
+    // Path to the gobx crate in generated code, for a renamed dependency or a re-export
+    #[darling(rename = "crate", default)]
+    crate_path: Option<syn::Path>,
+    // Leaves out to_gob_vec and from_gob_slice, for a type with methods of those names
+    no_convenience: Flag,
 }
 
 impl GobArgs {
-    fn parse_map_types(&self) -> Option<(String, String)> {
-        let interpret_as = self.interpret_as.as_ref()?;
-        
-        // Parse "map[KeyType]ValueType"
-        if !interpret_as.starts_with("map[") {
-            return None;
+    fn gobx(&self) -> syn::Path {
+        self.crate_path.clone().unwrap_or_else(|| syn::parse_quote!(::gobx))
+    }
+}
+
+const GOB_ARG_NAMES: [&str; 8] = ["crate", "id", "interpret_as", "match_fields", "name", "no_convenience", "repr", "rename_all"];
+
+// The Go type in interpret_as, checked against its grammar
+enum InterpretAs {
+    // map[KeyType]ValueType: the type ids of the key and value
+    Map(i64, i64),
+    // []ElemType
+    Slice,
+}
+
+fn parse_interpret_as(s: &str) -> Result<InterpretAs, String> {
+    if let Some(rest) = s.strip_prefix("map[") {
+        // The key ends at the first `]` outside braces, so interface{} keys work.
+        let mut depth = 0;
+        let end = rest.char_indices().find(|&(_, c)| {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            c == ']' && depth == 0
+        });
+        let Some((end, _)) = end else {
+            return Err(format!("interpret_as = \"{}\" has no `]` closing the key type; write map[KeyType]ValueType", s));
+        };
+        let (key, value) = (&rest[..end], &rest[end + 1..]);
+        if value.is_empty() {
+            return Err(format!("interpret_as = \"{}\" has no value type after `]`; write map[KeyType]ValueType", s));
         }
-        
-        let rest = &interpret_as[4..]; // Skip "map["
-        let bracket_pos = rest.find(']')?;
-        let key_type = rest[..bracket_pos].to_string();
-        let value_type = rest[bracket_pos + 1..].to_string();
-        
-        Some((key_type, value_type))
+        let key_id = go_builtin_id(key).ok_or_else(|| unknown_go_type(key))?;
+        let value_id = go_builtin_id(value).ok_or_else(|| unknown_go_type(value))?;
+        if !matches!(key_id, 6 | 8) {
+            return Err(format!("interpret_as map keys are the field names, so the key type must be string or interface{{}}, not {}", key));
+        }
+        return Ok(InterpretAs::Map(key_id, value_id));
+    }
+    if let Some(elem) = s.strip_prefix("[]") {
+        // Element definitions come from the Vec's own type; the name only has to be one.
+        let name_like = |c: char| c.is_alphanumeric() || "_.*[]{}".contains(c);
+        if elem.is_empty() || !elem.chars().all(name_like) {
+            return Err(format!("interpret_as = \"{}\" needs a Go element type after `[]`, e.g. []string", s));
+        }
+        return Ok(InterpretAs::Slice);
+    }
+    Err(format!("interpret_as = \"{}\" is not a Go map or slice type; write map[KeyType]ValueType or []ElemType", s))
+}
+
+fn unknown_go_type(name: &str) -> String {
+    format!("unknown Go type `{}` in interpret_as; use a predeclared type such as string or int64, or interface{{}}", name)
+}
+
+const MATCH_RULES: [&str; 2] = ["exact", "case_insensitive"];
+
+// A map key or field name as case_insensitive matching compares it: lowercase, without
+// underscores, so `UserID`, `userId` and `user_id` are all `userid`.
+fn fold_field_name(name: &str) -> String {
+    name.chars().filter(|&c| c != '_').flat_map(char::to_lowercase).collect()
+}
+
+const RENAME_RULES: [&str; 4] = ["PascalCase", "camelCase", "snake_case", "SCREAMING_SNAKE_CASE"];
+
+// `ident` in the rename_all case convention `rule`. Words are split at underscores and
+// at lower-to-upper case changes, so both field and variant identifiers work.
+fn rename(ident: &str, rule: &str) -> String {
+    let ident = ident.strip_prefix("r#").unwrap_or(ident);
+    let mut words: Vec<String> = Vec::new();
+    let mut prev_lower = false;
+    for c in ident.chars() {
+        if c == '_' {
+            words.push(String::new());
+        } else {
+            if words.is_empty() || (c.is_uppercase() && prev_lower) {
+                words.push(String::new());
+            }
+            words.last_mut().unwrap().extend(c.to_lowercase());
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    words.retain(|w| !w.is_empty());
+    let capitalize = |w: &String| {
+        let mut chars = w.chars();
+        chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+    };
+    match rule {
+        "PascalCase" => words.iter().map(capitalize).collect(),
+        "camelCase" => words.iter().enumerate().map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) }).collect(),
+        "SCREAMING_SNAKE_CASE" => words.join("_").to_uppercase(),
+        _ => words.join("_"),
     }
 }
 
@@ -38,6 +136,472 @@ impl GobArgs {
 struct GobFieldArgs {
     #[darling(default)]
     name: Option<String>,
+    // Field is declared as interface{} on the Go side
+    #[darling(default)]
+    interface: Flag,
+    // Field is a #[Gob] struct whose fields appear inline in this struct's field list
+    #[darling(default)]
+    flatten: Flag,
+    // Value for a field the stream didn't send: `default` for Default::default(),
+    // `default = "path"` to call a function. Go omits zero values, so a field Go sent
+    // as 0/""/false also gets the default.
+    #[darling(default)]
+    default: Option<darling::util::Override<syn::Path>>,
+    // Decoding fails if the stream didn't send the field. Go omits zero values, so a
+    // required field must be nonzero on the Go side.
+    #[darling(default)]
+    required: Flag,
+    // interpret_as map mode: a value of the wrong kind is retried as a number parsed from
+    // a string ("42" for an int field) before it is an error
+    #[darling(default)]
+    lenient: bool,
+    // interpret_as map mode: a BTreeMap<Value, Value> that takes every entry no other field
+    // names, and whose entries are encoded after the named fields
+    #[darling(default)]
+    extra: Flag,
+    // Module with `encode(&T, &mut Encoder<W>)`, `decode(&mut Decoder<R>) -> Result<T>` and
+    // `wire_type_id() -> i64`, used instead of the field type's own gob coding
+    #[darling(default)]
+    with: Option<syn::Path>,
+    // Split forms of `with`: a function for one direction only. The wire type stays the
+    // field type's own.
+    #[darling(default)]
+    encode_with: Option<syn::Path>,
+    #[darling(default)]
+    decode_with: Option<syn::Path>,
+    // A Display + FromStr field (e.g. an i64) that Go holds as a string: sent formatted,
+    // parsed on decode
+    #[darling(default)]
+    as_string: Flag,
+}
+
+// Wire type id of a Go predeclared type named in interpret_as, e.g. "int32" is an int (2).
+fn go_builtin_id(name: &str) -> Option<i64> {
+    match name {
+        "bool" => Some(1),
+        "int" | "int8" | "int16" | "int32" | "int64" => Some(2),
+        "uint" | "uint8" | "uint16" | "uint32" | "uint64" | "uintptr" => Some(3),
+        "float32" | "float64" => Some(4),
+        "[]byte" => Some(5),
+        "string" => Some(6),
+        "interface{}" => Some(8),
+        _ => None,
+    }
+}
+
+// Schema for skipping a value of a predeclared type that no field wants.
+fn builtin_schema(gobx: &syn::Path, id: i64) -> proc_macro2::TokenStream {
+    match id {
+        1 => quote! { #gobx::decode::TypeSchema::Bool },
+        2 => quote! { #gobx::decode::TypeSchema::Int },
+        3 => quote! { #gobx::decode::TypeSchema::Uint },
+        4 => quote! { #gobx::decode::TypeSchema::Float },
+        5 => quote! { #gobx::decode::TypeSchema::ByteSlice },
+        6 => quote! { #gobx::decode::TypeSchema::String },
+        _ => quote! { #gobx::decode::TypeSchema::Interface },
+    }
+}
+
+// `to_gob_vec` and `from_gob_slice` for a struct, unless `no_convenience` is set
+fn convenience_methods(gob_args: &GobArgs) -> proc_macro2::TokenStream {
+    if gob_args.no_convenience.is_present() {
+        return quote! {};
+    }
+    let gobx = gob_args.gobx();
+    quote! {
+        /// Encodes this value as a complete gob stream, type definitions included, that
+        /// Go's `gob.Decoder` reads as is.
+        pub fn to_gob_vec(&self) -> #gobx::Result<Vec<u8>>
+        where Self: #gobx::GobEncodable {
+            #gobx::to_vec_typed(self)
+        }
+
+        /// Decodes the first value in a gob stream, such as one from Go's `gob.Encoder`.
+        pub fn from_gob_slice(bytes: &[u8]) -> #gobx::Result<Self>
+        where Self: #gobx::GobDecodable {
+            #gobx::from_slice_typed(bytes)
+        }
+    }
+}
+
+// Wire id of a Rust type that always goes out as one of gob's predefined types, when its
+// path leaves no doubt about which type it is; 0 for anything else. A macro only sees names:
+// a user type called `String` or an alias for `u64` could be sent as anything.
+fn predefined_wire_id(ty: &syn::Type) -> i64 {
+    let syn::Type::Path(path) = ty else { return 0 };
+    if path.qself.is_some() || path.path.segments.iter().any(|s| !s.arguments.is_empty()) {
+        return 0;
+    }
+    let segments: Vec<String> = path.path.segments.iter().map(|s| s.ident.to_string()).collect();
+    match segments.join("::").as_str() {
+        "bool" => 1,
+        "i8" | "i16" | "i32" | "i64" | "isize" | "char" => 2,
+        "u8" | "u16" | "u32" | "u64" | "usize" => 3,
+        "f32" | "f64" => 4,
+        "std::string::String" | "alloc::string::String" => 6,
+        // std::time::Duration goes out as Go's time.Duration, an int of nanoseconds.
+        // A bare `Duration` may be chrono's or the user's own.
+        "std::time::Duration" | "core::time::Duration" => 2,
+        _ => 0,
+    }
+}
+
+// `Option<T>` fields stand for Go pointers: None is left off the wire.
+fn is_option(ty: &syn::Type) -> bool {
+    option_inner(ty).is_some()
+}
+
+// The T of an `Option<T>` field.
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else { return None };
+    if path.qself.is_some() {
+        return None;
+    }
+    let seg = path.path.segments.last().filter(|seg| seg.ident == "Option")?;
+    let syn::PathArguments::AngleBracketed(args) = &seg.arguments else { return None };
+    match args.args.first() {
+        Some(syn::GenericArgument::Type(inner)) => Some(inner),
+        _ => None,
+    }
+}
+
+// Whether `tokens` names any of the struct's type parameters, so a field of that type
+// needs where-clause bounds.
+fn mentions_params(tokens: proc_macro2::TokenStream, params: &[syn::Ident]) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        proc_macro2::TokenTree::Ident(ident) => params.contains(&ident),
+        proc_macro2::TokenTree::Group(group) => mentions_params(group.stream(), params),
+        _ => false,
+    })
+}
+
+// Struct-level #[gob(...)] attributes, written below #[Gob]
+#[derive(Debug, FromMeta)]
+struct GobStructArgs {
+    // Encoded as one opaque byte slice through gobx::GobSelfEncoder, like a Go GobEncoder
+    #[darling(default)]
+    self_encoding: bool,
+    // Where-clause predicates for the generated impls, replacing the ones inferred from
+    // the fields of a generic struct
+    #[darling(default)]
+    bound: Option<String>,
+}
+
+const STRUCT_ARG_NAMES: [&str; 2] = ["self_encoding", "bound"];
+
+// The arguments of the #[gob(...)] attributes on the item itself.
+fn item_gob_args(attrs: &[syn::Attribute]) -> syn::Result<Vec<NestedMeta>> {
+    let mut args = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("gob")) {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        args.extend(metas.into_iter().map(NestedMeta::Meta));
+    }
+    Ok(args)
+}
+
+// A self-encoding struct is a byte slice on the wire; its fields are its own business.
+fn expand_self_encoding(item: &DeriveInput, gob_args: &GobArgs) -> proc_macro2::TokenStream {
+    let gobx = gob_args.gobx();
+    let convenience = convenience_methods(gob_args);
+    let struct_name = &item.ident;
+    let type_id = gob_args.id.unwrap_or(0);
+    let go_name = gob_args.name.clone().unwrap_or_else(|| struct_name.to_string());
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    quote! {
+        impl #impl_generics #gobx::GobType for #struct_name #ty_generics #where_clause {
+            const ID: i64 = #type_id;
+        }
+
+        impl #impl_generics #gobx::GobDecodable for #struct_name #ty_generics #where_clause {
+            fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                let bytes = decoder.read_bytes()?;
+                <Self as #gobx::GobSelfEncoder>::gob_decode(&bytes)
+            }
+        }
+
+        impl #impl_generics #gobx::GobEncodable for #struct_name #ty_generics #where_clause {
+            fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                let bytes = #gobx::GobSelfEncoder::gob_encode(self)?;
+                encoder.write_bytes(&bytes)
+            }
+            fn type_id(&self) -> i64 { #type_id }
+            fn type_name(&self) -> &'static str { #go_name }
+            fn write_type_definition<W: std::io::Write>(writer: &mut #gobx::GobWriter<W>) -> std::io::Result<i64> {
+                writer.define_self_encoded::<Self>()
+            }
+        }
+
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            pub fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                #gobx::GobEncodable::encode(self, encoder)
+            }
+
+            pub fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                #gobx::GobDecodable::decode(decoder)
+            }
+
+            #convenience
+        }
+    }
+}
+
+// `interpret_as = "[]Elem"`: a struct wrapping one Vec is that Go slice on the wire, e.g.
+// `struct Tags(Vec<String>)` for a `[]string` message.
+fn expand_slice(item: &DeriveInput, gob_args: &GobArgs) -> proc_macro2::TokenStream {
+    let gobx = gob_args.gobx();
+    let struct_name = &item.ident;
+    let interpret_as = gob_args.interpret_as.as_ref().map(|s| s.as_str()).unwrap_or_default();
+    let fields = match &item.data {
+        Data::Struct(data) => &data.fields,
+        _ => return syn::Error::new_spanned(struct_name, "interpret_as = \"[]...\" is only supported on structs").to_compile_error(),
+    };
+    let field = match fields.iter().collect::<Vec<_>>()[..] {
+        [field] => field,
+        _ => return syn::Error::new_spanned(fields, "interpret_as = \"[]...\" needs a struct with exactly one field").to_compile_error(),
+    };
+    let is_vec = matches!(&field.ty, syn::Type::Path(path) if path.qself.is_none()
+        && path.path.segments.last().is_some_and(|seg| seg.ident == "Vec"));
+    if !is_vec {
+        return syn::Error::new_spanned(&field.ty, "interpret_as = \"[]...\" needs the field to be a Vec").to_compile_error();
+    }
+
+    let ty = &field.ty;
+    let (access, construct) = match &field.ident {
+        Some(ident) => (quote! { self.#ident }, quote! { Self { #ident: items } }),
+        None => (quote! { self.0 }, quote! { Self(items) }),
+    };
+    let type_id = gob_args.id.unwrap_or(0);
+    let convenience = convenience_methods(gob_args);
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    quote! {
+        impl #impl_generics #gobx::GobType for #struct_name #ty_generics #where_clause {
+            const ID: i64 = #type_id;
+        }
+
+        impl #impl_generics #gobx::GobDecodable for #struct_name #ty_generics #where_clause {
+            fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                let items = <#ty as #gobx::GobDecodable>::decode(decoder)?;
+                Ok(#construct)
+            }
+        }
+
+        impl #impl_generics #gobx::GobEncodable for #struct_name #ty_generics #where_clause {
+            fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                #gobx::GobEncodable::encode(&#access, encoder)
+            }
+            fn type_id(&self) -> i64 { #gobx::GobEncodable::type_id(&#access) }
+            fn type_name(&self) -> &'static str { #interpret_as }
+            fn write_type_definition<W: std::io::Write>(writer: &mut #gobx::GobWriter<W>) -> std::io::Result<i64> {
+                <#ty as #gobx::GobEncodable>::write_type_definition(writer)
+            }
+        }
+
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            pub fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                #gobx::GobEncodable::encode(self, encoder)
+            }
+
+            pub fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                #gobx::GobDecodable::decode(decoder)
+            }
+
+            /// Writes this slice to `writer` as a complete message, sending its sliceType
+            /// definition first if needed. Returns the bytes written.
+            pub fn encode_message<W: std::io::Write>(&self, writer: &mut #gobx::GobWriter<W>) -> std::io::Result<usize> {
+                writer.encode_typed(self)
+            }
+
+            #convenience
+        }
+    }
+}
+
+// #[gob(...)] on an enum variant
+#[derive(Debug, FromAttributes)]
+#[darling(attributes(gob))]
+struct GobVariantArgs {
+    // String sent for the variant under repr = "string"; defaults to the variant name
+    #[darling(default)]
+    name: Option<String>,
+    // Catch-all for discriminants no other variant has; decoding fails on them otherwise
+    #[darling(default)]
+    other: bool,
+}
+
+// A fieldless enum stands for a Go `type Status int` and its constants: on the wire it is
+// the discriminant as an int, or with repr = "string" (`type Role string`) the variant's name.
+fn expand_enum(item: &mut DeriveInput, gob_args: &GobArgs) -> proc_macro2::TokenStream {
+    let gobx = gob_args.gobx();
+    let enum_name = item.ident.clone();
+    let string_repr = match gob_args.repr.as_ref() {
+        None => false,
+        Some(repr) if repr.as_str() == "int" => false,
+        Some(repr) if repr.as_str() == "string" => true,
+        Some(other) => {
+            return syn::Error::new(other.span(), format!("unknown repr \"{}\", expected \"int\" or \"string\"", other.as_str())).to_compile_error();
+        }
+    };
+    let go_name = gob_args.name.clone().unwrap_or_else(|| if string_repr { "string" } else { "int" }.to_string());
+    let Data::Enum(data) = &mut item.data else { unreachable!() };
+
+    let mut variants = Vec::new();
+    let mut names = Vec::new();
+    let mut other = None;
+    for variant in data.variants.iter_mut() {
+        let (gob_attrs, other_attrs): (Vec<_>, Vec<_>) = variant.attrs.iter().cloned().partition(|attr| {
+            attr.path().is_ident("gob")
+        });
+        variant.attrs = other_attrs;
+        let args = match GobVariantArgs::from_attributes(&gob_attrs) {
+            Ok(args) => args,
+            Err(e) => return e.write_errors(),
+        };
+        if !matches!(variant.fields, Fields::Unit) {
+            return syn::Error::new_spanned(&variant.ident, "#[Gob] enums must be fieldless").to_compile_error();
+        }
+        if args.other {
+            if other.is_some() {
+                return syn::Error::new_spanned(&variant.ident, "only one variant can be #[gob(other)]").to_compile_error();
+            }
+            other = Some(variant.ident.clone());
+        }
+        names.push(args.name.unwrap_or_else(|| match &gob_args.rename_all {
+            Some(rule) => rename(&variant.ident.to_string(), rule.as_str()),
+            None => variant.ident.to_string(),
+        }));
+        variants.push(variant.ident.clone());
+    }
+    if string_repr {
+        if let Some(dup) = names.iter().enumerate().find_map(|(i, n)| names[..i].contains(n).then_some(n)) {
+            return syn::Error::new_spanned(&enum_name, format!("two variants are named \"{}\"", dup)).to_compile_error();
+        }
+        return expand_string_enum(&gobx, &enum_name, &go_name, &variants, &names, other.as_ref());
+    }
+
+    let unknown = match &other {
+        Some(other) => quote! { Ok(#enum_name::#other) },
+        None => quote! {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} is not a {} value", v, stringify!(#enum_name))))
+        },
+    };
+
+    quote! {
+        impl #gobx::GobType for #enum_name {
+            const ID: i64 = 2;
+        }
+
+        impl #enum_name {
+            /// Maps a wire value to its variant.
+            #[doc(hidden)]
+            pub fn gob_from_int(v: i64) -> std::io::Result<Self> {
+                #( if v == #enum_name::#variants as i64 { return Ok(#enum_name::#variants); } )*
+                #unknown
+            }
+
+            pub fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                #gobx::GobEncodable::encode(self, encoder)
+            }
+
+            pub fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                #gobx::GobDecodable::decode(decoder)
+            }
+        }
+
+        impl #gobx::GobEncodable for #enum_name {
+            fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                let v = match self {
+                    #( #enum_name::#variants => #enum_name::#variants as i64, )*
+                };
+                encoder.write_int(v)
+            }
+            fn type_id(&self) -> i64 { 2 }
+            fn type_name(&self) -> &'static str { #go_name }
+            fn write_type_definition<W: std::io::Write>(_: &mut #gobx::GobWriter<W>) -> std::io::Result<i64> { Ok(2) }
+        }
+
+        impl #gobx::GobDecodable for #enum_name {
+            fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                Self::gob_from_int(decoder.read_int()?)
+            }
+        }
+
+        impl std::convert::TryFrom<#gobx::Value> for #enum_name {
+            type Error = std::io::Error;
+            fn try_from(v: #gobx::Value) -> std::io::Result<Self> {
+                Self::gob_from_int(i64::try_from(v)?)
+            }
+        }
+    }
+}
+
+fn expand_string_enum(
+    gobx: &syn::Path,
+    enum_name: &syn::Ident,
+    go_name: &str,
+    variants: &[syn::Ident],
+    names: &[String],
+    other: Option<&syn::Ident>,
+) -> proc_macro2::TokenStream {
+    let unknown = match other {
+        Some(other) => quote! { Ok(#enum_name::#other) },
+        None => quote! {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:?} is not a {} value", v, stringify!(#enum_name))))
+        },
+    };
+
+    quote! {
+        impl #gobx::GobType for #enum_name {
+            const ID: i64 = 6;
+        }
+
+        impl #enum_name {
+            /// The string sent for this variant.
+            pub fn gob_str(&self) -> &'static str {
+                match self {
+                    #( #enum_name::#variants => #names, )*
+                }
+            }
+
+            /// Maps a wire string to its variant.
+            #[doc(hidden)]
+            pub fn gob_from_str(v: &str) -> std::io::Result<Self> {
+                match v {
+                    #( #names => Ok(#enum_name::#variants), )*
+                    _ => #unknown,
+                }
+            }
+
+            pub fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                #gobx::GobEncodable::encode(self, encoder)
+            }
+
+            pub fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                #gobx::GobDecodable::decode(decoder)
+            }
+        }
+
+        impl #gobx::GobEncodable for #enum_name {
+            fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                encoder.write_string(self.gob_str())
+            }
+            fn type_id(&self) -> i64 { 6 }
+            fn type_name(&self) -> &'static str { #go_name }
+            fn write_type_definition<W: std::io::Write>(_: &mut #gobx::GobWriter<W>) -> std::io::Result<i64> { Ok(6) }
+        }
+
+        impl #gobx::GobDecodable for #enum_name {
+            fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
+                Self::gob_from_str(&decoder.read_string()?)
+            }
+        }
+
+        impl std::convert::TryFrom<#gobx::Value> for #enum_name {
+            type Error = std::io::Error;
+            fn try_from(v: #gobx::Value) -> std::io::Result<Self> {
+                Self::gob_from_str(&String::try_from(v)?)
+            }
+        }
+    }
 }
 
 #[proc_macro_attribute]
@@ -54,10 +618,126 @@ pub fn Gob(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
 
+    let (struct_attrs, other_attrs): (Vec<_>, Vec<_>) = item.attrs.iter().cloned().partition(|attr| {
+        attr.path().is_ident("gob")
+    });
+    item.attrs = other_attrs;
+    let struct_args = match item_gob_args(&struct_attrs) {
+        Ok(metas) => match GobStructArgs::from_list(&metas) {
+            Ok(v) => v,
+            Err(e) => return TokenStream::from(e.write_errors()),
+        },
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+
+    let impls = expand(&mut item, &gob_args, &struct_args);
+    // The item is re-emitted, so it must not keep the #[gob] attributes nothing else declares.
+    strip_gob_attrs(&mut item);
+    TokenStream::from(quote! {
+        #item
+
+        #impls
+    })
+}
+
+/// `#[derive(Gob)]`, exported as `gobx::derive::Gob`: the same impls as the `#[Gob]`
+/// attribute, with the container arguments in a `#[gob(...)]` on the item.
+#[proc_macro_derive(GobDerive, attributes(gob))]
+pub fn derive_gob(input: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(input as DeriveInput);
+    // One #[gob(...)] carries what the attribute form splits between #[Gob(...)] and #[gob(...)].
+    let metas = match item_gob_args(&item.attrs) {
+        Ok(metas) => metas,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    // Checked here so the message lists every key, not just those of one half.
+    for meta in &metas {
+        let NestedMeta::Meta(meta) = meta else { continue };
+        if !GOB_ARG_NAMES.iter().chain(&STRUCT_ARG_NAMES).any(|name| meta.path().is_ident(name)) {
+            let known = GOB_ARG_NAMES.iter().chain(&STRUCT_ARG_NAMES).map(|name| format!("`{}`", name)).collect::<Vec<_>>();
+            let msg = format!("unknown #[gob] key; expected one of {}", known.join(", "));
+            return TokenStream::from(syn::Error::new_spanned(meta.path(), msg).to_compile_error());
+        }
+    }
+    let (struct_metas, gob_metas): (Vec<_>, Vec<_>) = metas.into_iter().partition(|meta| {
+        matches!(meta, NestedMeta::Meta(meta) if STRUCT_ARG_NAMES.iter().any(|name| meta.path().is_ident(name)))
+    });
+    let args = GobArgs::from_list(&gob_metas).and_then(|gob| Ok((gob, GobStructArgs::from_list(&struct_metas)?)));
+    match args {
+        Ok((gob_args, struct_args)) => TokenStream::from(expand(&mut item, &gob_args, &struct_args)),
+        Err(e) => TokenStream::from(e.write_errors()),
+    }
+}
+
+fn strip_gob_attrs(item: &mut DeriveInput) {
+    let not_gob = |attr: &syn::Attribute| !attr.path().is_ident("gob");
+    item.attrs.retain(not_gob);
+    match &mut item.data {
+        Data::Struct(data) => data.fields.iter_mut().for_each(|f| f.attrs.retain(not_gob)),
+        Data::Enum(data) => data.variants.iter_mut().for_each(|v| v.attrs.retain(not_gob)),
+        Data::Union(_) => {}
+    }
+}
+
+// The generated impls for `item`, which has its #[gob] field and variant attributes
+// removed as they are read.
+fn expand(item: &mut DeriveInput, gob_args: &GobArgs, struct_args: &GobStructArgs) -> proc_macro2::TokenStream {
+    let gobx = gob_args.gobx();
+    if let Some(rule) = gob_args.rename_all.as_ref().filter(|r| !RENAME_RULES.contains(&r.as_str())) {
+        let msg = format!("unknown rename_all \"{}\", expected one of {}", rule.as_str(), RENAME_RULES.join(", "));
+        return syn::Error::new(rule.span(), msg).to_compile_error();
+    }
+    let interpret_as = match gob_args.interpret_as.as_ref().map(|s| parse_interpret_as(s).map_err(|msg| syn::Error::new(s.span(), msg))) {
+        Some(Ok(parsed)) => Some(parsed),
+        Some(Err(e)) => return e.to_compile_error(),
+        None => None,
+    };
+    if let Some(rule) = &gob_args.match_fields {
+        if !MATCH_RULES.contains(&rule.as_str()) {
+            let msg = format!("unknown match_fields \"{}\", expected \"exact\" or \"case_insensitive\"", rule.as_str());
+            return syn::Error::new(rule.span(), msg).to_compile_error();
+        }
+        if !matches!(interpret_as, Some(InterpretAs::Map(..))) {
+            return syn::Error::new(rule.span(), "match_fields only applies to interpret_as = \"map[...]\"").to_compile_error();
+        }
+    }
+    let fold_keys = gob_args.match_fields.as_ref().is_some_and(|rule| rule.as_str() == "case_insensitive");
+    let convenience = convenience_methods(gob_args);
+
+    if struct_args.self_encoding {
+        return expand_self_encoding(item, gob_args);
+    }
+    if matches!(item.data, Data::Enum(_)) {
+        return expand_enum(item, gob_args);
+    }
+    if matches!(interpret_as, Some(InterpretAs::Slice)) {
+        return expand_slice(item, gob_args);
+    }
+
     let mut encode_fields = Vec::new();
     let mut decode_fields = Vec::new();
     let mut map_decode_fields = Vec::new();
     let mut map_encode_fields = Vec::new(); // For map-based encoding (fields sorted by key)
+    let mut field_types = Vec::new(); // (Go name, type id) pushes, in wire order
+    let mut gob_fields = Vec::new(); // GOB_FIELDS entries for the struct's own fields
+    let mut default_fields = Vec::new(); // Struct mode: defaults for unseen field numbers
+    let mut map_default_fields = Vec::new(); // Map mode: defaults for unseen keys
+    let mut required_fields = Vec::new(); // Struct mode: checks for unseen required field numbers
+    let mut map_required = Vec::new(); // Map mode: keys of required fields
+    let mut folded_keys: Vec<(String, syn::Ident)> = Vec::new(); // Map mode: case_insensitive keys so far
+    let mut field_count = quote! { 0 };
+    // Where-clause predicates for fields whose types use the struct's type parameters
+    let type_params: Vec<syn::Ident> = item.generics.type_params().map(|p| p.ident.clone()).collect();
+    let interpret_as_map = matches!(interpret_as, Some(InterpretAs::Map(..)));
+    let struct_ident = item.ident.clone();
+    let mut field_bounds = Vec::new();
+    let mut extra_field: Option<syn::Ident> = None;
+    // Map mode: keys are the field names, sent as strings or as interfaces holding them;
+    // values are interface-wrapped for interface{} and written directly otherwise.
+    let (map_key_id, map_value_id) = match interpret_as {
+        Some(InterpretAs::Map(key_id, value_id)) => (key_id, value_id),
+        _ => (8, 8),
+    };
     
     if let Data::Struct(ref mut data) = item.data {
         if let Fields::Named(ref mut fields) = data.fields {
@@ -65,71 +745,362 @@ pub fn Gob(args: TokenStream, input: TokenStream) -> TokenStream {
             struct FieldInfo {
                 name: String,
                 ident: syn::Ident,
+                as_string: bool,
             }
             let mut sorted_fields = Vec::new();
 
-            for (index, field) in fields.named.iter_mut().enumerate() {
+            // A field's wire number is the count of fields before it, where a flattened
+            // field counts as all of its own fields: #own_fields + Flat::GOB_FIELD_COUNT + ...
+            let mut own_fields = 0i64;
+            let mut flatten_types: Vec<syn::Type> = Vec::new();
+
+            for field in fields.named.iter_mut() {
                 let (gob_attrs, other_attrs): (Vec<_>, Vec<_>) = field.attrs.iter().cloned().partition(|attr| {
                     attr.path().is_ident("gob")
                 });
                 
                 field.attrs = other_attrs;
 
-                // Default field name is the struct field name
+                // Default field name is the struct field name, in the rename_all convention if any
                 let field_ident = field.ident.as_ref().unwrap();
-                let mut field_name_str = field_ident.to_string(); 
-                
-                // Check if we have a custom name
-                if !gob_attrs.is_empty() {
-                    if let Ok(args) = GobFieldArgs::from_attributes(&gob_attrs) {
-                         if let Some(name) = args.name {
-                             field_name_str = name;
-                         }
-                    } else if let Err(e) = GobFieldArgs::from_attributes(&gob_attrs) {
-                        return TokenStream::from(e.write_errors());
+                let mut field_name_str = match &gob_args.rename_all {
+                    Some(rule) => rename(&field_ident.to_string(), rule.as_str()),
+                    None => field_ident.to_string(),
+                };
+                let field_args = match GobFieldArgs::from_attributes(&gob_attrs) {
+                    Ok(args) => args,
+                    Err(e) => return e.write_errors(),
+                };
+
+                let offset = quote! { #own_fields #( + <#flatten_types>::GOB_FIELD_COUNT )* };
+
+                if field_args.required.is_present() && (field_args.flatten.is_present() || field_args.extra.is_present()) {
+                    return syn::Error::new(field_args.required.span(), "#[gob(required)] goes on named fields, not on flatten or extra ones").to_compile_error();
+                }
+                if field_args.flatten.is_present() {
+                    if field_args.interface.is_present() {
+                        return syn::Error::new(field_args.interface.span(), "#[gob(flatten)] cannot be combined with #[gob(interface)]").to_compile_error();
+                    }
+                    if gob_args.interpret_as.is_some() {
+                        return syn::Error::new(field_args.flatten.span(), "#[gob(flatten)] is only supported for structs without interpret_as").to_compile_error();
+                    }
+                    let ty = &field.ty;
+                    encode_fields.push(quote! {
+                        self.#field_ident.gob_encode_fields(encoder, base + #offset, last_field_num)?;
+                    });
+                    decode_fields.push(quote! {
+                        if field_num >= base + #offset && field_num < base + #offset + <#ty>::GOB_FIELD_COUNT {
+                            return self.#field_ident.gob_decode_field(decoder, field_num, base + #offset);
+                        }
+                    });
+                    field_types.push(quote! {
+                        <#ty>::gob_field_types(writer, out)?;
+                    });
+                    default_fields.push(quote! {
+                        self.#field_ident.gob_apply_defaults(seen, base + #offset);
+                    });
+                    required_fields.push(quote! {
+                        <#ty>::gob_missing_required(seen, base + #offset, missing);
+                    });
+                    flatten_types.push(ty.clone());
+                    continue;
+                }
+                if field_args.extra.is_present() {
+                    if !interpret_as_map {
+                        return syn::Error::new(field_args.extra.span(), "#[gob(extra)] is only supported with interpret_as = \"map[...]\"").to_compile_error();
+                    }
+                    if extra_field.is_some() {
+                        return syn::Error::new(field_args.extra.span(), "only one field can be #[gob(extra)]").to_compile_error();
                     }
+                    extra_field = Some(field_ident.clone());
+                    continue;
+                }
+                own_fields += 1;
+
+                let custom_coding = field_args.with.is_some() || field_args.encode_with.is_some() || field_args.decode_with.is_some();
+                let coding_path = field_args.with.as_ref().or(field_args.encode_with.as_ref()).or(field_args.decode_with.as_ref());
+                if let (Some(with), Some(split)) = (&field_args.with, field_args.encode_with.as_ref().or(field_args.decode_with.as_ref())) {
+                    let mut err = syn::Error::new_spanned(split, "#[gob(with)] cannot be combined with encode_with or decode_with");
+                    err.combine(syn::Error::new_spanned(with, "`with` given here"));
+                    return err.to_compile_error();
+                }
+                if let Some(path) = coding_path.filter(|_| field_args.interface.is_present() || interpret_as_map) {
+                    return syn::Error::new_spanned(path, "#[gob(with)], encode_with and decode_with are only supported on struct fields that are not interfaces").to_compile_error();
+                }
+                let as_string = field_args.as_string.is_present();
+                if as_string {
+                    let span = field_args.as_string.span();
+                    if custom_coding || field_args.interface.is_present() {
+                        return syn::Error::new(span, "#[gob(as_string)] cannot be combined with interface, with, encode_with or decode_with").to_compile_error();
+                    }
+                    if is_option(&field.ty) {
+                        return syn::Error::new(span, "#[gob(as_string)] is not supported on Option fields").to_compile_error();
+                    }
+                    if interpret_as_map && map_value_id != 6 && map_value_id != 8 {
+                        return syn::Error::new(span, "#[gob(as_string)] needs string or interface{} map values").to_compile_error();
+                    }
+                }
+
+                // Check if we have a custom name
+                if let Some(name) = field_args.name {
+                    field_name_str = name;
                 }
                 
                 // Collect for sorted map encoding
                 sorted_fields.push(FieldInfo {
                     name: field_name_str.clone(),
                     ident: field_ident.clone(),
+                    as_string,
+                });
+
+                let type_id_expr = if field_args.interface.is_present() {
+                    quote! { 8 }
+                } else if as_string {
+                    quote! { 6 }
+                } else if let Some(with) = &field_args.with {
+                    quote! { #with::wire_type_id() }
+                } else {
+                    let ty = &field.ty;
+                    quote! { <#ty as #gobx::GobEncodable>::write_type_definition(writer)? }
+                };
+                // Only a predefined wire type has an id that every stream agrees on.
+                let static_id = if field_args.interface.is_present() {
+                    8
+                } else if as_string {
+                    6
+                } else if custom_coding {
+                    0
+                } else {
+                    predefined_wire_id(option_inner(&field.ty).unwrap_or(&field.ty))
+                };
+                let field_index = gob_fields.len();
+                gob_fields.push(quote! {
+                    #gobx::types::FieldType { name: std::borrow::Cow::Borrowed(#field_name_str), id: #static_id }
+                });
+                field_types.push(quote! {
+                    out.push((Self::GOB_FIELDS[#field_index].name.to_string(), #type_id_expr));
                 });
 
+                // Custom coding gets the whole field, Option included.
+                let optional = is_option(&field.ty) && !custom_coding;
+                let ty = &field.ty;
+                if mentions_params(quote! { #ty }, &type_params) && !custom_coding && !as_string {
+                    // Interface and map-mode values are converted from a gobx::Value, and an
+                    // Option's inner value is what gets encoded.
+                    let target = option_inner(ty).unwrap_or(ty);
+                    if field_args.interface.is_present() || (interpret_as_map && map_value_id == 8) {
+                        field_bounds.push(quote! { #ty: #gobx::GobEncodable });
+                        field_bounds.push(quote! { #target: #gobx::GobEncodable + std::convert::TryFrom<#gobx::Value> });
+                        field_bounds.push(quote! {
+                            <#target as std::convert::TryFrom<#gobx::Value>>::Error: Into<Box<dyn std::error::Error + Send + Sync>>
+                        });
+                    } else {
+                        field_bounds.push(quote! { #ty: #gobx::GobEncodable + #gobx::GobDecodable });
+                        field_bounds.push(quote! { #target: #gobx::GobEncodable });
+                    }
+                }
+                let value_expr = if optional { quote! { value } } else { quote! { &self.#field_ident } };
+
                 // Generate encode logic for this field
-                let field_num = (index + 1) as u64;
-                
-                encode_fields.push(quote! {
+                let encode_value = if field_args.interface.is_present() {
+                    // Name + type id + length + value, as for an interface{} field in Go
+                    quote! { #gobx::encode_as_interface(#value_expr, encoder)?; }
+                } else if let Some(with) = &field_args.with {
+                    quote! { #with::encode(#value_expr, encoder)?; }
+                } else if let Some(encode_with) = &field_args.encode_with {
+                    quote! { #encode_with(#value_expr, encoder)?; }
+                } else if as_string {
+                    quote! { encoder.write_string(&std::string::ToString::to_string(#value_expr))?; }
+                } else {
+                    quote! { #gobx::GobEncodable::encode(#value_expr, encoder)?; }
+                };
+                let encode_field = quote! {
                     // Field delta: current field num - last field num. 
-                    encoder.write_uint(#field_num - last_field_num)?; 
-                    last_field_num = #field_num;
+                    let field_num = base + #offset;
+                    encoder.write_uint((field_num - *last_field_num) as u64)?; 
+                    *last_field_num = field_num;
                     
                     // Encode value
-                    gobx::GobEncodable::encode(&self.#field_ident, encoder)?;
+                    #encode_value
+                };
+                // Go leaves a false bool out of the struct, as it does any zero value. A bool
+                // with a default or marked required is always sent, since a missing field
+                // means something else to the decoder.
+                let plain_bool = matches!(ty, syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident("bool"))
+                    && !field_args.interface.is_present() && !custom_coding && !as_string
+                    && field_args.default.is_none() && !field_args.required.is_present();
+                encode_fields.push(if optional {
+                    quote! { if let Some(value) = &self.#field_ident { #encode_field } }
+                } else if plain_bool {
+                    quote! { if self.#field_ident { #encode_field } }
+                } else {
+                    encode_field
                 });
 
                 // Generate decode logic for this field (Struct mode)
-                let field_num_i64 = field_num as i64;
+                // The decode loop starts at -1, so the first field is index 0.
+                let decode_value = if field_args.interface.is_present() && optional {
+                    // A nil interface is the one way Go can send "no value" for it.
+                    quote! {
+                        decoder.decode_interface().and_then(|val| match val {
+                            #gobx::Value::Nil => Ok(None),
+                            val => std::convert::TryFrom::try_from(val).map(Some)
+                                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+                        })
+                    }
+                } else if field_args.interface.is_present() {
+                    quote! {
+                        decoder.decode_interface().and_then(|val| std::convert::TryFrom::try_from(val)
+                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+                    }
+                } else if let Some(with) = &field_args.with {
+                    quote! { #with::decode(decoder) }
+                } else if let Some(decode_with) = &field_args.decode_with {
+                    quote! { #decode_with(decoder) }
+                } else if as_string {
+                    quote! {
+                        decoder.read_string().and_then(|s| #gobx::decode::parse_gob_string(&s)).map_err(|e| std::io::Error::new(
+                            e.kind(), format!("{} field {}: {}", stringify!(#struct_ident), #field_name_str, e)))
+                    }
+                } else {
+                    quote! { #gobx::GobDecodable::decode(decoder) }
+                };
+                // The field's wire type has to be the one GOB_FIELDS gives it, if any.
+                let check_type = if interpret_as_map {
+                    quote! {}
+                } else {
+                    quote! {
+                        decoder.check_field_type(field_num, Self::GOB_FIELDS[#field_index].id, stringify!(#struct_ident), #field_name_str)?;
+                    }
+                };
                 decode_fields.push(quote! {
-                     #field_num_i64 => {
-                         let val = gobx::GobDecodable::decode(decoder)?;
-                         result.#field_ident = val;
+                     if field_num == base + #offset {
+                         #check_type
+                         decoder.enter_field(field_num)?;
+                         let decoded = #decode_value;
+                         decoder.exit_field();
+                         self.#field_ident = decoded?;
+                         return Ok(true);
                      }
                 });
                 
-                // Generate decode logic for this field (Map mode)
-                map_decode_fields.push(quote! {
-                    #field_name_str => {
-                        if let Ok(v) = std::convert::TryInto::try_into(value_val.clone()) {
-                             result.#field_ident = v;
-                        } else {
-                            // Try harder? e.g. Uint to Int cast
-                             // For now, simple TryInto.
+                if field_args.required.is_present() {
+                    if field_args.default.is_some() {
+                        return syn::Error::new(field_args.required.span(), "#[gob(required)] cannot be combined with #[gob(default)]").to_compile_error();
+                    }
+                    required_fields.push(quote! {
+                        if !seen.contains(&(base + #offset)) {
+                            missing.push(#field_name_str);
+                        }
+                    });
+                    map_required.push(field_name_str.clone());
+                }
+
+                if let Some(default) = &field_args.default {
+                    let default_expr = match default {
+                        darling::util::Override::Explicit(path) => quote! { #path() },
+                        darling::util::Override::Inherit => quote! { std::default::Default::default() },
+                    };
+                    default_fields.push(quote! {
+                        if !seen.contains(&(base + #offset)) {
+                            self.#field_ident = #default_expr;
+                        }
+                    });
+                    map_default_fields.push(quote! {
+                        if !seen.contains(&#field_name_str) {
+                            result.#field_ident = #default_expr;
+                        }
+                    });
+                }
+
+                // Generate decode logic for this field (Map mode). A value that doesn't
+                // convert to the field's type is an error; TryFrom already accepts an
+                // in-range Uint for an Int and vice versa.
+                let target = option_inner(ty).unwrap_or(ty);
+                let retry = if field_args.lenient {
+                    quote! { .or_else(|_| std::convert::TryInto::try_into(value_val.clone().parse_numeric())) }
+                } else {
+                    quote! {}
+                };
+                let assign = if optional { quote! { Some(v) } } else { quote! { v } };
+                let nil_is_none = if optional {
+                    // A nil pointer or interface
+                    quote! { if matches!(value_val, #gobx::Value::Nil) { result.#field_ident = None; seen.push(#field_name_str); continue; } }
+                } else {
+                    quote! {}
+                };
+                // The key this field's match arm compares against
+                let key_pattern = if fold_keys {
+                    let folded = fold_field_name(&field_name_str);
+                    if let Some((_, other)) = folded_keys.iter().find(|(key, _)| *key == folded) {
+                        let msg = format!("fields `{}` and `{}` both match the key \"{}\" under match_fields = \"case_insensitive\"", other, field_ident, folded);
+                        return syn::Error::new_spanned(field_ident, msg).to_compile_error();
+                    }
+                    folded_keys.push((folded.clone(), field_ident.clone()));
+                    folded
+                } else {
+                    field_name_str.clone()
+                };
+                // A string parse failure names the field, like a conversion failure.
+                let parse_string = quote! {
+                    #gobx::decode::parse_gob_string(&s).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData,
+                        format!("{} field {}: {}", stringify!(#struct_ident), #field_name_str, e)))?
+                };
+                map_decode_fields.push(if as_string && map_value_id == 8 {
+                    quote! {
+                        #key_pattern => {
+                            let value_val = read_value(decoder)?;
+                            let #gobx::Value::String(s) = value_val else {
+                                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+                                    "{} field {}: expected a string, got {}",
+                                    stringify!(#struct_ident), #field_name_str, value_val.kind())));
+                            };
+                            result.#field_ident = #parse_string;
+                            seen.push(#field_name_str);
+                        }
+                    }
+                } else if as_string {
+                    quote! {
+                        #key_pattern => {
+                            let s = decoder.read_string()?;
+                            result.#field_ident = #parse_string;
+                            seen.push(#field_name_str);
+                        }
+                    }
+                } else if map_value_id == 8 {
+                    quote! {
+                        #key_pattern => {
+                            let value_val = read_value(decoder)?;
+                            #nil_is_none
+                            let converted: Result<#target, _> = std::convert::TryInto::try_into(value_val.clone()) #retry;
+                            match converted {
+                                Ok(v) => {
+                                    result.#field_ident = #assign;
+                                    seen.push(#field_name_str);
+                                }
+                                Err(_) => {
+                                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+                                        "{} field {}: expected {}, got {}",
+                                        stringify!(#struct_ident), #field_name_str, stringify!(#target), value_val.kind())));
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    quote! {
+                        #key_pattern => {
+                            enter_value(decoder)?;
+                            let decoded = #gobx::GobDecodable::decode(decoder);
+                            decoder.exit_field();
+                            result.#field_ident = decoded?;
+                            seen.push(#field_name_str);
                         }
                     }
                 });
             }
             
+            field_count = quote! { #own_fields #( + <#flatten_types>::GOB_FIELD_COUNT )* };
+
             // Sort fields by name for consistent map encoding
             sorted_fields.sort_by(|a, b| a.name.cmp(&b.name));
             
@@ -137,62 +1108,90 @@ pub fn Gob(args: TokenStream, input: TokenStream) -> TokenStream {
                 let name = f.name;
                 let ident = f.ident;
                 
-                // Generate map encoding that encodes both key and value as interfaces
-                // Key is always a string (the field name)
-                // Value depends on map_types - if interface{}, encode with type info
-                
+                // Key is always the field name; value and key are wrapped as interfaces
+                // only where the map type says interface{}.
+                let encode_key = if map_key_id == 8 {
+                    // The field name as an interface holding a string
+                    // (name, type id, byte count, singleton delta, value).
+                    quote! { #gobx::encode_as_interface(&#name.to_string(), encoder)?; }
+                } else {
+                    quote! { encoder.write_string(#name)?; }
+                };
+                let encode_value = if f.as_string && map_value_id == 8 {
+                    quote! { #gobx::encode_as_interface(&self.#ident.to_string(), encoder)?; }
+                } else if f.as_string {
+                    quote! { encoder.write_string(&self.#ident.to_string())?; }
+                } else if map_value_id == 8 {
+                    // Value as interface, typed from GobEncodable
+                    quote! { #gobx::encode_as_interface(&self.#ident, encoder)?; }
+                } else {
+                    quote! { #gobx::GobEncodable::encode(&self.#ident, encoder)?; }
+                };
                 map_encode_fields.push(quote! {
-                    // Encode key as interface (string type)
-                    encoder.write_string(#name)?; // Type name for string
-                    encoder.write_int(6)?; // Type ID 6 = string
-                    
-                    // Encode the key string value (length + bytes)
-                    let key_bytes = #name.as_bytes();
-                    encoder.write_uint(key_bytes.len() as u64)?;
-                    encoder.write_all(key_bytes)?;
-                    
-                    // Encode value as interface
-                    // We need to determine the type name and ID at runtime
-                    // For now, we'll use GobEncodable trait methods
-                    gobx::encode_as_interface(&self.#ident, encoder)?;
+                    #encode_key
+                    #encode_value
                 });
             }
         }
     }
     
-    // Check if we need to interpret as map
-    let interpret_as_map = gob_args.interpret_as.as_ref().map_or(false, |s| s.starts_with("map["));
-    let map_types = gob_args.parse_map_types();
-    
+    // Entries of the #[gob(extra)] field go after the named fields. Its scalar values can be
+    // written without a GobWriter; keys and values must fit the map's declared types.
+    let (extra_count, encode_extra) = match &extra_field {
+        Some(extra) => {
+            let check = |id: i64, slot: proc_macro2::TokenStream| if id == 8 {
+                quote! {}
+            } else {
+                quote! {
+                    if #gobx::GobEncodable::type_id(#slot) != #id {
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!(
+                            "{} extra entry {:?} does not fit the map's type", stringify!(#struct_ident), key)));
+                    }
+                }
+            };
+            let key_check = check(map_key_id, quote! { key });
+            let value_check = check(map_value_id, quote! { value });
+            let encode_key = if map_key_id == 8 {
+                quote! { #gobx::encode_as_interface(key, encoder)?; }
+            } else {
+                quote! { #gobx::GobEncodable::encode(key, encoder)?; }
+            };
+            let encode_value = if map_value_id == 8 {
+                // A nil interface is just the empty name.
+                quote! {
+                    match value {
+                        #gobx::Value::Nil => encoder.write_string("")?,
+                        value => #gobx::encode_as_interface(value, encoder)?,
+                    }
+                }
+            } else {
+                quote! { #gobx::GobEncodable::encode(value, encoder)?; }
+            };
+            (quote! { + self.#extra.len() as u64 }, quote! {
+                for (key, value) in &self.#extra {
+                    #key_check
+                    #value_check
+                    #encode_key
+                    #encode_value
+                }
+            })
+        }
+        None => (quote! {}, quote! {}),
+    };
+
     let encode_impl = if interpret_as_map {
         let count_lit = proc_macro2::Literal::u64_unsuffixed(map_encode_fields.len() as u64);
-        
-        // Check if we need interface encoding
-        let value_is_interface = map_types.as_ref()
-            .map(|(_, v)| v == "interface{}")
-            .unwrap_or(false);
-        
-        if value_is_interface {
-            // For map[K]interface{}, encode each value as interface
-            quote! {
-                encoder.write_uint(#count_lit)?;
-                
-                #(#map_encode_fields)*
-                Ok(())
-            }
-        } else {
-            // Simple map encoding
-            quote! {
-                encoder.write_uint(#count_lit)?;
-                
-                #(#map_encode_fields)*
-                Ok(())
-            }
+        quote! {
+            encoder.write_uint(#count_lit #extra_count)?;
+            
+            #(#map_encode_fields)*
+            #encode_extra
+            Ok(())
         }
     } else {
         quote! {
-            let mut last_field_num = 0;
-            #(#encode_fields)*
+            let mut last_field_num = -1i64;
+            self.gob_encode_fields(encoder, 0, &mut last_field_num)?;
             
             // End of struct marked by delta 0
             encoder.write_uint(0)?;
@@ -209,124 +1208,334 @@ pub fn Gob(args: TokenStream, input: TokenStream) -> TokenStream {
     
     let struct_name = &item.ident;
     let type_id = gob_args.id.unwrap_or(0);
+    let go_name = gob_args.name.clone().unwrap_or_else(|| struct_name.to_string());
+
+    let is_struct = !interpret_as_map;
+    let type_definition_impl = if interpret_as_map {
+        quote! {
+            fn write_type_definition<W: std::io::Write>(writer: &mut #gobx::GobWriter<W>) -> std::io::Result<i64> {
+                writer.define_map(#map_key_id, #map_value_id)
+            }
+        }
+    } else {
+        quote! {
+            fn write_type_definition<W: std::io::Write>(writer: &mut #gobx::GobWriter<W>) -> std::io::Result<i64> {
+                let mut fields = Vec::new();
+                Self::gob_field_types(writer, &mut fields)?;
+                writer.define_struct(#go_name, #type_id, &fields)
+            }
+        }
+    };
+
+    // A struct's own definition and message; a map-interpreted struct sends its mapType.
+    let message_impl = if interpret_as_map {
+        quote! {
+            /// Writes this struct to `writer` as a complete map message, sending the map
+            /// type definition first if needed. Returns the bytes written.
+            pub fn encode_message<W: std::io::Write>(&self, writer: &mut #gobx::GobWriter<W>) -> std::io::Result<usize> {
+                writer.encode_typed(self)
+            }
+        }
+    } else {
+        quote! {
+            /// Writes this struct to `writer` as a complete message, sending its type
+            /// definition first if needed. Returns the bytes written.
+            pub fn encode_message<W: std::io::Write>(&self, writer: &mut #gobx::GobWriter<W>) -> std::io::Result<usize> {
+                writer.encode_typed(self)
+            }
+
+            /// The struct's own fields in wire order, with their Go names. A field's id is
+            /// its predefined wire type's (int is 2, string 6, ...) when its Rust type says
+            /// so unambiguously: a primitive, a fully qualified `std::string::String` or
+            /// `std::time::Duration`, or an `as_string` or `interface` field. Otherwise it
+            /// is 0, and `gob_schema` has the real id. Decoding checks a nonzero id against
+            /// the stream's definition. Fields of `#[gob(flatten)]` members are not listed
+            /// here but in the member's own `GOB_FIELDS`; `gob_schema` has them inline.
+            pub const GOB_FIELDS: &'static [#gobx::types::FieldType] = &[#(#gob_fields),*];
+
+            /// The definition this struct is sent with, as a new `GobWriter` numbers it:
+            /// its Go name and id, and every wire field with its type id.
+            pub fn gob_schema() -> std::io::Result<#gobx::types::StructType>
+            where Self: #gobx::GobEncodable {
+                let mut writer = #gobx::GobWriter::new(std::io::sink());
+                let id = <Self as #gobx::GobEncodable>::write_type_definition(&mut writer)?;
+                let mut fields = Vec::new();
+                Self::gob_field_types(&mut writer, &mut fields)?;
+                Ok(#gobx::types::StructType {
+                    common: #gobx::types::CommonType { name: #go_name.to_string(), id },
+                    fields: fields.into_iter().map(|(name, id)| #gobx::types::FieldType { name: name.into(), id }).collect(),
+                })
+            }
+
+            /// Appends this struct's wire fields as (Go name, type id), in field number order,
+            /// defining each field's type on `writer` first.
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn gob_field_types<W: std::io::Write>(writer: &mut #gobx::GobWriter<W>, out: &mut Vec<(String, i64)>) -> std::io::Result<()> {
+                #(#field_types)*
+                Ok(())
+            }
+        }
+    };
     
-    // Check if we need to interpret as map
-    let interpret_as_map = gob_args.interpret_as.as_ref().map_or(false, |s| s.starts_with("map["));
-    
-    let decode_impl = if interpret_as_map {
-        // Map decoding logic
-        // We need to map struct fields to map keys.
-        // We will assume map keys are strings matching the field names (or `gob(name=...)` override).
-        
-        // let mut map_match_arms = Vec::new();
-        
-        if let Data::Struct(ref data) = item.data {
-            if let Fields::Named(ref fields) = data.fields {
-                for field in &fields.named {
-                    let field_ident = field.ident.as_ref().unwrap();
-                    let field_name_str = field_ident.to_string();
-                    
-                    // Recover custom name from attributes which we stripped earlier?
-                    // Ah, we stripped them from `item` but we are iterating `item` here?
-                    // Wait, `item` was modified in place above (stripping attributes).
-                    // BUT we didn't save the custom names in a way easy to access here except by re-parsing or saving earlier.
-                    // We should have saved the mapping earlier.
-                    
-                    // Let's rely on `field_ident` string for now, or we need to refactor the loop above to collect info.
-                    // Refactoring loop above is better.
-                }
+    // A non-string interface key can't name a field; it reads as "" and goes to the extra
+    // field if there is one, or is skipped with its value.
+    let read_key = match (map_key_id, &extra_field) {
+        (8, None) => quote! {
+            let key_str = match <#gobx::Value as #gobx::GobDecodable>::decode(decoder)? { #gobx::Value::String(key) => key, _ => String::new() };
+        },
+        (8, Some(_)) => quote! {
+            let key_val = <#gobx::Value as #gobx::GobDecodable>::decode(decoder)?;
+            let key_str = match &key_val { #gobx::Value::String(key) => key.clone(), _ => String::new() };
+        },
+        (_, None) => quote! { let key_str = decoder.read_string()?; },
+        (_, Some(_)) => quote! {
+            let key_str = decoder.read_string()?;
+            let key_val = #gobx::Value::String(key_str.clone());
+        },
+    };
+    // Struct form: a field the Rust struct doesn't have goes to the extra field under its
+    // name, or is skipped.
+    let (struct_key_val, skip_struct_field) = match &extra_field {
+        Some(extra) => (
+            quote! { let key_val = #gobx::Value::String(key_str.clone()); },
+            quote! { result.#extra.insert(key_val, decoder.decode_typed(field_type)?); },
+        ),
+        None => (quote! {}, quote! { decoder.skip_field(field_num)?; }),
+    };
+    let match_key = if fold_keys {
+        quote! { key_str.chars().filter(|&c| c != '_').flat_map(char::to_lowercase).collect::<String>().as_str() }
+    } else {
+        quote! { key_str.as_str() }
+    };
+    let skip_value = match (map_value_id, &extra_field) {
+        (8, None) => quote! { <#gobx::Value as #gobx::GobDecodable>::decode(decoder)?; },
+        (_, None) => {
+            let schema = builtin_schema(&gobx, map_value_id);
+            quote! { decoder.skip_value(&#schema)?; }
+        }
+        (id, Some(extra)) => {
+            let value = match id {
+                1 => quote! { #gobx::Value::Bool(decoder.read_bool()?) },
+                2 => quote! { #gobx::Value::Int(decoder.read_int()?) },
+                3 => quote! { #gobx::Value::Uint(decoder.read_uint()?) },
+                4 => quote! { #gobx::Value::Float(decoder.read_float()?) },
+                5 => quote! { #gobx::Value::Bytes(decoder.read_bytes()?) },
+                6 => quote! { #gobx::Value::String(decoder.read_string()?) },
+                _ => quote! { <#gobx::Value as #gobx::GobDecodable>::decode(decoder)? },
+            };
+            quote! { result.#extra.insert(key_val, #value); }
+        }
+    };
+
+    let check_map_required = if map_required.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let missing: Vec<&str> = [#(#map_required),*].into_iter().filter(|name| !seen.contains(name)).collect();
+            if !missing.is_empty() {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+                    "{} is missing required fields: {}", stringify!(#struct_name), missing.join(", "))));
             }
         }
-        
-        // Placeholder for the better implementation below
+    };
+
+    let decode_impl = if interpret_as_map {
+        // Map keys are matched against the field names (or their `gob(name=...)` overrides).
+        // This is the body of decode_struct, so it is what `GobDecodable::decode` runs too:
+        // `decode_into`, `from_slice_typed`, a map-mode struct nested in another struct and
+        // a direct `decode` call all read the map the same way.
+        //
+        // When the stream says the value is a Go struct instead, its field names stand in for
+        // the keys, so the same struct decodes from either form.
         quote! {
-            // NOTE: We assume the decoder is positioned at the start of the Map value content
-            // (after any headers).
-            // A Gob Map on wire: [Count] [Key] [Value] [Key] [Value]...
-            // `decoder.read_uint()` gives the count.
-            
-            // However, our generated code is called by `GobDecodable::decode` (conceptually),
-            // which in turn is called by `Decoder`.
-            // BUT `UserInfo::decode` is called manually in test.
-            // If we call `UserInfo::decode(&mut decoder)`, it executes this block.
-            
-            // Debugging: print what we are doing
-            // println!("Decoding UserInfo as map...");
-            
-            // The first thing in a map is the element count.
-            let count = decoder.read_uint()?;
-            // println!("Map count: {}", count);
-            
-            for _ in 0..count {
-                let key_val = gobx::Value::decode(decoder)?;
-                let value_val = gobx::Value::decode(decoder)?; 
-                
-                // println!("Key: {:?}, Value: {:?}", key_val, value_val);
+            #[allow(unused_mut)]
+            let mut seen: Vec<&'static str> = Vec::new();
+            if let Some(#gobx::decode::TypeSchema::Struct(wire_fields)) = decoder.active_schema().cloned() {
+                let mut field_num = -1i64;
+                loop {
+                    let delta = decoder.read_uint()?;
+                    if delta == 0 { break; }
+                    field_num += delta as i64;
+                    let Some((_, field_type, key_str)) = usize::try_from(field_num).ok().and_then(|i| wire_fields.get(i)).cloned() else {
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown field index {} for Struct", field_num)));
+                    };
+                    #[allow(unused_variables)]
+                    let read_value = |decoder: &mut #gobx::Decoder<R>| decoder.decode_typed(field_type);
+                    #[allow(unused_variables)]
+                    let enter_value = |decoder: &mut #gobx::Decoder<R>| decoder.enter_field(field_num);
+                    #struct_key_val
 
-                if let gobx::Value::String(key_str) = key_val {
-                    match key_str.as_str() {
+                    match #match_key {
                         #(#map_decode_fields)*
                         _ => {
-                            // Ignore unknown fields
+                            #skip_struct_field
                         }
                     }
                 }
+                #(#map_default_fields)*
+                #check_map_required
+                return Ok(result);
             }
+
+            // The decoder is positioned at the map's content: [count] then key/value pairs.
+            let count = decoder.read_uint()?;
+            #[allow(unused_variables)]
+            let read_value = |decoder: &mut #gobx::Decoder<R>| <#gobx::Value as #gobx::GobDecodable>::decode(decoder);
+            // A concrete map value has no wire type of its own to hand down.
+            #[allow(unused_variables)]
+            let enter_value = |decoder: &mut #gobx::Decoder<R>| decoder.enter_unknown();
+            for _ in 0..count {
+                #read_key
+
+                match #match_key {
+                    #(#map_decode_fields)*
+                    _ => {
+                        // Ignore unknown fields
+                        #skip_value
+                    }
+                }
+            }
+            #(#map_default_fields)*
+            #check_map_required
             Ok(result)
         } 
     } else {
         // Standard struct delta decoding
         quote! {
                 let mut field_num = -1i64;
+                let mut seen = Vec::new();
                 
                 loop {
                     let delta = decoder.read_uint()?;
                     if delta == 0 { break; }
                     field_num += delta as i64;
                     
-                    match field_num {
-                        #(#decode_fields)*
-                        _ => {
+                    match result.gob_decode_field(decoder, field_num, 0)? {
+                        true => seen.push(field_num),
+                        false if decoder.ignore_unknown_fields() => {
+                            decoder.skip_field(field_num)?;
+                        }
+                        false => {
                             return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Unknown field delta {} (total {}) for struct {}", delta, field_num, stringify!(#struct_name))));
                         }
                     }
                 }
+                result.gob_apply_defaults(&seen, 0);
+                let mut missing = Vec::new();
+                Self::gob_missing_required(&seen, 0, &mut missing);
+                if !missing.is_empty() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+                        "{} is missing required fields: {}", stringify!(#struct_name), missing.join(", "))));
+                }
                 Ok(result)
         }
     };
     
-    let expanded = quote! {
-        #item
+    // The struct's own generics, plus either the field bounds or #[gob(bound = "...")].
+    let mut generics = item.generics.clone();
+    let where_clause = generics.make_where_clause();
+    match &struct_args.bound {
+        Some(bound) => {
+            let parser = Punctuated::<syn::WherePredicate, Token![,]>::parse_terminated;
+            match syn::parse::Parser::parse_str(parser, bound) {
+                Ok(predicates) => where_clause.predicates.extend(predicates),
+                Err(e) => return e.to_compile_error(),
+            }
+        }
+        None => {
+            for bound in &field_bounds {
+                where_clause.predicates.push(syn::parse_quote! { #bound });
+            }
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // Decoding fills in a Self::default(), which a generic struct only has for some parameters.
+    let mut decode_generics = generics.clone();
+    decode_generics.make_where_clause().predicates.push(syn::parse_quote! { Self: Default });
+    let decode_where_clause = &decode_generics.where_clause;
 
-        impl gobx::GobType for #struct_name {
+    let expanded = quote! {
+        impl #impl_generics #gobx::GobType for #struct_name #ty_generics #where_clause {
             const ID: i64 = #type_id;
         }
         
-        impl gobx::GobDecodable for #struct_name {
-            fn decode<R: std::io::Read>(decoder: &mut gobx::Decoder<R>) -> std::io::Result<Self> {
+        impl #impl_generics #gobx::GobDecodable for #struct_name #ty_generics #decode_where_clause {
+            fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> {
                  // We require Default for decode construction
                  Self::decode_struct(decoder)
             }
         }
+
+        impl #impl_generics #gobx::GobEncodable for #struct_name #ty_generics #where_clause {
+            fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
+                Self::encode(self, encoder)
+            }
+            fn type_id(&self) -> i64 { <Self as #gobx::GobType>::ID }
+            fn type_name(&self) -> &'static str { #go_name }
+            fn is_struct(&self) -> bool { #is_struct }
+            #type_definition_impl
+        }
         
-        impl #struct_name {
-            pub fn encode<W: std::io::Write>(&self, encoder: &mut gobx::Encoder<W>) -> std::io::Result<()> {
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            pub fn encode<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>) -> std::io::Result<()> {
                 #encode_impl
             }
             
-            pub fn decode<R: std::io::Read>(decoder: &mut gobx::Decoder<R>) -> std::io::Result<Self> 
+            pub fn decode<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> 
             where Self: Default {
                 Self::decode_struct(decoder)
             }
 
-            pub fn decode_struct<R: std::io::Read>(decoder: &mut gobx::Decoder<R>) -> std::io::Result<Self> 
+            pub fn decode_struct<R: std::io::Read>(decoder: &mut #gobx::Decoder<R>) -> std::io::Result<Self> 
             where Self: Default {
                 let mut result = Self::default();
                 #decode_impl
             }
+
+            /// Number of wire fields, counting the fields of flattened members.
+            #[doc(hidden)]
+            pub const GOB_FIELD_COUNT: i64 = #field_count;
+
+            /// Writes this struct's fields numbered from `base`, without the terminating 0,
+            /// so a parent can inline them with `#[gob(flatten)]`.
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn gob_encode_fields<W: std::io::Write>(&self, encoder: &mut #gobx::Encoder<W>, base: i64, last_field_num: &mut i64) -> std::io::Result<()> {
+                #(#encode_fields)*
+                Ok(())
+            }
+
+            /// Sets `#[gob(default)]` fields whose numbers (ours counted from `base`) are not in `seen`.
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn gob_apply_defaults(&mut self, seen: &[i64], base: i64) {
+                #(#default_fields)*
+            }
+
+            /// Adds the names of `#[gob(required)]` fields whose numbers (ours counted from
+            /// `base`) are not in `seen`.
+            #[doc(hidden)]
+            #[allow(unused_variables, clippy::ptr_arg)]
+            pub fn gob_missing_required(seen: &[i64], base: i64, missing: &mut Vec<&'static str>) {
+                #(#required_fields)*
+            }
+
+            #message_impl
+
+            #convenience
+
+            /// Decodes wire field `field_num` if it is one of ours, numbering our fields from `base`.
+            /// Returns false for a field this struct doesn't declare.
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn gob_decode_field<R: std::io::Read>(&mut self, decoder: &mut #gobx::Decoder<R>, field_num: i64, base: i64) -> std::io::Result<bool> {
+                #(#decode_fields)*
+                Ok(false)
+            }
         }
     };
 
-    TokenStream::from(expanded)
+    expanded
 }
 
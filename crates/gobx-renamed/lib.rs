@@ -0,0 +1,61 @@
+//! Checks that `#[Gob(crate = "...")]` code compiles and works where gobx is a renamed
+//! dependency (`gob`) or reached through a re-export.
+
+pub mod facade {
+    pub use gob as wire;
+}
+
+use gob::Gob;
+
+#[Gob(crate = "gob", id = 80)]
+#[derive(Debug, Default, PartialEq)]
+pub struct Point {
+    pub x: i64,
+    #[gob(interface)]
+    pub label: String,
+    pub tags: Vec<String>,
+}
+
+#[Gob(crate = "gob", interpret_as = "map[string]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+pub struct Session {
+    pub uid: i64,
+    pub uname: String,
+}
+
+#[Gob(crate = "crate::facade::wire", repr = "string")]
+#[derive(Debug, Default, PartialEq)]
+pub enum Role {
+    #[default]
+    Admin,
+    Guest,
+}
+
+#[derive(gob::derive::Gob, Debug, Default, PartialEq)]
+#[gob(crate = "crate::facade::wire", interpret_as = "[]int64")]
+pub struct Scores {
+    pub items: Vec<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renamed_crate_path() {
+        let point = Point { x: 3, label: "a".to_string(), tags: vec!["t".to_string()] };
+        let bytes = gob::to_vec_typed(&point).unwrap();
+        assert_eq!(gob::from_slice_typed::<Point>(&bytes).unwrap(), point);
+
+        let session = Session { uid: 1, uname: "ann".to_string() };
+        let bytes = gob::to_vec_typed(&session).unwrap();
+        assert_eq!(gob::from_slice_typed::<Session>(&bytes).unwrap(), session);
+
+        let bytes = gob::to_vec_typed(&Role::Guest).unwrap();
+        assert_eq!(gob::from_slice(&bytes).unwrap(), gob::Value::from("Guest"));
+
+        let scores = Scores { items: vec![1, -2] };
+        let bytes = gob::to_vec_typed(&scores).unwrap();
+        assert_eq!(gob::from_slice_typed::<Scores>(&bytes).unwrap(), scores);
+    }
+}
@@ -0,0 +1,12 @@
+//! Arbitrary bytes must decode to `Ok` or `Err`: no panic, hang or runaway allocation.
+#![no_main]
+
+use gobx::{Decoder, Value};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Decoder::new(data).decode_all();
+    let _ = Decoder::new(data).decode_into::<Value>();
+    let _ = Decoder::new(data).read_raw_message().map(|m| m.map(|m| m.decode()));
+    let _ = gobx::codec::decode_message(data);
+});
@@ -0,0 +1,67 @@
+//go:build ignore
+
+// gen_fixtures writes the gob fixtures used by the Rust tests:
+//
+//	normal-session.bin    a session map as stored by the web app
+//	normal-session-2.bin  the same with an "email" entry
+//	goth-session.bin      a session map holding a *sessions.Session in an interface
+//
+// Run it from the repository root:
+//
+//	go run gen_fixtures.go
+//
+// Go randomizes map iteration order, so regenerated files can list map entries in a
+// different order than the committed ones. The tests compare decoded values, not
+// bytes, for anything containing a map with more than one entry.
+package main
+
+import (
+	"encoding/gob"
+	"log"
+	"os"
+
+	"github.com/gorilla/sessions"
+)
+
+func write(filename string, data map[interface{}]interface{}) {
+	file, err := os.Create(filename)
+	if err != nil {
+		log.Fatalf("create %s: %v", filename, err)
+	}
+	defer file.Close()
+
+	if err := gob.NewEncoder(file).Encode(data); err != nil {
+		log.Fatalf("encode %s: %v", filename, err)
+	}
+}
+
+func main() {
+	gob.Register(&sessions.Session{})
+
+	write("normal-session.bin", map[interface{}]interface{}{
+		"uid":                  int64(1),
+		"uname":                "dsotsen",
+		"_old_uid":             "1",
+		"userHasTwoFactorAuth": false,
+	})
+
+	write("normal-session-2.bin", map[interface{}]interface{}{
+		"uid":                  int64(1),
+		"uname":                "dsotsen",
+		"email":                "dsotsen@qq.com",
+		"_old_uid":             "1",
+		"userHasTwoFactorAuth": false,
+	})
+
+	write("goth-session.bin", map[interface{}]interface{}{
+		"uid":                  int64(3),
+		"uname":                "Qin-Zhou",
+		"_old_uid":             "3",
+		"userHasTwoFactorAuth": false,
+		"_gothic_session": &sessions.Session{
+			ID:      "17634d7885249bfc",
+			Values:  map[interface{}]interface{}{},
+			Options: &sessions.Options{MaxAge: -1},
+		},
+	})
+}
@@ -0,0 +1,34 @@
+//go:build ignore
+
+// gen_tagged_fixture writes tagged.bin, a struct with []string and []int64 fields,
+// used by the Rust macro tests. It is a separate program from gen_fixtures.go because
+// gob numbers types per process: on its own, Tagged gets id 64, []string 65 and
+// []int64 66.
+//
+//	go run gen_tagged_fixture.go
+package main
+
+import (
+	"encoding/gob"
+	"log"
+	"os"
+)
+
+type Tagged struct {
+	Name   string
+	Tags   []string
+	Scores []int64
+}
+
+func main() {
+	file, err := os.Create("tagged.bin")
+	if err != nil {
+		log.Fatalf("create tagged.bin: %v", err)
+	}
+	defer file.Close()
+
+	value := Tagged{Name: "ann", Tags: []string{"a", "b"}, Scores: []int64{1, -2}}
+	if err := gob.NewEncoder(file).Encode(value); err != nil {
+		log.Fatalf("encode tagged.bin: %v", err)
+	}
+}
@@ -0,0 +1,145 @@
+use std::io::Cursor;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::decode::{truncated, READ_CHUNK};
+use crate::{codec, Decoder, Result, Value};
+
+/// Reads gob values from a tokio [`AsyncRead`], e.g. a socket, without blocking.
+///
+/// Whole messages are read asynchronously and handed to a [`Decoder`] over an in-memory
+/// buffer, so type definitions and value decoding are the same as for the blocking path.
+/// A value whose interface types Go defines mid-value spans several messages; it is
+/// decoded once all of them have arrived.
+pub struct AsyncDecoder<R> {
+    reader: R,
+    decoder: Decoder<Cursor<Vec<u8>>>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, decoder: Decoder::new(Cursor::new(Vec::new())) }
+    }
+
+    /// The decoder the values are read with, for its settings and the types seen so far.
+    pub fn decoder(&self) -> &Decoder<Cursor<Vec<u8>>> {
+        &self.decoder
+    }
+
+    pub fn decoder_mut(&mut self) -> &mut Decoder<Cursor<Vec<u8>>> {
+        &mut self.decoder
+    }
+
+    /// Reads the next value, processing any type definitions before it.
+    /// Returns `Ok(None)` at a clean end of stream, as [`Decoder::read_next`] does.
+    pub async fn read_next(&mut self) -> Result<Option<Value>> {
+        self.compact();
+        loop {
+            let start = self.decoder.get_mut().position();
+            if start < self.decoder.get_mut().get_ref().len() as u64 {
+                match self.decoder.read_next() {
+                    Ok(Some(value)) => return Ok(Some(value)),
+                    // Only type definitions so far; they are registered now.
+                    Ok(None) => {}
+                    // The value goes on in a message that hasn't arrived yet. Start it over
+                    // once that message is buffered.
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                        self.decoder.reset_message();
+                        self.decoder.get_mut().set_position(start);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if !self.read_message().await? {
+                let cursor = self.decoder.get_mut();
+                if cursor.position() < cursor.get_ref().len() as u64 {
+                    return Err(truncated(std::io::ErrorKind::UnexpectedEof.into()));
+                }
+                return Ok(None);
+            }
+        }
+    }
+
+    // Appends one whole message, length prefix included, to the buffer. False at a clean end
+    // of stream.
+    async fn read_message(&mut self) -> Result<bool> {
+        let mut header = [0u8; 9];
+        match self.reader.read_exact(&mut header[..1]).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        let mut used = 1;
+        if header[0] >= 128 {
+            let n = codec::uint_byte_count(header[0])?;
+            self.reader.read_exact(&mut header[1..1 + n]).await.map_err(truncated)?;
+            used += n;
+        }
+        let (len, _) = codec::decode_uint(&header[..used])?;
+
+        let buf = self.decoder.get_mut().get_mut();
+        buf.extend_from_slice(&header[..used]);
+        // `len` comes off the wire, so grow the buffer as bytes arrive, as
+        // `Decoder::read_exact_bytes` does, rather than allocating it all up front.
+        let end = buf.len() + len as usize;
+        while buf.len() < end {
+            let start = buf.len();
+            buf.resize(start + (end - start).min(READ_CHUNK), 0);
+            self.reader.read_exact(&mut buf[start..]).await.map_err(truncated)?;
+        }
+        Ok(true)
+    }
+
+    // Drops the messages already decoded.
+    fn compact(&mut self) {
+        let cursor = self.decoder.get_mut();
+        let pos = cursor.position() as usize;
+        if pos > 0 {
+            cursor.get_mut().drain(..pos);
+            cursor.set_position(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(f)
+    }
+
+    fn read_all_async(input: &[u8]) -> Result<Vec<Value>> {
+        block_on(async {
+            let mut decoder = AsyncDecoder::new(input);
+            let mut values = Vec::new();
+            while let Some(v) = decoder.read_next().await? {
+                values.push(v);
+            }
+            Ok(values)
+        })
+    }
+
+    #[test]
+    fn test_matches_blocking_decoder() {
+        for input in [&include_bytes!("../goth-session.bin")[..], &include_bytes!("../normal-session.bin")[..]] {
+            let expected: Vec<Value> = Decoder::new(input).values().collect::<Result<_>>().unwrap();
+            assert!(!expected.is_empty());
+            assert_eq!(read_all_async(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let input = include_bytes!("../normal-session.bin");
+        let err = read_all_async(&input[..input.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert!(read_all_async(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_huge_length_is_not_allocated_up_front() {
+        // A header claiming a 4 GiB message, then nothing.
+        let err = read_all_async(b"\xfb\x01\x00\x00\x00\x00\x04").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
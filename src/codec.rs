@@ -0,0 +1,163 @@
+//! Gob's primitive encodings over byte slices, with no `std::io` and no allocation.
+//!
+//! Each `decode_*` function reads from the start of `buf` and returns the value together
+//! with the number of bytes it consumed, so fragments can be walked in place. This module
+//! is all that is built without the `std` feature; [`Decoder`](crate::Decoder) uses the
+//! same conversions on top of `std::io::Read`.
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecError {
+    /// `buf` ended before the value did.
+    Truncated,
+    /// An unsigned integer with a byte count over 8.
+    UintOutOfRange,
+    /// A bool encoded as something other than 0 or 1.
+    InvalidBool,
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Truncated => f.write_str("gob data truncated"),
+            CodecError::UintOutOfRange => f.write_str("encoded unsigned integer out of range"),
+            CodecError::InvalidBool => f.write_str("invalid bool encoding"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CodecError {}
+
+#[cfg(feature = "std")]
+impl From<CodecError> for std::io::Error {
+    fn from(e: CodecError) -> Self {
+        let kind = match e {
+            CodecError::Truncated => std::io::ErrorKind::UnexpectedEof,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+/// Number of big-endian bytes following a uint's first byte, which must be 128 or more
+/// (smaller values are the uint itself).
+#[inline]
+pub fn uint_byte_count(first: u8) -> Result<usize, CodecError> {
+    let n = (!first).wrapping_add(1) as usize;
+    if n > 8 {
+        return Err(CodecError::UintOutOfRange);
+    }
+    Ok(n)
+}
+
+/// Undoes gob's zig-zag encoding of a signed integer.
+#[inline]
+pub fn int_from_bits(bits: u64) -> i64 {
+    let sint = (bits >> 1) as i64;
+    if bits & 1 == 0 { sint } else { !sint }
+}
+
+/// Gob's zig-zag encoding of a signed integer, sent as a uint.
+#[inline]
+pub fn int_to_bits(v: i64) -> u64 {
+    if v < 0 { ((!v as u64) << 1) | 1 } else { (v as u64) << 1 }
+}
+
+/// Number of bytes a uint takes on the wire, e.g. for a length prefix written before the
+/// bytes it counts.
+pub fn measure_uint(v: u64) -> usize {
+    if v < 128 { 1 } else { 1 + (8 - v.leading_zeros() as usize / 8) }
+}
+
+/// Number of bytes an int takes on the wire.
+pub fn measure_int(v: i64) -> usize {
+    measure_uint(int_to_bits(v))
+}
+
+/// Floats are sent as their bits with the bytes reversed, so that small exponents stay short.
+#[inline]
+pub fn float_from_bits(bits: u64) -> f64 {
+    f64::from_bits(bits.swap_bytes())
+}
+
+#[inline]
+pub fn bool_from_bits(bits: u64) -> Result<bool, CodecError> {
+    match bits {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(CodecError::InvalidBool),
+    }
+}
+
+pub fn decode_uint(buf: &[u8]) -> Result<(u64, usize), CodecError> {
+    let first = *buf.first().ok_or(CodecError::Truncated)?;
+    if first < 128 {
+        return Ok((first as u64, 1));
+    }
+    let n = uint_byte_count(first)?;
+    let bytes = buf.get(1..1 + n).ok_or(CodecError::Truncated)?;
+    let v = bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    Ok((v, 1 + n))
+}
+
+pub fn decode_int(buf: &[u8]) -> Result<(i64, usize), CodecError> {
+    let (bits, used) = decode_uint(buf)?;
+    Ok((int_from_bits(bits), used))
+}
+
+pub fn decode_float(buf: &[u8]) -> Result<(f64, usize), CodecError> {
+    let (bits, used) = decode_uint(buf)?;
+    Ok((float_from_bits(bits), used))
+}
+
+pub fn decode_bool(buf: &[u8]) -> Result<(bool, usize), CodecError> {
+    let (bits, used) = decode_uint(buf)?;
+    Ok((bool_from_bits(bits)?, used))
+}
+
+/// A length-prefixed byte slice or string, borrowed from `buf`.
+pub fn decode_bytes(buf: &[u8]) -> Result<(&[u8], usize), CodecError> {
+    let (len, used) = decode_uint(buf)?;
+    let end = usize::try_from(len).ok().and_then(|len| used.checked_add(len)).ok_or(CodecError::Truncated)?;
+    let bytes = buf.get(used..end).ok_or(CodecError::Truncated)?;
+    Ok((bytes, end))
+}
+
+/// One message off the front of a stream: its type id (negative for a type definition)
+/// and its body.
+pub fn decode_message(buf: &[u8]) -> Result<(i64, &[u8], usize), CodecError> {
+    let (message, used) = decode_bytes(buf)?;
+    let (type_id, id_len) = decode_int(message)?;
+    Ok((type_id, &message[id_len..], used))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_primitives() {
+        assert_eq!(decode_uint(b"\x07"), Ok((7, 1)));
+        assert_eq!(decode_uint(b"\xfe\x01\x00\xff"), Ok((256, 3)));
+        assert_eq!(decode_uint(b"\xfe\x01"), Err(CodecError::Truncated));
+        assert_eq!(decode_uint(b"\xf7"), Err(CodecError::UintOutOfRange));
+        assert_eq!(decode_uint(b""), Err(CodecError::Truncated));
+
+        assert_eq!(decode_int(b"\x03"), Ok((-2, 1)));
+        assert_eq!(decode_float(b"\xfe\xf0\x3f"), Ok((1.0, 3)));
+        assert_eq!(decode_bool(b"\x01"), Ok((true, 1)));
+        assert_eq!(decode_bool(b"\x02"), Err(CodecError::InvalidBool));
+        assert_eq!(decode_bytes(b"\x02hi!"), Ok((&b"hi"[..], 3)));
+        assert_eq!(decode_bytes(b"\x05hi"), Err(CodecError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_message() {
+        // An int message: length, type id 2, singleton delta, value 3.
+        let stream = b"\x03\x04\x00\x06\x01";
+        assert_eq!(decode_message(stream), Ok((2, &b"\x00\x06"[..], 4)));
+        assert_eq!(decode_message(&stream[4..]), Err(CodecError::Truncated));
+    }
+}
@@ -1,29 +1,167 @@
 use byteorder::{BigEndian, ByteOrder};
-use std::collections::{HashMap, BTreeMap};
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::collections::{HashMap, HashSet, BTreeMap, BTreeSet};
 use crate::Result;
+use crate::codec;
 use crate::value::Value;
 
-#[derive(Debug, Clone)]
+// Largest buffer allocated ahead of the bytes that fill it.
+pub(crate) const READ_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TypeSchema {
     Bool,
     Int,
     Uint,
     Float,
+    Complex, // Two floats; Go sends complex64 and complex128 alike
     ByteSlice,
     String,
     Interface,
     Map(i64, i64), // KeyID, ElemID
+    Slice(i64), // ElemID
+    Array(i64, usize), // ElemID, Len
+    GobEncoder(String), // Opaque bytes from a GobEncoder, BinaryMarshaler or TextMarshaler; type name
     Struct(Vec<(i64, i64, String)>), // (FieldDelta, TypeID, Name)
     Custom(i64), // Placeholder for user defined types
 }
 
+impl TypeSchema {
+    /// Go's name for a predefined type, e.g. "int" or "[]byte". Other kinds have no single
+    /// name and give their kind instead ("map", "slice", "struct", ...).
+    pub fn name(&self) -> &'static str {
+        match self {
+            TypeSchema::Bool => "bool",
+            TypeSchema::Int => "int",
+            TypeSchema::Uint => "uint",
+            TypeSchema::Float => "float64",
+            TypeSchema::Complex => "complex128",
+            TypeSchema::ByteSlice => "[]byte",
+            TypeSchema::String => "string",
+            TypeSchema::Interface => "interface",
+            TypeSchema::Map(..) => "map",
+            TypeSchema::Slice(_) => "slice",
+            TypeSchema::Array(..) => "array",
+            TypeSchema::GobEncoder(_) => "GobEncoder",
+            TypeSchema::Struct(_) => "struct",
+            TypeSchema::Custom(_) => "custom",
+        }
+    }
+}
+
+/// One value message as read off the wire, left undecoded. See [`Decoder::read_raw_message`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawMessage {
+    /// Type id of the value, as numbered by the stream it was read from.
+    pub type_id: i64,
+    /// The encoded value: singleton delta (for non-structs) and body, with no message
+    /// header and with any type definitions sent mid-value removed.
+    pub payload: Vec<u8>,
+    /// Encoded wire types (`wireType` bodies) of every user type the value needs, by id.
+    pub definitions: Vec<(i64, Vec<u8>)>,
+}
+
+impl RawMessage {
+    /// Decodes the payload using the message's own definitions.
+    pub fn decode(&self) -> Result<Value> {
+        let mut decoder = Decoder::new_unframed(self.payload.as_slice());
+        for (id, wire) in &self.definitions {
+            let schema = Decoder::new_unframed(wire.as_slice()).decode_wire_type()?;
+            decoder.types.insert(*id, schema);
+        }
+        let schema = decoder.schema_for(self.type_id)?;
+        decoder.read_singleton_delta(&schema)?;
+        decoder.decode_value(&schema)
+    }
+
+    /// Go name of the message's type: the name in its definition for user types, or the
+    /// predefined type's name. Unnamed types (e.g. a map literal type) give `type <id>`.
+    pub fn type_name(&self) -> String {
+        let predefined = match self.type_id {
+            1 => "bool",
+            2 => "int",
+            3 => "uint",
+            4 => "float64",
+            5 => "[]byte",
+            6 => "string",
+            7 => "complex128",
+            8 => "interface",
+            _ => "",
+        };
+        if !predefined.is_empty() {
+            return predefined.to_string();
+        }
+        self.definitions.iter()
+            .find(|(id, _)| *id == self.type_id)
+            .and_then(|(_, wire)| wire_type_name(wire))
+            .unwrap_or_else(|| format!("type {}", self.type_id))
+    }
+}
+
+// A wire type's first field holds a type struct whose first field is CommonType, whose first
+// field is Name. Go omits an empty Name, so the third delta is then 2 (Id) rather than 1.
+fn wire_type_name(wire: &[u8]) -> Option<String> {
+    let mut decoder = Decoder::new_unframed(wire);
+    decoder.read_uint().ok()?;
+    if decoder.read_uint().ok()? != 1 || decoder.read_uint().ok()? != 1 {
+        return None;
+    }
+    decoder.read_string().ok()
+}
+
+// Bytes consumed while reading a raw message, plus the concrete type ids of interfaces in it.
+#[derive(Default)]
+struct Capture {
+    bytes: Vec<u8>,
+    type_ids: Vec<i64>,
+}
+
+/// What `Decoder` does when a map in the stream holds the same key more than once, which
+/// Go never produces but a corrupt or hand-made stream can.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// Keep the last value, as assigning into a Go map would.
+    #[default]
+    LastWins,
+    FirstWins,
+    /// Fail the decode with `InvalidData`.
+    Error,
+    /// Replace the value with a `Value::Array` of every value given for the key, in order.
+    CollectIntoArray,
+}
+
 pub struct Decoder<R: std::io::Read> {
     reader: R,
     types: HashMap<i64, TypeSchema>,
-    stash: Vec<u8>,
     current_msg_remaining: usize, 
+    // When false, reads come straight from `reader` with no message framing.
+    framed: bool,
+    ignore_unknown_fields: bool,
+    lossy_strings: bool,
+    lenient_bool: bool,
+    preserve_map_order: bool,
+    preserve_field_order: bool,
+    duplicate_keys: DuplicateKeyPolicy,
+    strict_types: bool,
+    // Wire type ids of the values being decoded by generated code, innermost last.
+    // `None` when the type isn't known (e.g. unframed input).
+    active_types: Vec<Option<i64>>,
+    // Encoded wire type of every definition read, kept so raw messages can carry them.
+    raw_types: HashMap<i64, Vec<u8>>,
+    capture: Option<Capture>,
+    // Types decoded as Value::Raw, and the concrete types of interfaces seen inside them
+    raw_type_ids: HashSet<i64>,
+    raw_deps: HashMap<i64, Vec<i64>>,
+    max_depth: usize,
+    // Values currently being decoded or skipped, outermost included
+    depth: usize,
 }
 
+/// Default for [`Decoder::set_max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
 impl<R: std::io::Read> Decoder<R> {
     pub fn new(reader: R) -> Self {
         let mut types = HashMap::new();
@@ -33,14 +171,196 @@ impl<R: std::io::Read> Decoder<R> {
         types.insert(4, TypeSchema::Float);
         types.insert(5, TypeSchema::ByteSlice);
         types.insert(6, TypeSchema::String);
+        types.insert(7, TypeSchema::Complex);
         types.insert(8, TypeSchema::Interface);
         
         Self { 
             reader, 
             types, 
-            stash: Vec::new(),
             current_msg_remaining: 0,
+            framed: true,
+            ignore_unknown_fields: false,
+            lossy_strings: false,
+            lenient_bool: false,
+            preserve_map_order: false,
+            preserve_field_order: false,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            strict_types: false,
+            active_types: Vec::new(),
+            raw_types: HashMap::new(),
+            capture: None,
+            raw_type_ids: HashSet::new(),
+            raw_deps: HashMap::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+        }
+    }
+
+    /// When enabled, struct fields the Rust type doesn't know about (e.g. fields a newer Go
+    /// producer added) are skipped instead of failing the decode. Off by default.
+    pub fn set_ignore_unknown_fields(&mut self, ignore: bool) {
+        self.ignore_unknown_fields = ignore;
+    }
+
+    pub fn ignore_unknown_fields(&self) -> bool {
+        self.ignore_unknown_fields
+    }
+
+    /// When enabled, strings that aren't valid UTF-8 (Go strings may hold arbitrary bytes)
+    /// decode with U+FFFD replacing the bad sequences instead of failing. Off by default.
+    pub fn set_lossy_strings(&mut self, lossy: bool) {
+        self.lossy_strings = lossy;
+    }
+
+    pub fn lossy_strings(&self) -> bool {
+        self.lossy_strings
+    }
+
+    /// When enabled, any nonzero bool decodes as true, for producers that write e.g. 0xFF
+    /// for true. Go only writes 0 and 1 and rejects anything else, as this decoder does by
+    /// default.
+    pub fn set_lenient_bool(&mut self, lenient: bool) {
+        self.lenient_bool = lenient;
+    }
+
+    pub fn lenient_bool(&self) -> bool {
+        self.lenient_bool
+    }
+
+    /// When enabled, maps decode as `Value::OrderedMap` with entries in stream order, so
+    /// re-encoding with `MapOrder::Insertion` reproduces the producer's layout. Off by default.
+    pub fn set_preserve_map_order(&mut self, preserve: bool) {
+        self.preserve_map_order = preserve;
+    }
+
+    pub fn preserve_map_order(&self) -> bool {
+        self.preserve_map_order
+    }
+
+    /// When enabled, structs decode as `Value::OrderedStruct` with fields in the order the
+    /// Go type defines them, so re-encoding sends the same definition. Off by default.
+    pub fn set_preserve_field_order(&mut self, preserve: bool) {
+        self.preserve_field_order = preserve;
+    }
+
+    pub fn preserve_field_order(&self) -> bool {
+        self.preserve_field_order
+    }
+
+    pub fn set_duplicate_key_policy(&mut self, policy: DuplicateKeyPolicy) {
+        self.duplicate_keys = policy;
+    }
+
+    pub fn duplicate_key_policy(&self) -> DuplicateKeyPolicy {
+        self.duplicate_keys
+    }
+
+    /// When enabled, a definition for a type id that already has a different schema fails
+    /// the decode instead of replacing it. Go never redefines an id within a stream, so
+    /// this points at corruption or a broken producer. Off by default.
+    pub fn set_strict_types(&mut self, strict: bool) {
+        self.strict_types = strict;
+    }
+
+    pub fn strict_types(&self) -> bool {
+        self.strict_types
+    }
+
+    /// How deeply values may nest (a struct holding a map of structs is three levels) before
+    /// decoding fails with `InvalidData`. Bounds the recursion an untrusted stream can cause,
+    /// which would otherwise overflow the stack. Defaults to [`DEFAULT_MAX_DEPTH`].
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    // Goes one level deeper, failing if that passes max_depth.
+    fn descend(&mut self) -> Result<()> {
+        if self.depth >= self.max_depth {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("gob value nested more than {} levels deep", self.max_depth)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    // Runs `f` one level deeper, failing if that passes max_depth.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.descend()?;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Values of these type ids (as numbered by this stream) are read as `Value::Raw`,
+    /// holding their encoded bytes, wherever the stream's types say they appear: as a
+    /// message, a struct field, or a slice, array or map element. A value inside an
+    /// interface is decoded as usual, since Go needs its registered name to take it back.
+    /// With the definitions from [`raw_definitions`](Self::raw_definitions), `GobWriter`
+    /// sends raw values back unchanged.
+    pub fn set_raw_type_ids(&mut self, ids: impl IntoIterator<Item = i64>) {
+        self.raw_type_ids = ids.into_iter().collect();
+    }
+
+    pub fn raw_type_ids(&self) -> &HashSet<i64> {
+        &self.raw_type_ids
+    }
+
+    /// Encoded definitions of the types the `Value::Raw` values in `value` need, by id, for
+    /// `GobWriter::define_raw_types`: their own types, the types those refer to, and the
+    /// concrete types of interfaces inside them.
+    pub fn raw_definitions(&self, value: &Value) -> Vec<(i64, Vec<u8>)> {
+        fn raw_ids(value: &Value, ids: &mut Vec<i64>) {
+            match value {
+                Value::Raw(id, _) => ids.push(*id),
+                Value::Array(items) => items.iter().for_each(|item| raw_ids(item, ids)),
+                Value::Map(map) => map.iter().for_each(|(k, v)| { raw_ids(k, ids); raw_ids(v, ids) }),
+                Value::OrderedMap(entries) => entries.iter().for_each(|(k, v)| { raw_ids(k, ids); raw_ids(v, ids) }),
+                Value::Struct(_, fields) => fields.values().for_each(|v| raw_ids(v, ids)),
+                Value::OrderedStruct(_, fields) => fields.iter().for_each(|(_, v)| raw_ids(v, ids)),
+                _ => {}
+            }
         }
+        let mut ids = Vec::new();
+        raw_ids(value, &mut ids);
+        let deps: Vec<i64> = ids.iter().flat_map(|id| self.raw_deps.get(id).into_iter().flatten().copied()).collect();
+        self.definitions_for(ids.into_iter().chain(deps))
+    }
+
+    /// The type registry: predefined types plus every definition read so far, by type id.
+    pub fn types(&self) -> &HashMap<i64, TypeSchema> {
+        &self.types
+    }
+
+    /// Gives back the reader. After a value is read (`read_next`, `decode_into`, ...) the
+    /// decoder has consumed exactly that value's messages, so the reader is positioned just
+    /// past them, e.g. at trailer data that follows a gob section. The decoder never reads
+    /// ahead of what it decodes; a reader given to `new_buffered` comes back as the
+    /// `BufReader`, which still holds any bytes it buffered past that point.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    // Forgets a partly read message so the caller can rewind the reader to its start.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn reset_message(&mut self) {
+        self.current_msg_remaining = 0;
+        self.active_types.clear();
+    }
+
+    /// Creates a decoder over a bare value body (no `[length][type id]` message headers),
+    /// e.g. the bytes produced by `Encoder` or the payload of an interface value.
+    pub fn new_unframed(reader: R) -> Self {
+        let mut decoder = Self::new(reader);
+        decoder.framed = false;
+        decoder
     }
 
     fn read_raw_exact(&mut self, buf: &mut [u8]) -> Result<()> {
@@ -59,55 +379,50 @@ impl<R: std::io::Read> Decoder<R> {
         if u7_or_len < 128 {
             return Ok(u7_or_len as u64);
         }
-        let len = (!u7_or_len).wrapping_add(1) as usize;
-        let mut buf = vec![0; len];
-        self.read_raw_exact(&mut buf)?;
-        Ok(BigEndian::read_uint(&buf, len))
+        let len = codec::uint_byte_count(u7_or_len)?;
+        let mut buf = [0u8; 8];
+        self.read_raw_exact(&mut buf[..len])?;
+        Ok(BigEndian::read_uint(&buf[..len], len))
     }
     
-    fn process_next_message_header(&mut self) -> Result<()> {
-        loop {
-            // Read Msg Length
-            let msg_len_res = self.read_raw_uint();
-            if let Err(e) = msg_len_res {
-                return Err(e); 
-            }
-            let msg_len = msg_len_res? as usize;
-            
-            self.current_msg_remaining = msg_len;
-            
-            let type_id = self.read_int()?;
-            
-            if type_id < 0 {
-                let def_id = -type_id;
-                let schema = self.decode_wire_type()?;
-                self.types.insert(def_id, schema);
-                
-                if self.current_msg_remaining > 0 {
-                    let mut drain = vec![0; self.current_msg_remaining];
-                    self.read_raw_exact(&mut drain)?;
-                    self.current_msg_remaining = 0;
-                }
-                continue;
-            } else {
-                return Ok(());
-            }
+    // Length of the next message, or None at a clean end of stream, where not one byte of a
+    // new message was read. Running out anywhere after that is a truncated stream.
+    fn read_message_len(&mut self) -> Result<Option<usize>> {
+        let first = match self.read_raw_u8() {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if first < 128 {
+            return Ok(Some(first as usize));
         }
+        let n = (!first).wrapping_add(1) as usize;
+        if n > 8 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid message length prefix {:#04x}", first)));
+        }
+        let mut buf = [0u8; 8];
+        self.read_raw_exact(&mut buf[..n]).map_err(truncated)?;
+        Ok(Some(BigEndian::read_uint(&buf[..n], n) as usize))
+    }
+
+    // A value only continues into the next message at an interface's type sequence, where the
+    // caller reads the type id itself, so crossing a boundary just consumes the length prefix.
+    fn process_next_message_header(&mut self) -> Result<()> {
+        self.current_msg_remaining = self.read_raw_uint()? as usize;
+        Ok(())
     }
 
     fn read_exact_internal(&mut self, buf: &mut [u8]) -> Result<()> {
         let mut pos = 0;
         
-        while pos < buf.len() && !self.stash.is_empty() {
-            buf[pos] = self.stash.remove(0);
-            pos += 1;
+        if !self.framed {
+            self.read_raw_exact(buf)?;
+            pos = buf.len();
         }
-        
+
         while pos < buf.len() {
             if self.current_msg_remaining == 0 {
-                if let Err(e) = self.process_next_message_header() {
-                     return Err(e);
-                }
+                self.process_next_message_header()?;
             }
             
             let needed = buf.len() - pos;
@@ -119,6 +434,9 @@ impl<R: std::io::Read> Decoder<R> {
                 pos += to_read;
             }
         }
+        if let Some(capture) = &mut self.capture {
+            capture.bytes.extend_from_slice(buf);
+        }
         Ok(())
     }
 
@@ -134,106 +452,128 @@ impl<R: std::io::Read> Decoder<R> {
         if u7_or_len < 128 {
             return Ok(u7_or_len as u64);
         }
-        let len = (!u7_or_len).wrapping_add(1);
-        self.fast_get_uint_be(len as usize)
+        let len = codec::uint_byte_count(u7_or_len)?;
+        self.fast_get_uint_be(len)
     }
     
+    // `nbytes` is at most 8 (checked by `uint_byte_count`), so the bytes fit on the stack.
     fn fast_get_uint_be(&mut self, nbytes: usize) -> Result<u64> {
-        let mut buf = vec![0; nbytes];
-        self.read_exact_internal(&mut buf)?;
+        let mut buf = [0u8; 8];
+        self.read_exact_internal(&mut buf[..nbytes])?;
         Ok(BigEndian::read_uint(&buf[..nbytes], nbytes))
     }
     
     #[inline]
     pub fn read_int(&mut self) -> Result<i64> {
-        let bits = self.read_uint()?;
-        let sign = bits & 1;
-        let sint = (bits >> 1) as i64;
-        if sign == 0 {
-            Ok(sint)
-        } else {
-            Ok(!sint)
-        }
+        Ok(codec::int_from_bits(self.read_uint()?))
     }
     
     #[inline]
     pub fn read_float(&mut self) -> Result<f64> {
-         let bits = self.read_uint()?;
-         Ok(f64::from_bits(bits.swap_bytes()))
+        Ok(codec::float_from_bits(self.read_uint()?))
     }
     
+    /// Reads a float into an `f32`. Fails, as Go does, when the value is finite but out of
+    /// `f32` range; otherwise it is rounded to the nearest `f32`.
+    pub fn read_float32(&mut self) -> Result<f32> {
+        let v = self.read_float()?;
+        if v.is_finite() && v.abs() > f32::MAX as f64 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("float32 overflow: {}", v)));
+        }
+        Ok(v as f32)
+    }
+
+    /// Reads a bool, sent as the uint 0 or 1. A value of 2 or more is an error unless
+    /// `set_lenient_bool` is on, in which case it is true.
     #[inline]
     pub fn read_bool(&mut self) -> Result<bool> {
-        match self.read_uint()? {
-            0 => Ok(false),
-            1 => Ok(true),
-            _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "integer overflow")),
+        let bits = self.read_uint()?;
+        if self.lenient_bool {
+            return Ok(bits != 0);
         }
+        Ok(codec::bool_from_bits(bits)?)
     }
     
     pub fn read_bytes(&mut self) -> Result<Vec<u8>> {
-        let len = self.read_uint()? as usize;
-        let mut buf = vec![0; len];
-        self.read_exact_internal(&mut buf)?;
-        Ok(buf)
+        let len = self.read_uint()?;
+        let len = usize::try_from(len).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("byte count {} out of range", len)))?;
+        self.read_exact_bytes(len)
     }
     
     pub fn read_exact_bytes(&mut self, len: usize) -> Result<Vec<u8>> {
-        let mut buf = vec![0; len];
-        self.read_exact_internal(&mut buf)?;
+        // `len` comes off the wire, so the buffer grows as bytes arrive instead of being
+        // allocated up front: a corrupt count runs out of input rather than memory.
+        let mut buf = Vec::with_capacity(len.min(READ_CHUNK));
+        while buf.len() < len {
+            let start = buf.len();
+            buf.resize(start + (len - start).min(READ_CHUNK), 0);
+            self.read_exact_internal(&mut buf[start..])?;
+        }
         Ok(buf)
     }
 
+    // Reads and drops `n` raw bytes, e.g. the rest of a message.
+    fn skip_raw(&mut self, mut n: usize) -> Result<()> {
+        let mut chunk = [0u8; 512];
+        while n > 0 {
+            let len = n.min(chunk.len());
+            self.read_raw_exact(&mut chunk[..len])?;
+            n -= len;
+        }
+        Ok(())
+    }
+
     pub fn read_string(&mut self) -> Result<String> {
         let bytes = self.read_bytes()?;
-        String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(s),
+            Err(e) if self.lossy_strings => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    /// Iterates over the remaining values in the stream. Iteration ends at a clean end of
+    /// stream, or after yielding the first error.
+    pub fn values(&mut self) -> Values<'_, R> {
+        Values { decoder: self, done: false }
+    }
+
+    /// Decodes every remaining value. Any malformed input is an `Err`, never a panic,
+    /// which is what the `decode` fuzz target checks.
+    pub fn decode_all(&mut self) -> Result<Vec<Value>> {
+        self.values().collect()
     }
 
     pub fn read_next(&mut self) -> Result<Option<Value>> {
         if self.current_msg_remaining > 0 {
-            let mut drain = vec![0; self.current_msg_remaining];
-            self.read_raw_exact(&mut drain)?;
+            self.skip_raw(self.current_msg_remaining)?;
             self.current_msg_remaining = 0;
         }
 
         loop {
-            let msg_len_res = self.read_raw_uint();
-            if let Err(e) = msg_len_res {
-                 if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                     return Ok(None);
-                 }
-                 return Err(e);
-            }
-            let msg_len = msg_len_res? as usize;
+            let Some(msg_len) = self.read_message_len()? else {
+                return Ok(None);
+            };
             self.current_msg_remaining = msg_len;
             
-            let type_id = self.read_int()?;
+            let type_id = self.read_int().map_err(truncated)?;
             
             if type_id < 0 {
-                let def_id = -type_id;
-                let schema = self.decode_wire_type()?;
-                self.types.insert(def_id, schema);
-                
+                self.register_type(-type_id).map_err(truncated)?;
+
                 if self.current_msg_remaining > 0 {
-                     let mut drain = vec![0; self.current_msg_remaining];
-                     self.read_raw_exact(&mut drain)?;
+                     self.skip_raw(self.current_msg_remaining)?;
                      self.current_msg_remaining = 0;
                 }
                 continue;
             } else {
                  if let Some(schema) = self.types.get(&type_id).cloned() {
-                     if type_id == 64 {
-                         let b = self.read_u8()?;
-                         if b != 0 {
-                             self.stash.push(b);
-                         }
-                    }
+                    self.read_singleton_delta(&schema).map_err(truncated)?;
                     
-                    let val = self.decode_value(&schema)?;
+                    let val = self.decode_value_of(type_id, &schema).map_err(truncated)?;
                     
                     if self.current_msg_remaining > 0 {
-                         let mut drain = vec![0; self.current_msg_remaining];
-                         self.read_raw_exact(&mut drain)?;
+                         self.skip_raw(self.current_msg_remaining)?;
                          self.current_msg_remaining = 0;
                     }
                     
@@ -245,7 +585,71 @@ impl<R: std::io::Read> Decoder<R> {
         }
     }
     
-    fn decode_wire_type(&mut self) -> Result<TypeSchema> {
+    /// Reads the next value message without decoding it, for passing values through without
+    /// knowing their types. Type definitions along the way are registered as usual, and the
+    /// returned message carries the definitions its value depends on.
+    pub fn read_raw_message(&mut self) -> Result<Option<RawMessage>> {
+        if self.current_msg_remaining > 0 {
+            self.skip_raw(self.current_msg_remaining)?;
+            self.current_msg_remaining = 0;
+        }
+
+        loop {
+            self.current_msg_remaining = match self.read_message_len()? {
+                Some(len) => len,
+                None => return Ok(None),
+            };
+
+            let type_id = self.read_int()?;
+            if type_id < 0 {
+                self.register_type(-type_id)?;
+            } else {
+                let schema = self.schema_for(type_id)?;
+                self.capture = Some(Capture::default());
+                let walked = self.read_singleton_delta(&schema).and_then(|_| self.skip_value(&schema));
+                let capture = self.capture.take().unwrap_or_default();
+                walked?;
+
+                if self.current_msg_remaining > 0 {
+                    self.skip_raw(self.current_msg_remaining)?;
+                    self.current_msg_remaining = 0;
+                }
+
+                let definitions = self.definitions_for(std::iter::once(type_id).chain(capture.type_ids));
+                return Ok(Some(RawMessage { type_id, payload: capture.bytes, definitions }));
+            }
+
+            if self.current_msg_remaining > 0 {
+                self.skip_raw(self.current_msg_remaining)?;
+                self.current_msg_remaining = 0;
+            }
+        }
+    }
+
+    // Encoded definitions of the given types and every user type they refer to.
+    fn definitions_for(&self, roots: impl Iterator<Item = i64>) -> Vec<(i64, Vec<u8>)> {
+        let mut pending: Vec<i64> = roots.collect();
+        pending.reverse();
+        let mut seen = std::collections::HashSet::new();
+        let mut definitions = Vec::new();
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            // Predefined types have no definition to carry.
+            let Some(wire) = self.raw_types.get(&id) else { continue };
+            definitions.push((id, wire.clone()));
+            match self.types.get(&id) {
+                Some(TypeSchema::Map(key, elem)) => pending.extend([*elem, *key]),
+                Some(TypeSchema::Slice(elem) | TypeSchema::Array(elem, _)) => pending.push(*elem),
+                Some(TypeSchema::Struct(fields)) => pending.extend(fields.iter().rev().map(|f| f.1)),
+                _ => {}
+            }
+        }
+        definitions
+    }
+
+    pub(crate) fn decode_wire_type(&mut self) -> Result<TypeSchema> {
          let mut schema = TypeSchema::Interface; 
          let mut field_num = -1;
          loop {
@@ -254,16 +658,106 @@ impl<R: std::io::Read> Decoder<R> {
              field_num += delta as i64;
              
              match field_num {
-                 0 => { return Err(std::io::Error::new(std::io::ErrorKind::Other, "ArrayT not impl")); }
-                 1 => { return Err(std::io::Error::new(std::io::ErrorKind::Other, "SliceT not impl")); }
+                 0 => { schema = self.decode_array_type()?; }
+                 1 => { schema = self.decode_slice_type()?; }
                  2 => { schema = self.decode_struct_type()?; }
                  3 => { schema = self.decode_map_type()?; }
-                 4 => { return Err(std::io::Error::new(std::io::ErrorKind::Other, "GobEncoderT not impl")); }
-                 _ => { return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Unknown WireType field {}", field_num))); }
+                 // GobEncoderT, BinaryMarshalerT, TextMarshalerT: all a byte slice on the wire.
+                 4..=6 => { schema = self.decode_gob_encoder_type()?; }
+                 _ => { return Err(std::io::Error::other(format!("Unknown WireType field {}", field_num))); }
              }
          }
     }
 
+    // Reads a definition body for type `id` into the registry, keeping its encoded bytes.
+    fn register_type(&mut self, id: i64) -> Result<()> {
+        let outer = self.capture.replace(Capture::default());
+        let schema = self.decode_wire_type();
+        let wire = self.capture.take().unwrap_or_default().bytes;
+        self.capture = outer;
+        let schema = schema?;
+        if let Some(existing) = self.types.get(&id).filter(|existing| self.strict_types && **existing != schema) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+                "type id {} redefined: was {:?}, now {:?}", id, existing, schema)));
+        }
+        self.types.insert(id, schema);
+        self.raw_types.insert(id, wire);
+        Ok(())
+    }
+
+    // CommonType { Name, Id }: only informational, the id is already in the message header.
+    fn skip_common_type(&mut self) -> Result<()> {
+        self.read_common_type_name().map(drop)
+    }
+
+    fn read_common_type_name(&mut self) -> Result<String> {
+        let mut name = String::new();
+        let mut ct_field = -1;
+        loop {
+            let ct_delta = self.read_uint()?;
+            if ct_delta == 0 { return Ok(name); }
+            ct_field += ct_delta as i64;
+            match ct_field {
+                0 => { name = self.read_string()?; }
+                1 => { let _ = self.read_int()?; }
+                _ => {}
+            }
+        }
+    }
+
+    fn decode_gob_encoder_type(&mut self) -> Result<TypeSchema> {
+        let mut name = String::new();
+        let mut field_num = -1;
+        loop {
+            let delta = self.read_uint()?;
+            if delta == 0 { break; }
+            field_num += delta as i64;
+            match field_num {
+                0 => name = self.read_common_type_name()?,
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown gobEncoderType field {}", field_num))),
+            }
+        }
+        Ok(TypeSchema::GobEncoder(name))
+    }
+
+    fn decode_array_type(&mut self) -> Result<TypeSchema> {
+        let mut elem_id = 0;
+        let mut len = 0;
+        let mut field_num = -1;
+        loop {
+            let delta = self.read_uint()?;
+            if delta == 0 { break; }
+            field_num += delta as i64;
+            match field_num {
+                0 => self.skip_common_type()?,
+                1 => { elem_id = self.read_int()?; }
+                2 => {
+                    len = usize::try_from(self.read_int()?).map_err(|_| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, "negative array length")
+                    })?;
+                }
+                _ => {}
+            }
+        }
+        Ok(TypeSchema::Array(elem_id, len))
+    }
+
+    fn decode_slice_type(&mut self) -> Result<TypeSchema> {
+        let mut elem_id = 0;
+        let mut field_num = -1;
+        loop {
+            let delta = self.read_uint()?;
+            if delta == 0 { break; }
+            field_num += delta as i64;
+            match field_num {
+                0 => self.skip_common_type()?,
+                1 => { elem_id = self.read_int()?; }
+                _ => {}
+            }
+        }
+        Ok(TypeSchema::Slice(elem_id))
+    }
+
     fn decode_map_type(&mut self) -> Result<TypeSchema> {
         let mut key_id = 0;
         let mut elem_id = 0;
@@ -273,19 +767,7 @@ impl<R: std::io::Read> Decoder<R> {
             if delta == 0 { break; }
             field_num += delta as i64;
             match field_num {
-                0 => {
-                    let mut ct_field = -1;
-                    loop {
-                        let ct_delta = self.read_uint()?;
-                        if ct_delta == 0 { break; }
-                        ct_field += ct_delta as i64;
-                        match ct_field {
-                            0 => { let _ = self.read_string()?; }
-                            1 => { let _ = self.read_int()?; }
-                            _ => {}
-                        }
-                    }
-                }
+                0 => self.skip_common_type()?,
                 1 => { key_id = self.read_int()?; }
                 2 => { elem_id = self.read_int()?; }
                 _ => {}
@@ -302,19 +784,7 @@ impl<R: std::io::Read> Decoder<R> {
              if delta == 0 { break; }
              field_num += delta as i64;
              match field_num {
-                 0 => {
-                     let mut ct_field = -1;
-                     loop {
-                         let ct_delta = self.read_uint()?;
-                         if ct_delta == 0 { break; }
-                         ct_field += ct_delta as i64;
-                         match ct_field {
-                             0 => { let _ = self.read_string()?; } 
-                             1 => { let _ = self.read_int()?; }
-                             _ => {}
-                         }
-                     }
-                 }
+                 0 => self.skip_common_type()?,
                  1 => {
                      let count = self.read_uint()?;
                      for _ in 0..count {
@@ -340,20 +810,70 @@ impl<R: std::io::Read> Decoder<R> {
          Ok(TypeSchema::Struct(fields))
     }
     
+    // Decodes a value whose wire type id is known, keeping it raw if that id was asked for.
+    fn decode_value_of(&mut self, type_id: i64, schema: &TypeSchema) -> Result<Value> {
+        if !self.raw_type_ids.contains(&type_id) {
+            return self.decode_value(schema);
+        }
+        let outer = self.capture.replace(Capture::default());
+        let walked = self.skip_value(schema);
+        let inner = self.capture.take().unwrap_or_default();
+        self.capture = outer;
+        walked?;
+        let deps = self.raw_deps.entry(type_id).or_default();
+        for id in &inner.type_ids {
+            if !deps.contains(id) {
+                deps.push(*id);
+            }
+        }
+        if let Some(outer) = &mut self.capture {
+            outer.bytes.extend_from_slice(&inner.bytes);
+            outer.type_ids.extend_from_slice(&inner.type_ids);
+        }
+        Ok(Value::Raw(type_id, inner.bytes))
+    }
+
     fn decode_value(&mut self, schema: &TypeSchema) -> Result<Value> {
+        self.nested(|decoder| decoder.decode_value_body(schema))
+    }
+
+    fn decode_value_body(&mut self, schema: &TypeSchema) -> Result<Value> {
         match schema {
             TypeSchema::Bool => Ok(Value::Bool(self.read_bool()?)),
             TypeSchema::Int => Ok(Value::Int(self.read_int()?)),
             TypeSchema::Uint => Ok(Value::Uint(self.read_uint()?)),
             TypeSchema::Float => Ok(Value::Float(self.read_float()?)),
+            TypeSchema::Complex => Ok(Value::Complex(self.read_float()?, self.read_float()?)),
             TypeSchema::String => Ok(Value::String(self.read_string()?)),
             TypeSchema::ByteSlice => Ok(Value::Bytes(self.read_bytes()?)),
+            TypeSchema::GobEncoder(name) => Ok(Value::GobEncoded(name.clone(), self.read_bytes()?)),
             TypeSchema::Map(kid, vid) => {
                 let count = self.read_uint()?;
                 self.decode_map_body(count, *kid, *vid)
             }
+            TypeSchema::Slice(elem_id) => {
+                // Elements are bare values of the element type: a []User holds
+                // struct bodies back to back, not interfaces.
+                let elem_schema = self.schema_for(*elem_id)?;
+                let count = self.read_uint()?;
+                let mut items = Vec::new();
+                for _ in 0..count {
+                    items.push(self.decode_value_of(*elem_id, &elem_schema)?);
+                }
+                Ok(Value::Array(items))
+            }
+            TypeSchema::Array(elem_id, len) => {
+                // Like a slice, but Go checks the count against the array type.
+                let elem_schema = self.schema_for(*elem_id)?;
+                self.read_array_len(*len)?;
+                let mut items = Vec::with_capacity((*len).min(1024));
+                for _ in 0..*len {
+                    items.push(self.decode_value_of(*elem_id, &elem_schema)?);
+                }
+                Ok(Value::Array(items))
+            }
             TypeSchema::Struct(fields) => {
-                let mut struct_val = BTreeMap::new();
+                let mut struct_val = Vec::new();
                 let mut field_idx = -1;
                 loop {
                     let delta = self.read_uint()?;
@@ -362,8 +882,8 @@ impl<R: std::io::Read> Decoder<R> {
                     if field_idx >= 0 && (field_idx as usize) < fields.len() {
                         let (_, type_id, name) = &fields[field_idx as usize];
                         if let Some(field_schema) = self.types.get(type_id).cloned() {
-                             let val = self.decode_value(&field_schema)?;
-                             struct_val.insert(name.clone(), val);
+                             let val = self.decode_value_of(*type_id, &field_schema)?;
+                             struct_val.push((name.clone(), val));
                         } else {
                              return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown type for struct field {}", name)));
                         }
@@ -371,13 +891,17 @@ impl<R: std::io::Read> Decoder<R> {
                         return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown field index {} for Struct", field_idx)));
                     }
                 }
-                Ok(Value::Struct("Struct".to_string(), struct_val)) 
+                // Deltas are positive, so the fields arrive in definition order.
+                if self.preserve_field_order {
+                    return Ok(Value::OrderedStruct("Struct".to_string(), struct_val));
+                }
+                Ok(Value::Struct("Struct".to_string(), struct_val.into_iter().collect()))
             }
             TypeSchema::Interface => {
                 self.decode_interface()
             }
             _ => {
-                Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Unimplemented decoder for {:?}", schema)))
+                Err(std::io::Error::other(format!("Unimplemented decoder for {:?}", schema)))
             }
         }
     }
@@ -385,63 +909,299 @@ impl<R: std::io::Read> Decoder<R> {
     fn decode_map_body(&mut self, count: u64, kid: i64, vid: i64) -> Result<Value> {
         let k_schema = self.types.get(&kid).cloned().unwrap_or(TypeSchema::Custom(kid));
         let v_schema = self.types.get(&vid).cloned().unwrap_or(TypeSchema::Custom(vid));
+        // Only CollectIntoArray cares whether a key has repeated before.
+        let collecting = self.duplicate_keys == DuplicateKeyPolicy::CollectIntoArray;
+        if self.preserve_map_order {
+            let mut entries: Vec<(Value, Value)> = Vec::new();
+            let mut index: BTreeMap<Value, usize> = BTreeMap::new();
+            let mut collected = BTreeSet::new();
+            for _ in 0..count {
+                let k = self.decode_value_of(kid, &k_schema)?;
+                let v = self.decode_value_of(vid, &v_schema)?;
+                match index.get(&k) {
+                    Some(&i) => {
+                        let first = collecting && collected.insert(i);
+                        self.resolve_duplicate(&k, &mut entries[i].1, v, first)?;
+                    }
+                    None => {
+                        index.insert(k.clone(), entries.len());
+                        entries.push((k, v));
+                    }
+                }
+            }
+            return Ok(Value::OrderedMap(entries));
+        }
         let mut map = BTreeMap::new();
+        let mut collected = BTreeSet::new();
         for _ in 0..count {
-            let k = self.decode_value(&k_schema)?;
-            let v = self.decode_value(&v_schema)?;
-            map.insert(k, v);
+            let k = self.decode_value_of(kid, &k_schema)?;
+            let v = self.decode_value_of(vid, &v_schema)?;
+            if let Some(existing) = map.get_mut(&k) {
+                let first = collecting && !collected.contains(&k);
+                self.resolve_duplicate(&k, existing, v, first)?;
+                if first { collected.insert(k); }
+            } else {
+                map.insert(k, v);
+            }
         }
         Ok(Value::Map(map))
     }
 
-    pub fn decode_interface(&mut self) -> Result<Value> {
-        let name = self.read_string()?;
-        if name.is_empty() { return Ok(Value::Nil); }
-        
-        let mut type_id = self.read_int()?;
-        if type_id < 0 {
-            let def_id = -type_id;
-            let schema = self.decode_wire_type()?;
-            self.types.insert(def_id, schema);
-            type_id = def_id;
+    // Applies the duplicate key policy to a key seen again. Under CollectIntoArray, `first`
+    // is true the first time this key repeats, when `existing` still holds the original value.
+    fn resolve_duplicate(&self, key: &Value, existing: &mut Value, value: Value, first: bool) -> Result<()> {
+        match self.duplicate_keys {
+            DuplicateKeyPolicy::LastWins => *existing = value,
+            DuplicateKeyPolicy::FirstWins => {}
+            DuplicateKeyPolicy::Error => {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("duplicate map key {}", key)));
+            }
+            DuplicateKeyPolicy::CollectIntoArray => {
+                if first {
+                    let original = std::mem::replace(existing, Value::Nil);
+                    *existing = Value::Array(vec![original, value]);
+                } else if let Value::Array(items) = existing {
+                    items.push(value);
+                }
+            }
         }
+        Ok(())
+    }
 
-        let len = self.read_uint()? as usize;
-        
-        let b = self.read_u8()?;
-        if b != 0 {
-            self.stash.push(b);
-        }
+    /// Reads and discards one value of the given schema.
+    pub fn skip_value(&mut self, schema: &TypeSchema) -> Result<()> {
+        self.nested(|decoder| decoder.skip_value_body(schema))
+    }
 
-        let result;
-        match name.as_str() {
-            "string" => { result = Ok(Value::String(self.read_string()?)); }
-            "int" | "int64" | "uint" => { result = Ok(Value::Int(self.read_int()?)); }
-            "bool" => { result = Ok(Value::Bool(self.read_bool()?)); }
-            "float64" => { result = Ok(Value::Float(self.read_float()?)); }
-            _ => {
-                if let Some(schema) = self.types.get(&type_id).cloned() {
-                    if len > 0 {
-                        let mut val = self.decode_value(&schema)?;
-                        if let Value::Struct(_, fields) = val {
-                            val = Value::Struct(name.clone(), fields);
-                        }
-                        result = Ok(val);
+    fn skip_value_body(&mut self, schema: &TypeSchema) -> Result<()> {
+        match schema {
+            TypeSchema::Bool | TypeSchema::Int | TypeSchema::Uint | TypeSchema::Float => {
+                self.read_uint()?;
+            }
+            TypeSchema::Complex => {
+                self.read_uint()?;
+                self.read_uint()?;
+            }
+            TypeSchema::ByteSlice | TypeSchema::String | TypeSchema::GobEncoder(_) => {
+                let len = self.read_uint()? as usize;
+                self.skip_bytes(len)?;
+            }
+            TypeSchema::Interface => {
+                let name = self.read_string()?;
+                if !name.is_empty() {
+                    let type_id = self.read_concrete_type_id()?;
+                    let len = self.read_uint()? as usize;
+                    if self.capture.is_some() {
+                        // Walk the value so interfaces nested in it are seen too.
+                        let schema = self.schema_for(type_id)?;
+                        self.read_singleton_delta(&schema)?;
+                        self.skip_value(&schema)?;
                     } else {
-                        result = Ok(Value::Nil);
+                        self.skip_bytes(len)?;
                     }
-                } else {
-                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown concrete type definition for interface: {} (ID {})", name, type_id)));
                 }
             }
+            TypeSchema::Map(kid, vid) => {
+                let k_schema = self.schema_for(*kid)?;
+                let v_schema = self.schema_for(*vid)?;
+                let count = self.read_uint()?;
+                for _ in 0..count {
+                    self.skip_value(&k_schema)?;
+                    self.skip_value(&v_schema)?;
+                }
+            }
+            TypeSchema::Slice(elem_id) => {
+                let elem_schema = self.schema_for(*elem_id)?;
+                let count = self.read_uint()?;
+                for _ in 0..count {
+                    self.skip_value(&elem_schema)?;
+                }
+            }
+            TypeSchema::Array(elem_id, len) => {
+                let elem_schema = self.schema_for(*elem_id)?;
+                self.read_array_len(*len)?;
+                for _ in 0..*len {
+                    self.skip_value(&elem_schema)?;
+                }
+            }
+            TypeSchema::Struct(fields) => {
+                let mut field_idx = -1;
+                loop {
+                    let delta = self.read_uint()?;
+                    if delta == 0 { break; }
+                    field_idx += delta as i64;
+                    let type_id = usize::try_from(field_idx).ok().and_then(|i| fields.get(i)).map(|f| f.1).ok_or_else(|| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown field index {} for Struct", field_idx))
+                    })?;
+                    let field_schema = self.schema_for(type_id)?;
+                    self.skip_value(&field_schema)?;
+                }
+            }
+            TypeSchema::Custom(id) => {
+                return Err(std::io::Error::other(format!("Cannot skip value of unknown type {}", id)));
+            }
+        }
+        Ok(())
+    }
+
+    fn read_array_len(&mut self, len: usize) -> Result<()> {
+        let count = self.read_uint()?;
+        if count != len as u64 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("array length mismatch: type has {}, value has {}", len, count)));
+        }
+        Ok(())
+    }
+
+    fn skip_bytes(&mut self, mut len: usize) -> Result<()> {
+        let mut buf = [0u8; 256];
+        while len > 0 {
+            let n = len.min(buf.len());
+            self.read_exact_internal(&mut buf[..n])?;
+            len -= n;
         }
+        Ok(())
+    }
+
+    // Structs are sent as-is; every other type, when it stands alone as a message or sits in
+    // an interface, is a singleton preceded by a zero field delta.
+    fn read_singleton_delta(&mut self, schema: &TypeSchema) -> Result<()> {
+        if !matches!(schema, TypeSchema::Struct(_)) {
+            let delta = self.read_uint()?;
+            if delta != 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("corrupted data: non-zero delta {} for singleton", delta)));
+            }
+        }
+        Ok(())
+    }
+
+    fn schema_for(&self, type_id: i64) -> Result<TypeSchema> {
+        self.types.get(&type_id).cloned().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown type ID: {}", type_id))
+        })
+    }
+
+    // Wire type id of field `field_index` of the struct currently being decoded, if known.
+    fn active_field_type(&self, field_index: i64) -> Option<i64> {
+        let type_id = (*self.active_types.last()?)?;
+        match self.types.get(&type_id)? {
+            TypeSchema::Struct(fields) => usize::try_from(field_index).ok().and_then(|i| fields.get(i)).map(|f| f.1),
+            _ => None,
+        }
+    }
+
+    /// Wire type of the value generated code is decoding, when the stream says what it is:
+    /// the message's type under `decode_into`, or a struct field's type. `None` for bare
+    /// bodies read with `new_unframed`. `#[Gob(interpret_as = "map[...]")]` structs use it to
+    /// take a Go struct as well as a map.
+    pub fn active_schema(&self) -> Option<&TypeSchema> {
+        self.types.get(&(*self.active_types.last()?)?)
+    }
+
+    /// Decodes one value of wire type `type_id`, as numbered by this stream, to a `Value`.
+    pub fn decode_typed(&mut self, type_id: i64) -> Result<Value> {
+        let schema = self.schema_for(type_id)?;
+        self.decode_value_of(type_id, &schema)
+    }
+
+    /// Called by generated code before decoding field `field_index` of the current struct,
+    /// so a nested struct knows its own wire type. Fails once values nest past `max_depth`,
+    /// which is what bounds recursive types; on success it must be paired with `exit_field`.
+    #[doc(hidden)]
+    pub fn enter_field(&mut self, field_index: i64) -> Result<()> {
+        self.descend()?;
+        let field_type = self.active_field_type(field_index);
+        self.active_types.push(field_type);
+        Ok(())
+    }
+
+    /// Called by generated code before decoding field `field_index` of the current struct:
+    /// fails if the stream's definition of the struct gives the field a type other than
+    /// `expected`, the predefined id its Rust type is always sent with (its `GOB_FIELDS`
+    /// entry). An `expected` of 0, or a struct whose definition isn't known, checks nothing.
+    #[doc(hidden)]
+    pub fn check_field_type(&self, field_index: i64, expected: i64, struct_name: &str, field_name: &str) -> Result<()> {
+        match self.active_field_type(field_index) {
+            Some(actual) if expected != 0 && actual != expected => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+                "{} field {}: expected type id {}, got {}", struct_name, field_name, expected, actual))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like `enter_field`, for a nested value whose wire type isn't known.
+    #[doc(hidden)]
+    pub fn enter_unknown(&mut self) -> Result<()> {
+        self.descend()?;
+        self.active_types.push(None);
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    pub fn exit_field(&mut self) {
+        self.active_types.pop();
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// Skips field `field_index` of the struct currently being decoded, using the field type
+    /// from the struct's wire definition. Used for fields the Rust type doesn't declare.
+    pub fn skip_field(&mut self, field_index: i64) -> Result<()> {
+        let type_id = self.active_field_type(field_index).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Cannot skip field {}: the struct's wire type is unknown", field_index))
+        })?;
+        let schema = self.schema_for(type_id)?;
+        self.skip_value(&schema)
+    }
+
+    // The concrete type's definitions (if not sent before) precede its id, and Go may flush
+    // them as separate messages in the middle of the enclosing value. They are not part of
+    // the value, so a raw capture drops them.
+    fn read_concrete_type_id(&mut self) -> Result<i64> {
+        loop {
+            let mark = self.capture.as_ref().map(|c| c.bytes.len());
+            let type_id = self.read_int()?;
+            if type_id >= 0 {
+                if let Some(capture) = &mut self.capture {
+                    capture.type_ids.push(type_id);
+                }
+                return Ok(type_id);
+            }
+            if let (Some(capture), Some(mark)) = (&mut self.capture, mark) {
+                capture.bytes.truncate(mark);
+            }
+            self.register_type(-type_id)?;
+        }
+    }
+
+    /// Decodes an interface value. The concrete type is looked up by its wire id, not the
+    /// name Go sent, so aliases such as `byte`, `uintptr` and `rune` decode as uint or int.
+    pub fn decode_interface(&mut self) -> Result<Value> {
+        let name = self.read_string()?;
+        if name.is_empty() { return Ok(Value::Nil); }
         
-        result
+        let type_id = self.read_concrete_type_id()?;
+
+        // Byte count of the value; only useful for skipping it.
+        let _len = self.read_uint()?;
+
+        let schema = self.types.get(&type_id).cloned().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown concrete type definition for interface: {} (ID {})", name, type_id))
+        })?;
+
+        self.read_singleton_delta(&schema)?;
+
+        // The registered name is the one Go code knows the type by.
+        Ok(match self.decode_value(&schema)? {
+            Value::Struct(_, fields) => Value::Struct(name, fields),
+            Value::OrderedStruct(_, fields) => Value::OrderedStruct(name, fields),
+            Value::GobEncoded(_, bytes) => Value::GobEncoded(name, bytes),
+            // A complex64 shares id 7; its parts were float32s before Go widened them.
+            Value::Complex(re, im) if name == "complex64" => Value::Complex(re as f32 as f64, im as f32 as f64),
+            val => val,
+        })
     }
     
     pub fn parse(&mut self) -> Result<()> {
         while let Some(v) = self.read_next()? {
-            println!("Decoded Value: {:?}", v);
+            println!("Decoded Value: {}", v);
         }
         Ok(())
     }
@@ -452,26 +1212,20 @@ impl<R: std::io::Read> Decoder<R> {
         
         loop {
             // Read Msg Length
-            let msg_len_res = self.read_raw_uint();
-            if let Err(e) = msg_len_res {
-                 return Err(e); 
-            }
-            let msg_len = msg_len_res? as usize;
+            let msg_len = self.read_message_len()?.ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "end of gob stream")
+            })?;
             
             self.current_msg_remaining = msg_len;
             
             let type_id = self.read_int()?;
-            println!("DEBUG: Msg Len: {}, Type ID: {}", msg_len, type_id);
             
             if type_id < 0 {
                 // Type definition
-                let def_id = -type_id;
-                let schema = self.decode_wire_type()?;
-                self.types.insert(def_id, schema);
-                
+                self.register_type(-type_id)?;
+
                 if self.current_msg_remaining > 0 {
-                    let mut drain = vec![0; self.current_msg_remaining];
-                    self.read_raw_exact(&mut drain)?;
+                    self.skip_raw(self.current_msg_remaining)?;
                     self.current_msg_remaining = 0;
                 }
                 continue;
@@ -479,28 +1233,23 @@ impl<R: std::io::Read> Decoder<R> {
                 // Value message!
                 // We are now positioned at the start of the value content.
                 
-                // Hack from read_next: Special handling for type 64?
-                if type_id == 64 {
-                     let b = self.read_u8()?;
-                     if b != 0 {
-                         self.stash.push(b);
-                     }
-                }
+                // Top-level non-struct values carry a zero singleton delta before the body.
+                let schema = self.schema_for(type_id)?;
+                self.read_singleton_delta(&schema)?;
 
                 // We delegate to T::decode.
                 // Note: We ignore type_id for now, assuming T knows how to decode itself
                 // matching the wire format. In a robust implementation, we would check type_id compatibility.
                 
-                // Also, we need to handle the `ignore` byte if type_id == 64? No, that's handled inside decode_interface usually?
-                // Wait, type_id 64 is likely not used for custom structs directly unless they are wire types?
-                // For standard values, we just decode.
-                
-                let val = T::decode(self)?;
+                let depth = self.active_types.len();
+                self.active_types.push(Some(type_id));
+                let val = T::decode(self);
+                self.active_types.truncate(depth);
+                let val = val?;
                 
                 // Ensure we drain any remaining bytes of the message
                 if self.current_msg_remaining > 0 {
-                     let mut drain = vec![0; self.current_msg_remaining];
-                     self.read_raw_exact(&mut drain)?;
+                     self.skip_raw(self.current_msg_remaining)?;
                      self.current_msg_remaining = 0;
                 }
                 
@@ -510,8 +1259,93 @@ impl<R: std::io::Read> Decoder<R> {
     }
 }
 
+// Running out of input once a message has started means the stream was cut short.
+pub(crate) fn truncated(e: std::io::Error) -> std::io::Error {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "gob stream truncated in the middle of a message")
+    } else {
+        e
+    }
+}
+
+/// Iterator returned by [`Decoder::values`].
+pub struct Values<'d, R: std::io::Read> {
+    decoder: &'d mut Decoder<R>,
+    done: bool,
+}
+
+impl<R: std::io::Read> Iterator for Values<'_, R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.decoder.read_next() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<R: std::io::Read> Decoder<std::io::BufReader<R>> {
+    /// Creates a decoder that wraps `reader` in a `BufReader`.
+    /// Header and varint decoding read one byte at a time, so an unbuffered `File` or socket
+    /// would otherwise pay a syscall per byte.
+    pub fn new_buffered(reader: R) -> Self {
+        Self::new(std::io::BufReader::new(reader))
+    }
+}
+
+impl<'a> Decoder<&'a [u8]> {
+    /// Reads a string, borrowing it from the input slice instead of allocating when the bytes
+    /// lie within the current message. Falls back to an owned `String` otherwise.
+    pub fn read_str_borrowed(&mut self) -> Result<Cow<'a, str>> {
+        let len = self.read_uint()? as usize;
+        let in_message = !self.framed || len <= self.current_msg_remaining;
+        if self.capture.is_none() && in_message && len <= self.reader.len() {
+            let (bytes, rest) = self.reader.split_at(len);
+            self.reader = rest;
+            if self.framed {
+                self.current_msg_remaining -= len;
+            }
+            if self.lossy_strings {
+                return Ok(String::from_utf8_lossy(bytes));
+            }
+            return std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+        }
+        let bytes = self.read_exact_bytes(len)?;
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(Cow::Owned(s)),
+            Err(e) if self.lossy_strings => Ok(Cow::Owned(String::from_utf8_lossy(e.as_bytes()).into_owned())),
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
+    }
+}
+
 pub trait GobDecodable: Sized {
     fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self>;
+
+    /// Reads a slice of this type: the count, then each element. u8 reads a byte slice.
+    #[doc(hidden)]
+    fn decode_slice<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Vec<Self>> {
+        let count = decoder.read_uint()?;
+        // The count comes off the wire; don't trust it for the allocation.
+        let mut items = Vec::with_capacity(count.min(1024) as usize);
+        for _ in 0..count {
+            items.push(Self::decode(decoder)?);
+        }
+        Ok(items)
+    }
 }
 
 impl GobDecodable for bool {
@@ -538,18 +1372,206 @@ impl GobDecodable for f64 {
     }
 }
 
+impl GobDecodable for f32 {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        decoder.read_float32()
+    }
+}
+
 impl GobDecodable for String {
     fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
         decoder.read_string()
     }
 }
 
-impl GobDecodable for Vec<u8> {
+impl GobDecodable for u8 {
     fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        let v = decoder.read_uint()?;
+        u8::try_from(v).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("value {} out of range for uint8", v)))
+    }
+
+    fn decode_slice<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Vec<Self>> {
         decoder.read_bytes()
     }
 }
 
+// Values too large for the narrower widths are an error rather than wrapping.
+macro_rules! impl_decodable_int {
+    ($($ty:ty => $read:ident, $go:literal;)*) => {$(
+        impl GobDecodable for $ty {
+            fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+                let v = decoder.$read()?;
+                <$ty>::try_from(v).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("value {} out of range for {}", v, $go)))
+            }
+        }
+    )*};
+}
+
+impl_decodable_int! {
+    i8 => read_int, "int8";
+    i16 => read_int, "int16";
+    i32 => read_int, "int32";
+    isize => read_int, "int";
+    u16 => read_uint, "uint16";
+    u32 => read_uint, "uint32";
+    usize => read_uint, "uint";
+}
+
+/// A Go rune. Go allows any int32, but only Unicode scalar values are a `char`; a
+/// surrogate or a value past U+10FFFF is an error.
+impl GobDecodable for char {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        let v = decoder.read_int()?;
+        u32::try_from(v).ok().and_then(char::from_u32).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("value {} is not a valid char", v))
+        })
+    }
+}
+
+/// A Go array; the count must be exactly `N`, as Go requires for arrays.
+impl<T: GobDecodable, const N: usize> GobDecodable for [T; N] {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        decoder.read_array_len(N)?;
+        // Elements after a failed one are left unread; the error is returned below.
+        let mut error = None;
+        let items: [Option<T>; N] = std::array::from_fn(|_| match error {
+            Some(_) => None,
+            None => T::decode(decoder).map_err(|e| error = Some(e)).ok(),
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(items.map(|item| item.expect("every element was decoded"))),
+        }
+    }
+}
+
+impl<T: GobDecodable> GobDecodable for Vec<T> {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        T::decode_slice(decoder)
+    }
+}
+
+macro_rules! impl_decodable_pointer {
+    ($($ptr:ident),*) => {$(
+        impl<T: GobDecodable> GobDecodable for $ptr<T> {
+            fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+                T::decode(decoder).map($ptr::new)
+            }
+        }
+    )*};
+}
+
+impl_decodable_pointer!(Box, Rc, Arc);
+
+/// A Go string, always decoded into an owned `Cow`.
+impl GobDecodable for Cow<'_, str> {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        decoder.read_string().map(Cow::Owned)
+    }
+}
+
+/// A Go pointer field. A value on the wire is always `Some`; `#[Gob]` structs start
+/// such fields at `None`, so a field missing from the stream stays `None`.
+impl<T: GobDecodable> GobDecodable for Option<T> {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        T::decode(decoder).map(Some)
+    }
+}
+
+// Which element of a tuple wire field `field_num` is: by the field's "F<n>" name when the
+// stream says what the struct is, else by position.
+fn tuple_element<R: std::io::Read>(decoder: &Decoder<R>, field_num: i64) -> Option<usize> {
+    match decoder.active_schema() {
+        Some(TypeSchema::Struct(fields)) => {
+            let (_, _, name) = fields.get(usize::try_from(field_num).ok()?)?;
+            name.strip_prefix('F')?.parse().ok()
+        }
+        _ => usize::try_from(field_num).ok(),
+    }
+}
+
+macro_rules! impl_decodable_tuple {
+    ($($len:literal => ($($idx:tt $name:ident),+);)*) => {$(
+        /// A struct with fields "F0", "F1", ..., as tuples are encoded. Fields missing from
+        /// the stream, as Go leaves zero values out, are their `Default`.
+        impl<$($name: GobDecodable + Default),+> GobDecodable for ($($name,)+) {
+            fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+                let mut result = Self::default();
+                let mut field_num = -1i64;
+                loop {
+                    let delta = decoder.read_uint()?;
+                    if delta == 0 { break; }
+                    field_num += delta as i64;
+                    let Some(element) = tuple_element(decoder, field_num).filter(|&i| i < $len) else {
+                        if decoder.ignore_unknown_fields() {
+                            decoder.skip_field(field_num)?;
+                            continue;
+                        }
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+                            "Unknown field {} for {}-tuple", field_num, $len)));
+                    };
+                    decoder.enter_field(field_num)?;
+                    let decoded = match element {
+                        $($idx => $name::decode(decoder).map(|v| result.$idx = v),)+
+                        _ => unreachable!(),
+                    };
+                    decoder.exit_field();
+                    decoded?;
+                }
+                Ok(result)
+            }
+        }
+    )*};
+}
+
+impl_decodable_tuple! {
+    1 => (0 A);
+    2 => (0 A, 1 B);
+    3 => (0 A, 1 B, 2 C);
+    4 => (0 A, 1 B, 2 C, 3 D);
+    5 => (0 A, 1 B, 2 C, 3 D, 4 E);
+    6 => (0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+    7 => (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+    8 => (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+}
+
+// Reads a Go map's count and entries into `insert`; a repeated key is left to the map.
+fn decode_map<R, K, V>(decoder: &mut Decoder<R>, mut insert: impl FnMut(K, V)) -> Result<()>
+where
+    R: std::io::Read,
+    K: GobDecodable,
+    V: GobDecodable,
+{
+    let count = decoder.read_uint()?;
+    for _ in 0..count {
+        let k = K::decode(decoder)?;
+        let v = V::decode(decoder)?;
+        insert(k, v);
+    }
+    Ok(())
+}
+
+impl<K, V, S> GobDecodable for HashMap<K, V, S>
+where
+    K: GobDecodable + Eq + std::hash::Hash,
+    V: GobDecodable,
+    S: std::hash::BuildHasher + Default,
+{
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        let mut map = HashMap::default();
+        decode_map(decoder, |k, v| { map.insert(k, v); })?;
+        Ok(map)
+    }
+}
+
+impl<K: GobDecodable + Ord, V: GobDecodable> GobDecodable for BTreeMap<K, V> {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        decode_map(decoder, |k, v| { map.insert(k, v); })?;
+        Ok(map)
+    }
+}
+
 impl GobDecodable for Value {
     fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
         // We use read_next which handles message headers and type definitions.
@@ -622,3 +1644,560 @@ impl GobDecodable for Value {
         decoder.decode_interface()
     }
 }
+
+/// Parses a value Go sent as a string into its Rust type, for `#[gob(as_string)]` fields.
+#[doc(hidden)]
+pub fn parse_gob_string<T>(s: &str) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    s.parse().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData,
+        format!("cannot parse {:?} as {}: {}", s, std::any::type_name::<T>(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_buffered_decoder_reads_session() {
+        let file = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/normal-session.bin")).unwrap();
+        let mut dec = Decoder::new_buffered(file);
+        let value = dec.read_next().unwrap().expect("expected a value message");
+        let Value::Map(map) = value else { panic!("expected a map, got {:?}", value) };
+        assert_eq!(map.get(&Value::from("uname")), Some(&Value::from("dsotsen")));
+        assert_eq!(map.get(&Value::from("uid")), Some(&Value::Int(1)));
+        assert!(dec.read_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_interface_singleton_and_struct_values() {
+        let file = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/goth-session.bin")).unwrap();
+        let mut dec = Decoder::new_buffered(file);
+        let Some(Value::Map(map)) = dec.read_next().unwrap() else { panic!("expected a map") };
+        assert_eq!(map.get(&Value::from("uid")), Some(&Value::Int(3)));
+        assert_eq!(map.get(&Value::from("userHasTwoFactorAuth")), Some(&Value::Bool(false)));
+        let Some(Value::Struct(name, fields)) = map.get(&Value::from("_gothic_session")) else {
+            panic!("expected the session struct")
+        };
+        assert_eq!(name, "*sessions.Session");
+        assert_eq!(fields.get("ID"), Some(&Value::from("17634d7885249bfc")));
+    }
+
+    #[test]
+    fn test_interface_empty_struct() {
+        // map[interface{}]interface{}{"k": Empty{}}: the struct's body is just its terminator.
+        let mut stream = vec![0x0d, 0x7f, 0x04, 0x01, 0x02, 0xff, 0x80, 0x00, 0x01, 0x10, 0x01, 0x10, 0x00, 0x00];
+        stream.extend_from_slice(b"\x11\xff\x81\x03\x01\x01\x05Empty\x01\xff\x82\x00\x00\x00");
+        stream.extend_from_slice(b"\x1a\xff\x80\x00\x01\x06string\x0c\x03\x00\x01k\x05Empty\xff\x82\x01\x00");
+        let mut dec = Decoder::new(stream.as_slice());
+        let Some(Value::Map(map)) = dec.read_next().unwrap() else { panic!("expected a map") };
+        assert_eq!(map.get(&Value::from("k")), Some(&Value::Struct("Empty".to_string(), BTreeMap::new())));
+    }
+
+    #[test]
+    fn test_interface_gob_encoder_error() {
+        // map[string]interface{}{"err": &RPCError{...}}, where *RPCError is registered with
+        // gob and its GobEncode returns []byte("not found").
+        let mut stream = b"\x0d\x7f\x04\x01\x02\xff\x80\x00\x01\x0c\x01\x10\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x14\xff\x81\x05\x01\x01\x08RPCError\x01\xff\x82\x00\x00\x00");
+        stream.extend_from_slice(b"\x25\xff\x80\x00\x01\x03err\x0e*main.RPCError\xff\x82\x0b\x00\x09not found");
+        let mut dec = Decoder::new(stream.as_slice());
+        let Some(Value::Map(map)) = dec.read_next().unwrap() else { panic!("expected a map") };
+        let err = &map[&Value::from("err")];
+        assert_eq!(err, &Value::GobEncoded("*main.RPCError".to_string(), b"not found".to_vec()));
+        assert_eq!(err.as_error_message(), Some("not found"));
+        assert_eq!(dec.types()[&65], TypeSchema::GobEncoder("RPCError".to_string()));
+
+        // Re-encoded, it goes out as the same gobEncoderType under the interface name.
+        let buf = crate::to_vec(&Value::Map(map.clone())).unwrap();
+        assert_eq!(crate::from_slice(&buf).unwrap(), Value::Map(map));
+    }
+
+    #[test]
+    fn test_integer_map_keys() {
+        // map[int64]string{10: "ten", -1: "neg", 2: "two"}, entries in the order Go sent them.
+        let mut stream = b"\x0d\x7f\x04\x01\x02\xff\x80\x00\x01\x04\x01\x0c\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x13\xff\x80\x00\x03\x14\x03ten\x01\x03neg\x04\x03two");
+        let Some(Value::Map(map)) = Decoder::new(stream.as_slice()).read_next().unwrap() else {
+            panic!("expected a map")
+        };
+        let keys: Vec<_> = map.keys().cloned().collect();
+        assert_eq!(keys, [Value::Int(-1), Value::Int(2), Value::Int(10)]);
+        assert_eq!(map[&Value::Int(10)], Value::from("ten"));
+        let typed: BTreeMap<i64, String> = Decoder::new(stream.as_slice()).decode_into().unwrap();
+        assert_eq!(typed.into_iter().collect::<Vec<_>>(), [(-1, "neg".into()), (2, "two".into()), (10, "ten".into())]);
+
+        // map[uint32]bool{300: false, 7: true, 1: true}; uint32 goes on the wire as uint.
+        let mut stream = b"\x0d\x7f\x04\x01\x02\xff\x80\x00\x01\x06\x01\x02\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x0c\xff\x80\x00\x03\xfe\x01\x2c\x00\x07\x01\x01\x01");
+        let Some(Value::Map(map)) = Decoder::new(stream.as_slice()).read_next().unwrap() else {
+            panic!("expected a map")
+        };
+        let entries: Vec<_> = map.into_iter().collect();
+        assert_eq!(entries, [
+            (Value::Uint(1), Value::Bool(true)),
+            (Value::Uint(7), Value::Bool(true)),
+            (Value::Uint(300), Value::Bool(false)),
+        ]);
+    }
+
+    #[test]
+    fn test_read_str_borrowed() {
+        // A framed message holding the singleton string "hello".
+        let input: &[u8] = b"\x08\x0c\x00\x05hello";
+        let mut dec = Decoder::new(input);
+        assert_eq!(dec.read_int().unwrap(), 6);
+        assert_eq!(dec.read_uint().unwrap(), 0);
+        let s = dec.read_str_borrowed().unwrap();
+        assert!(matches!(s, Cow::Borrowed("hello")));
+
+        let mut dec = Decoder::new_unframed(&b"\x02\xff\xfe"[..]);
+        assert_eq!(dec.read_str_borrowed().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_slice_of_struct() {
+        // Go: type User struct { Name string; Age int }
+        //     enc.Encode([]User{{"ann", 30}, {"bob", 41}})
+        // Go sends the []User definition (id 65) before the User definition (id 64) it refers to.
+        let mut stream = b"\x0d\xff\x81\x02\x01\x02\xff\x82\x00\x01\xff\x80\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x22\x7f\x03\x01\x01\x04User\x01\xff\x80\x00\x01\x02\
+            \x01\x04Name\x01\x0c\x00\x01\x03Age\x01\x04\x00\x00\x00");
+        stream.extend_from_slice(b"\x14\xff\x82\x00\x02\x01\x03ann\x01\x3c\x00\x01\x03bob\x01\x52\x00");
+
+        let mut dec = Decoder::new(stream.as_slice());
+        let Some(Value::Array(users)) = dec.read_next().unwrap() else { panic!("expected an array") };
+        assert_eq!(users.len(), 2);
+        let Value::Struct(_, bob) = &users[1] else { panic!("expected a struct, got {:?}", users[1]) };
+        assert_eq!(bob.get("Name"), Some(&Value::from("bob")));
+        assert_eq!(bob.get("Age"), Some(&Value::Int(41)));
+        assert!(matches!(dec.types().get(&65), Some(TypeSchema::Slice(64))));
+        assert!(dec.read_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_earlier_and_recursive_type_references() {
+        // Go: type Inner struct { V int }
+        //     type Node struct { Name string; In Inner; Next *Node }
+        //     enc.Encode(Inner{7}); enc.Encode(Node{"a", Inner{1}, &Node{Name: "b"}})
+        // Node's In field refers to Inner, defined two messages before Node; Next refers to Node itself.
+        let mut stream = b"\x18\x7f\x03\x01\x01\x05Inner\x01\xff\x80\x00\x01\x01\x01\x01V\x01\x04\x00\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x05\xff\x80\x01\x0e\x00");
+        stream.extend_from_slice(b"\x2d\xff\x81\x03\x01\x01\x04Node\x01\xff\x82\x00\x01\x03\x01\x04Name\x01\x0c\x00\
+            \x01\x02In\x01\xff\x80\x00\x01\x04Next\x01\xff\x82\x00\x00\x00");
+        stream.extend_from_slice(b"\x11\xff\x82\x01\x01a\x01\x01\x02\x00\x01\x01\x01b\x01\x00\x00\x00");
+
+        let mut dec = Decoder::new(stream.as_slice());
+        let Some(Value::Struct(_, inner)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+        assert_eq!(inner["V"], Value::Int(7));
+        let Some(Value::Struct(_, node)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+        assert_eq!(node["In"], Value::Struct("Struct".to_string(), BTreeMap::from([("V".to_string(), Value::Int(1))])));
+        let Value::Struct(_, next) = &node["Next"] else { panic!("expected a struct") };
+        assert_eq!(next["Name"], Value::from("b"));
+        assert!(!next.contains_key("Next"));
+        assert!(dec.read_next().unwrap().is_none());
+
+        // Raw messages carry both definitions, the recursive one once.
+        let mut dec = Decoder::new(stream.as_slice());
+        dec.read_raw_message().unwrap();
+        let raw = dec.read_raw_message().unwrap().unwrap();
+        assert_eq!(raw.definitions.iter().map(|d| d.0).collect::<Vec<_>>(), [65, 64]);
+        assert_eq!(raw.decode().unwrap(), node_value(&stream));
+    }
+
+    fn node_value(stream: &[u8]) -> Value {
+        let mut dec = Decoder::new(stream);
+        dec.read_next().unwrap();
+        dec.read_next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_type_schema_eq() {
+        let mut decoder = Decoder::new(&include_bytes!("../normal-session.bin")[..]);
+        decoder.read_next().unwrap();
+        assert_eq!(decoder.types()[&64], TypeSchema::Map(8, 8));
+        assert_eq!(decoder.types()[&64].name(), "map");
+        assert_eq!(decoder.types()[&5].name(), "[]byte");
+        assert_ne!(TypeSchema::Struct(vec![(0, 2, "A".to_string())]), TypeSchema::Struct(vec![(0, 2, "B".to_string())]));
+    }
+
+    #[test]
+    fn test_byte_array() {
+        // Go's bytes for [4]byte{1, 2, 3, 200}: an arrayType with elem uint and len 4, then
+        // the count and each byte as a uint.
+        let go = b"\x0d\x7f\x01\x01\x02\xff\x80\x00\x01\x06\x01\x08\x00\x00\x09\xff\x80\x00\x04\x01\x02\x03\xff\xc8";
+        let mut decoder = Decoder::new(&go[..]);
+        let value = decoder.read_next().unwrap().unwrap();
+        assert_eq!(decoder.types()[&64], TypeSchema::Array(3, 4));
+        assert_eq!(value.as_fixed_bytes::<4>(), Some([1, 2, 3, 200]));
+        assert_eq!(value.as_fixed_bytes::<16>(), None);
+        assert_eq!(crate::to_vec_typed(&[1u8, 2, 3, 200]).unwrap(), go);
+        assert_eq!(crate::from_slice_typed::<[u8; 4]>(go).unwrap(), [1, 2, 3, 200]);
+        assert!(crate::from_slice_typed::<[u8; 5]>(go).is_err());
+
+        // A count that disagrees with the type is an error, as in Go.
+        let mut bad = go.to_vec();
+        bad[18] = 0x03;
+        assert_eq!(Decoder::new(bad.as_slice()).read_next().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_strict_types() {
+        // normal-session.bin's map definition, then a second definition of id 64 as a
+        // []int slice, then the first value.
+        let go = include_bytes!("../normal-session.bin");
+        let mut stream = go[..14].to_vec();
+        stream.extend_from_slice(b"\x0b\x7f\x02\x01\x02\xff\x80\x00\x01\x04\x00\x00");
+        stream.extend_from_slice(&go[14..]);
+
+        // By default the second definition silently wins.
+        let mut lenient = Decoder::new(stream.as_slice());
+        let _ = lenient.read_next();
+        assert_eq!(lenient.types()[&64], TypeSchema::Slice(2));
+        let mut strict = Decoder::new(stream.as_slice());
+        strict.set_strict_types(true);
+        let err = strict.read_next().unwrap_err();
+        assert!(err.to_string().contains("type id 64 redefined"), "{}", err);
+
+        // Repeating an identical definition is harmless.
+        let mut stream = go[..14].to_vec();
+        stream.extend_from_slice(go);
+        let mut strict = Decoder::new(stream.as_slice());
+        strict.set_strict_types(true);
+        assert_eq!(strict.read_next().unwrap(), Decoder::new(&go[..]).read_next().unwrap());
+    }
+
+    #[test]
+    fn test_top_level_interface() {
+        // Go: var x interface{}; enc.Encode(&x); x = "hi"; enc.Encode(&x); enc.Encode(int64(7))
+        // A top-level interface has the predefined id 8 and no definition.
+        let mut stream = b"\x03\x10\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x0f\x10\x00\x06string\x0c\x04\x00\x02hi");
+        stream.extend_from_slice(b"\x03\x04\x00\x0e");
+
+        let values: Vec<Value> = Decoder::new(stream.as_slice()).values().collect::<Result<_>>().unwrap();
+        assert_eq!(values, [Value::Nil, Value::from("hi"), Value::Int(7)]);
+
+        let mut dec = Decoder::new(stream.as_slice());
+        assert_eq!(dec.decode_into::<Value>().unwrap(), Value::Nil);
+        assert_eq!(dec.decode_into::<Value>().unwrap(), Value::from("hi"));
+        assert_eq!(dec.decode_into::<i64>().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_garbage_is_an_error() {
+        // Flip bytes of real streams and append junk, with a fixed seed; every result
+        // must be Ok or Err without a panic. The fuzz target does the same at scale.
+        let fixtures: [&[u8]; 3] = [
+            include_bytes!("../normal-session.bin"),
+            include_bytes!("../goth-session.bin"),
+            include_bytes!("../tagged.bin"),
+        ];
+        let mut seed = 0x2545f4914f6cdd1du64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for fixture in fixtures {
+            for _ in 0..500 {
+                let mut input = fixture.to_vec();
+                for _ in 0..1 + next() % 4 {
+                    let i = next() as usize % input.len();
+                    input[i] = next() as u8;
+                }
+                input.truncate(next() as usize % (input.len() + 1));
+                let _ = Decoder::new(input.as_slice()).decode_all();
+                let _ = Decoder::new_unframed(input.as_slice()).decode_value(&TypeSchema::Interface);
+            }
+        }
+        for junk in [&b"\xf8\xff\xff\xff\xff\xff\xff\xff\xff"[..], b"\x0a\x04\x00\xf8\xff\xff\xff\xff\xff\xff\xff\x7f", b"\x0b\x0a\x00\xf8\x00\x00\x00\x10\x00\x00\x00\x00\x61", b"\xf8\x00\x00\x00\x10\x00\x00\x00\x00\x04"] {
+            assert!(Decoder::new(junk).decode_all().is_err());
+        }
+    }
+
+    #[test]
+    fn test_interface_slice() {
+        // Go: enc.Encode([]interface{}{1, "two", true})
+        let mut stream = b"\x1b\x7f\x02\x01\x01\x0e[]interface {}\x01\xff\x80\x00\x01\x10\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x23\xff\x80\x00\x03\x03int\x04\x02\x00\x02\x06string\x0c\x05\x00\x03two\x04bool\x02\x02\x00\x01");
+
+        let expected = vec![Value::Int(1), Value::from("two"), Value::Bool(true)];
+        let mut dec = Decoder::new(stream.as_slice());
+        assert_eq!(dec.read_next().unwrap(), Some(Value::Array(expected.clone())));
+        assert_eq!(dec.types()[&64], TypeSchema::Slice(8));
+        assert_eq!(Decoder::new(stream.as_slice()).decode_into::<Vec<Value>>().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_interface_scalar_aliases() {
+        // Go: interface{} values uintptr(7), byte(200), int32(-3) and rune('x'). Each is sent
+        // under its own name but with the wire id of uint or int, which is all decoding uses.
+        // A byte is a uint like any other, not a raw byte.
+        let cases: [(&[u8], Value); 4] = [
+            (b"\x07uintptr\x06\x02\x00\x07", Value::Uint(7)),
+            (b"\x05uint8\x06\x03\x00\xff\xc8", Value::Uint(200)),
+            (b"\x05int32\x04\x02\x00\x05", Value::Int(-3)),
+            (b"\x05int32\x04\x03\x00\xff\xf0", Value::Int(120)),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(Decoder::new_unframed(bytes).decode_interface().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_complex_numbers() {
+        // Go: interface{}(complex64(1+2i)). complex64 shares complex128's id 7 and sends its
+        // parts as ordinary gob floats.
+        let bytes = b"\x09complex64\x0e\x05\x00\xfe\xf0\x3f\x40";
+        let value = Decoder::new_unframed(&bytes[..]).decode_interface().unwrap();
+        assert_eq!(value, Value::Complex(1.0, 2.0));
+        assert_eq!(value.to_string(), "(1+2i)");
+        assert_eq!(Value::Complex(0.5, -1.5).to_string(), "(0.5-1.5i)");
+
+        let stream = crate::to_vec(&Value::Complex(-3.0, 0.25)).unwrap();
+        assert_eq!(crate::from_slice(&stream).unwrap(), Value::Complex(-3.0, 0.25));
+        // In an interface{} slot it goes out as a complex128.
+        let map = Value::from([("c", Value::Complex(1.0, 2.0)), ("n", Value::Int(1))]);
+        let stream = crate::to_vec(&map).unwrap();
+        assert_eq!(crate::from_slice(&stream).unwrap(), map);
+    }
+
+    #[test]
+    fn test_map_of_string_slices() {
+        // Go: http.Header-like map[string][]string{"Accept": {"text/html", "*/*"}, "X-Id": {"7"}}.
+        // The map is id 64 but its []string elem (id 65) is defined first.
+        let stream = b"\x16\xff\x81\x02\x01\x01\x08[]string\x01\xff\x82\x00\x01\x0c\x00\x00\
+            #\x7f\x04\x01\x01\x13map[string][]string\x01\xff\x80\x00\x01\x0c\x01\xff\x82\x00\x00\
+            \x22\xff\x80\x00\x02\x06Accept\x02\x09text/html\x03*/*\x04X-Id\x01\x017";
+        let mut dec = Decoder::new(&stream[..]);
+        let expected = Value::from([
+            ("Accept", Value::Array(vec![Value::from("text/html"), Value::from("*/*")])),
+            ("X-Id", Value::Array(vec![Value::from("7")])),
+        ]);
+        assert_eq!(dec.read_next().unwrap(), Some(expected));
+        assert_eq!(dec.types()[&64], TypeSchema::Map(6, 65));
+        assert_eq!(dec.types()[&65], TypeSchema::Slice(6));
+
+        let headers: HashMap<String, Vec<String>> = Decoder::new(&stream[..]).decode_into().unwrap();
+        assert_eq!(headers["Accept"], ["text/html", "*/*"]);
+        assert_eq!(headers["X-Id"], ["7"]);
+        let headers: BTreeMap<String, Vec<String>> = headers.into_iter().collect();
+        let bytes = crate::to_vec_typed(&headers).unwrap();
+        assert_eq!(crate::from_slice_typed::<BTreeMap<String, Vec<String>>>(&bytes).unwrap(), headers);
+    }
+
+    #[test]
+    fn test_duplicate_key_policy() {
+        // map[string]int64 (id 64) holding "a":1, "b":2, "a":3, "a":4.
+        let mut stream = b"\x0d\x7f\x04\x01\x02\xff\x80\x00\x01\x0c\x01\x04\x00\x00".to_vec();
+        stream.extend_from_slice(b"\x10\xff\x80\x00\x04\x01a\x02\x01b\x04\x01a\x06\x01a\x08");
+
+        let decode = |policy, preserve| {
+            let mut dec = Decoder::new(stream.as_slice());
+            dec.set_duplicate_key_policy(policy);
+            dec.set_preserve_map_order(preserve);
+            dec.read_next()
+        };
+        let map = |a: Value| Value::Map([(Value::from("a"), a), (Value::from("b"), Value::Int(2))].into_iter().collect());
+
+        assert_eq!(decode(DuplicateKeyPolicy::LastWins, false).unwrap(), Some(map(Value::Int(4))));
+        assert_eq!(decode(DuplicateKeyPolicy::FirstWins, false).unwrap(), Some(map(Value::Int(1))));
+        let collected = Value::Array(vec![Value::Int(1), Value::Int(3), Value::Int(4)]);
+        assert_eq!(decode(DuplicateKeyPolicy::CollectIntoArray, false).unwrap(), Some(map(collected.clone())));
+        let err = decode(DuplicateKeyPolicy::Error, false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // In stream order, the merged entry stays where the key first appeared.
+        let ordered = Value::OrderedMap(vec![(Value::from("a"), collected), (Value::from("b"), Value::Int(2))]);
+        assert_eq!(decode(DuplicateKeyPolicy::CollectIntoArray, true).unwrap(), Some(ordered));
+    }
+
+    #[test]
+    fn test_truncated_stream_is_an_error() {
+        let input = include_bytes!("../normal-session.bin");
+        // The map type definition is a whole 14-byte message, so cutting right after it
+        // ends the stream cleanly. Any other cut lands inside a message.
+        let def_len = 1 + input[0] as usize;
+        for cut in 0..input.len() {
+            let mut dec = Decoder::new(&input[..cut]);
+            let result = dec.read_next();
+            if cut == 0 || cut == def_len {
+                assert!(matches!(result, Ok(None)), "cut at {}: {:?}", cut, result);
+            } else {
+                let err = result.expect_err(&format!("cut at {} should fail", cut));
+                assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof, "cut at {}", cut);
+            }
+        }
+        // Past the value, the end of the stream is clean again.
+        let mut dec = Decoder::new(&input[..]);
+        assert!(dec.read_next().unwrap().is_some());
+        assert!(dec.read_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_lossy_strings() {
+        let body: &[u8] = b"\x03a\xffb";
+        let err = Decoder::new_unframed(body).read_string().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut dec = Decoder::new_unframed(body);
+        dec.set_lossy_strings(true);
+        assert_eq!(dec.read_string().unwrap(), "a\u{fffd}b");
+
+        let mut dec = Decoder::new_unframed(body);
+        dec.set_lossy_strings(true);
+        assert_eq!(dec.read_str_borrowed().unwrap(), "a\u{fffd}b");
+    }
+
+    #[test]
+    fn test_lenient_bool() {
+        // A uint of 255 where a bool goes, as some non-Go encoders write true.
+        let body: &[u8] = b"\xff\xff\x00";
+        let err = Decoder::new_unframed(body).read_bool().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut dec = Decoder::new_unframed(body);
+        dec.set_lenient_bool(true);
+        assert!(dec.read_bool().unwrap());
+        assert!(!dec.read_bool().unwrap());
+
+        let mut bytes = crate::to_vec(&Value::Bool(true)).unwrap();
+        *bytes.last_mut().unwrap() = 2;
+        assert!(crate::from_slice(&bytes).is_err());
+        let mut dec = Decoder::new(bytes.as_slice());
+        dec.set_lenient_bool(true);
+        assert_eq!(dec.read_next().unwrap(), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_narrow_integers() {
+        let bytes = crate::to_vec_typed(&-70000i64).unwrap();
+        assert_eq!(crate::from_slice_typed::<i32>(&bytes).unwrap(), -70000);
+        let err = crate::from_slice_typed::<i16>(&bytes).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "value -70000 out of range for int16");
+
+        let bytes = crate::to_vec_typed(&(u32::MAX as u64 + 1)).unwrap();
+        assert_eq!(crate::from_slice_typed::<usize>(&bytes).unwrap(), 1 << 32);
+        assert!(crate::from_slice_typed::<u32>(&bytes).is_err());
+
+        // Narrow values go out as plain int and uint.
+        assert_eq!(crate::to_vec_typed(&7u16).unwrap(), crate::to_vec_typed(&7u64).unwrap());
+        assert_eq!(crate::to_vec_typed(&-7i8).unwrap(), crate::to_vec_typed(&-7i64).unwrap());
+    }
+
+    #[test]
+    fn test_uints_of_every_width() {
+        // One value per byte count, 1 through 8, each in its own message; a long string
+        // gives a message length that itself takes two bytes.
+        let values: Vec<u64> = (0..8).map(|n| 0x80u64 << (8 * n)).chain([u64::MAX]).collect();
+        let mut writer = crate::GobWriter::new(Vec::new());
+        for v in &values {
+            writer.encode_typed(v).unwrap();
+        }
+        writer.encode_typed(&"x".repeat(300)).unwrap();
+        let stream = writer.into_inner().unwrap();
+
+        let mut dec = Decoder::new(stream.as_slice());
+        for v in &values {
+            assert_eq!(dec.decode_into::<u64>().unwrap(), *v);
+        }
+        assert_eq!(dec.decode_into::<String>().unwrap().len(), 300);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        // 80 maps, each in an interface{} value of the one outside: 160 levels.
+        let mut value = Value::Int(1);
+        for _ in 0..80 {
+            value = Value::from([("inner", value), ("n", Value::Int(0))]);
+        }
+        let stream = crate::to_vec(&value).unwrap();
+
+        let mut dec = Decoder::new(stream.as_slice());
+        assert_eq!(dec.max_depth(), DEFAULT_MAX_DEPTH);
+        let err = dec.read_next().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "gob value nested more than 128 levels deep");
+
+        // Skipping a value recurses the same way.
+        let mut dec = Decoder::new(stream.as_slice());
+        dec.set_max_depth(50);
+        assert_eq!(dec.read_raw_message().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        let mut dec = Decoder::new(stream.as_slice());
+        dec.set_max_depth(50);
+        dec.set_raw_type_ids([64]);
+        assert_eq!(dec.read_next().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+        let mut dec = Decoder::new(stream.as_slice());
+        dec.set_max_depth(200);
+        assert_eq!(dec.read_next().unwrap(), Some(value));
+    }
+
+    #[test]
+    fn test_tuples() {
+        let pair = ("ann".to_string(), 30i64);
+        let bytes = crate::to_vec_typed(&pair).unwrap();
+        assert_eq!(crate::from_slice_typed::<(String, i64)>(&bytes).unwrap(), pair);
+        assert_eq!(crate::from_slice(&bytes).unwrap(), Value::struct_("Struct").field("F0", "ann").field("F1", 30i64).build());
+
+        let entries = vec![(1u64, "a".to_string()), (2, "b".to_string())];
+        let bytes = crate::to_vec_typed(&entries).unwrap();
+        assert_eq!(crate::from_slice_typed::<Vec<(u64, String)>>(&bytes).unwrap(), entries);
+        let wide = (true, 1i8, 2u16, 3.5f64, "s".to_string(), vec![1u8], -7isize, ("in".to_string(), 0u32));
+        let bytes = crate::to_vec_typed(&wide).unwrap();
+        assert_eq!(crate::from_slice_typed::<(bool, i8, u16, f64, String, Vec<u8>, isize, (String, u32))>(&bytes).unwrap(), wide);
+
+        // A Go struct is matched by field name, and Go leaves zero fields out.
+        let go = Value::OrderedStruct("Pair".to_string(), vec![
+            ("F1".to_string(), Value::Int(3)),
+            ("F0".to_string(), Value::from("a")),
+        ]);
+        let bytes = crate::to_vec(&go).unwrap();
+        assert_eq!(crate::from_slice_typed::<(String, i64)>(&bytes).unwrap(), ("a".to_string(), 3));
+        let bytes = crate::to_vec(&Value::struct_("Pair").field("F1", 3i64).build()).unwrap();
+        assert_eq!(crate::from_slice_typed::<(String, i64)>(&bytes).unwrap(), (String::new(), 3));
+
+        let bytes = crate::to_vec(&Value::struct_("Pair").field("F0", "a").field("Note", "x").build()).unwrap();
+        let err = crate::from_slice_typed::<(String, i64)>(&bytes).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown field 1 for 2-tuple");
+        let mut dec = Decoder::new(bytes.as_slice());
+        dec.set_ignore_unknown_fields(true);
+        assert_eq!(dec.decode_into::<(String, i64)>().unwrap(), ("a".to_string(), 0));
+
+        // A tuple's type id is only known once a stream defines it, so only a GobWriter can
+        // put one in an interface.
+        let err = crate::encode_as_interface(&pair, &mut crate::Encoder::new(Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        // Nor a slice of them, which isn't a struct itself.
+        let err = crate::encode_as_interface(&entries, &mut crate::Encoder::new(Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        let mut bytes = Vec::new();
+        crate::GobWriter::new(&mut bytes).encode_interface_typed(&pair).unwrap();
+        assert_eq!(crate::from_slice(&bytes).unwrap(), Value::struct_("Tuple2").field("F0", "ann").field("F1", 30i64).build());
+    }
+
+    #[test]
+    fn test_into_inner_after_gob_section() {
+        let mut file = crate::to_vec(&Value::from("header")).unwrap();
+        file.extend_from_slice(&crate::to_vec_typed(&7i64).unwrap());
+        file.extend_from_slice(b"TRAILER");
+
+        let mut dec = Decoder::new(file.as_slice());
+        assert_eq!(dec.read_next().unwrap(), Some(Value::from("header")));
+        assert_eq!(dec.decode_into::<i64>().unwrap(), 7);
+        assert_eq!(dec.into_inner(), b"TRAILER");
+
+        let mut dec = Decoder::new_buffered(file.as_slice());
+        dec.read_next().unwrap();
+        dec.read_next().unwrap();
+        let mut rest = String::new();
+        std::io::Read::read_to_string(&mut dec.into_inner(), &mut rest).unwrap();
+        assert_eq!(rest, "TRAILER");
+    }
+}
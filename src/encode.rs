@@ -1,28 +1,67 @@
 use std::io::Write;
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
 use crate::Result;
+use crate::Value;
 
+/// Writes gob primitives to `W`.
+///
+/// The encoder doesn't buffer, but `W` may (a `BufWriter`, a compressor). Finish a
+/// stream with `into_inner`, which flushes `W` and reports the flush error if there
+/// is one. Dropping the encoder does not flush: whatever `W` does on drop applies,
+/// and a `BufWriter` that is dropped silently discards any error from its last flush.
 pub struct Encoder<W: Write> {
     writer: W,
+    bytes_written: u64,
+    scratch: Vec<Vec<u8>>, // Spare buffers for length-prefixed interface values
+}
+
+/// Takes a cleared buffer from `pool`, allocating only when the pool is empty.
+pub(crate) fn take_buf(pool: &mut Vec<Vec<u8>>) -> Vec<u8> {
+    pool.pop().unwrap_or_default()
+}
+
+/// Returns a buffer to `pool`, keeping its capacity for the next value.
+pub(crate) fn put_buf(pool: &mut Vec<Vec<u8>>, mut buf: Vec<u8>) {
+    buf.clear();
+    pool.push(buf);
 }
 
 impl<W: Write> Encoder<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self { writer, bytes_written: 0, scratch: Vec::new() }
     }
 
     pub fn flush(&mut self) -> Result<()> {
         self.writer.flush()
     }
 
+    /// Total bytes handed to the underlying writer so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Flushes and returns the underlying writer. This is the way to finish a stream:
+    /// once it returns Ok, everything written so far has been handed on by `W`.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
     pub fn write_all(&mut self, buf: &[u8]) -> Result<()> {
         self.writer.write_all(buf)?;
+        self.bytes_written += buf.len() as u64;
         Ok(())
     }
 
     /// Writes a single byte.
     pub fn write_u8(&mut self, v: u8) -> Result<()> {
-        self.writer.write_all(&[v])?;
-        Ok(())
+        self.write_all(&[v])
     }
 
     /// Writes an unsigned integer using gob's variable-length encoding.
@@ -54,20 +93,13 @@ impl<W: Write> Encoder<W> {
              buf[n - 1 - i] = (temp & 0xFF) as u8;
              temp >>= 8;
         }
-        self.writer.write_all(&buf[0..n])?;
-        Ok(())
+        self.write_all(&buf[0..n])
     }
 
     /// Writes a signed integer.
     /// Signed integers are zigzag-encoded (or similar) into an unsigned integer, then written.
     pub fn write_int(&mut self, v: i64) -> Result<()> {
-        let u: u64;
-        if v < 0 {
-            u = ((!v as u64) << 1) | 1;
-        } else {
-            u = (v as u64) << 1;
-        }
-        self.write_uint(u)
+        self.write_uint(crate::codec::int_to_bits(v))
     }
 
     /// Writes a floating point number.
@@ -78,7 +110,13 @@ impl<W: Write> Encoder<W> {
         self.write_uint(swapped)
     }
 
-    /// Writes a boolean value.
+    /// Writes an `f32`. Gob has one float type: like Go, the value is widened to `f64`
+    /// (exactly, so the bits of the original survive) and written as such.
+    pub fn write_float32(&mut self, v: f32) -> Result<()> {
+        self.write_float(v as f64)
+    }
+
+    /// Writes a boolean value: the uint 1 for true, 0 for false. Go rejects anything else.
     pub fn write_bool(&mut self, v: bool) -> Result<()> {
         if v {
             self.write_uint(1)
@@ -91,8 +129,7 @@ impl<W: Write> Encoder<W> {
     /// Encoded as length (uint) followed by raw bytes.
     pub fn write_bytes(&mut self, v: &[u8]) -> Result<()> {
         self.write_uint(v.len() as u64)?;
-        self.writer.write_all(v)?;
-        Ok(())
+        self.write_all(v)
     }
 
     /// Writes a string.
@@ -115,17 +152,25 @@ impl<W: Write> Encoder<W> {
         self.write_int(type_id)?;
         
         // Value: We need to encode it to a buffer to know the length first.
-        let mut temp_buf = Vec::new();
-        let mut temp_enc = Encoder::new(&mut temp_buf);
-        val.encode(&mut temp_enc)?;
-        
-        // Value Length
-        self.write_uint(temp_buf.len() as u64)?;
-        
-        // Value Bytes
-        self.write_all(&temp_buf)?;
+        // The buffer comes from the scratch pool, which the temporary encoder borrows
+        // so nested interfaces reuse buffers too.
+        // Like a top-level value, a non-struct concrete value is sent as a singleton:
+        // a zero field delta followed by the value. Structs carry their own deltas.
+        let mut temp_buf = take_buf(&mut self.scratch);
+        let mut temp_enc = Encoder { writer: &mut temp_buf, bytes_written: 0, scratch: std::mem::take(&mut self.scratch) };
+        let result = if val.is_struct() {
+            val.encode(&mut temp_enc)
+        } else {
+            temp_enc.write_uint(0).and_then(|_| val.encode(&mut temp_enc))
+        };
+        self.scratch = temp_enc.scratch;
         
-        Ok(())
+        // Value Length, then Value Bytes
+        let result = result
+            .and_then(|_| self.write_uint(temp_buf.len() as u64))
+            .and_then(|_| self.write_all(&temp_buf));
+        put_buf(&mut self.scratch, temp_buf);
+        result
     }
 }
 
@@ -133,6 +178,40 @@ pub trait GobEncodable {
     fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()>;
     fn type_id(&self) -> i64 { 0 } // Default 0 if not known/needed, but should be overridden
     fn type_name(&self) -> &'static str { "" } // Type name for interface encoding
+    /// Whether the value is encoded as a gob struct (field deltas ending in 0).
+    /// Anything else is framed as a singleton when it stands alone or sits in an interface.
+    fn is_struct(&self) -> bool { false }
+    /// Sends this type's definition to `writer` unless it already has it, and returns the
+    /// id the type has on that stream. Predefined types need no definition; `#[Gob]`
+    /// structs send theirs, after those of their fields.
+    fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64>
+    where Self: Sized {
+        let _ = writer;
+        Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+            format!("no wire type definition is available for {}", std::any::type_name::<Self>())))
+    }
+
+    // How a Vec of this type is encoded. Only u8 differs, as a byte slice rather than a
+    // slice of uints; the hooks stand in for specializing Vec<u8>.
+
+    /// Writes a slice of this type: the count, then each element.
+    #[doc(hidden)]
+    fn encode_slice<W: std::io::Write>(items: &[Self], encoder: &mut Encoder<W>) -> Result<()>
+    where Self: Sized {
+        encoder.write_uint(items.len() as u64)?;
+        items.iter().try_for_each(|item| item.encode(encoder))
+    }
+
+    /// Type id and interface name of a slice of this type, when it is predefined.
+    #[doc(hidden)]
+    fn slice_type() -> (i64, &'static str) where Self: Sized { (0, "") }
+
+    #[doc(hidden)]
+    fn write_slice_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64>
+    where Self: Sized {
+        let elem_id = Self::write_type_definition(writer)?;
+        writer.define_slice(elem_id)
+    }
 }
 
 impl GobEncodable for bool {
@@ -141,6 +220,7 @@ impl GobEncodable for bool {
     }
     fn type_id(&self) -> i64 { 1 } // Bool
     fn type_name(&self) -> &'static str { "bool" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(1) }
 }
 
 impl GobEncodable for i64 {
@@ -149,6 +229,7 @@ impl GobEncodable for i64 {
     }
     fn type_id(&self) -> i64 { 2 } // Int
     fn type_name(&self) -> &'static str { "int64" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(2) }
 }
 
 impl GobEncodable for u64 {
@@ -157,6 +238,7 @@ impl GobEncodable for u64 {
     }
     fn type_id(&self) -> i64 { 3 } // Uint
     fn type_name(&self) -> &'static str { "uint64" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(3) }
 }
 
 impl GobEncodable for f64 {
@@ -165,6 +247,16 @@ impl GobEncodable for f64 {
     }
     fn type_id(&self) -> i64 { 4 } // Float
     fn type_name(&self) -> &'static str { "float64" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(4) }
+}
+
+impl GobEncodable for f32 {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        encoder.write_float32(*self)
+    }
+    fn type_id(&self) -> i64 { 4 } // Float
+    fn type_name(&self) -> &'static str { "float32" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(4) }
 }
 
 impl GobEncodable for String {
@@ -173,39 +265,266 @@ impl GobEncodable for String {
     }
     fn type_id(&self) -> i64 { 6 } // String
     fn type_name(&self) -> &'static str { "string" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(6) }
+}
+
+impl GobEncodable for u8 {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        encoder.write_uint(*self as u64)
+    }
+    fn type_id(&self) -> i64 { 3 } // Uint
+    fn type_name(&self) -> &'static str { "uint8" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(3) }
+
+    fn encode_slice<W: std::io::Write>(items: &[Self], encoder: &mut Encoder<W>) -> Result<()> {
+        encoder.write_bytes(items)
+    }
+    fn slice_type() -> (i64, &'static str) { (5, "[]byte") } // ByteSlice
+    fn write_slice_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(5) }
+}
+
+// The narrower integer widths are sent as Go's int and uint, like any signed or unsigned
+// Go integer.
+macro_rules! impl_encodable_int {
+    ($($ty:ty => $write:ident($wide:ty), $id:literal, $go:literal;)*) => {$(
+        impl GobEncodable for $ty {
+            fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+                encoder.$write(*self as $wide)
+            }
+            fn type_id(&self) -> i64 { $id }
+            fn type_name(&self) -> &'static str { $go }
+            fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok($id) }
+        }
+    )*};
+}
+
+impl_encodable_int! {
+    i8 => write_int(i64), 2, "int8";
+    i16 => write_int(i64), 2, "int16";
+    i32 => write_int(i64), 2, "int32";
+    isize => write_int(i64), 2, "int";
+    u16 => write_uint(u64), 3, "uint16";
+    u32 => write_uint(u64), 3, "uint32";
+    usize => write_uint(u64), 3, "uint";
+    char => write_int(i64), 2, "int32"; // Go's rune
+}
+
+/// A Go array such as `[16]byte` or `[3]int64`. Go sends arrays as the count and then each
+/// element, so unlike `Vec<u8>` every byte of a byte array is a uint of its own. An array
+/// has no fixed type id or name, so `encode_as_interface` refuses one.
+impl<T: GobEncodable, const N: usize> GobEncodable for [T; N] {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        encoder.write_uint(N as u64)?;
+        self.iter().try_for_each(|item| item.encode(encoder))
+    }
+    fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+        let elem_id = T::write_type_definition(writer)?;
+        writer.define_array(elem_id, N)
+    }
+}
+
+/// A Go slice, or a byte slice for `Vec<u8>`.
+impl<T: GobEncodable> GobEncodable for Vec<T> {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        T::encode_slice(self, encoder)
+    }
+    fn type_id(&self) -> i64 { T::slice_type().0 }
+    fn type_name(&self) -> &'static str { T::slice_type().1 }
+    fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+        T::write_slice_type_definition(writer)
+    }
+}
+
+// Smart pointers are transparent on the wire: Go sees the pointee.
+macro_rules! impl_encodable_pointer {
+    ($($ptr:ident),*) => {$(
+        impl<T: GobEncodable> GobEncodable for $ptr<T> {
+            fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+                (**self).encode(encoder)
+            }
+            fn type_id(&self) -> i64 { (**self).type_id() }
+            fn type_name(&self) -> &'static str { (**self).type_name() }
+            fn is_struct(&self) -> bool { (**self).is_struct() }
+            fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+                T::write_type_definition(writer)
+            }
+        }
+    )*};
+}
+
+impl_encodable_pointer!(Box, Rc, Arc);
+
+/// A string that may be borrowed; sent as a Go string.
+impl GobEncodable for Cow<'_, str> {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        encoder.write_string(self)
+    }
+    fn type_id(&self) -> i64 { 6 } // String
+    fn type_name(&self) -> &'static str { "string" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(6) }
 }
 
-impl GobEncodable for Vec<u8> {
+/// A Go pointer field: `Some` is the pointee, `None` the zero value. `#[Gob]` structs
+/// leave a `None` field off the wire instead, as Go does for a nil pointer.
+impl<T: GobEncodable + Default> GobEncodable for Option<T> {
     fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
-        encoder.write_bytes(self)
+        match self {
+            Some(v) => v.encode(encoder),
+            None => T::default().encode(encoder),
+        }
+    }
+    fn type_id(&self) -> i64 {
+        self.as_ref().map_or_else(|| T::default().type_id(), |v| v.type_id())
+    }
+    fn type_name(&self) -> &'static str {
+        self.as_ref().map_or_else(|| T::default().type_name(), |v| v.type_name())
+    }
+    fn is_struct(&self) -> bool {
+        self.as_ref().map_or_else(|| T::default().is_struct(), |v| v.is_struct())
+    }
+    fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+        T::write_type_definition(writer)
+    }
+}
+
+macro_rules! impl_encodable_tuple {
+    ($($len:literal => ($($idx:tt $name:ident),+);)*) => {$(
+        /// Go has no tuples: a tuple is sent as a struct named "TupleN", N being its length,
+        /// with fields "F0", "F1", ... in order. A Go struct with those field names decodes it.
+        /// Its type id is assigned by the stream, so `encode_as_interface` can't send it;
+        /// `GobWriter::encode_interface_typed` can.
+        impl<$($name: GobEncodable),+> GobEncodable for ($($name,)+) {
+            fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+                $(
+                    encoder.write_uint(1)?;
+                    self.$idx.encode(encoder)?;
+                )+
+                encoder.write_uint(0)
+            }
+            fn type_name(&self) -> &'static str { concat!("Tuple", $len) }
+            fn is_struct(&self) -> bool { true }
+            fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+                let fields = [$((concat!("F", $idx).to_string(), $name::write_type_definition(writer)?)),+];
+                writer.define_struct(concat!("Tuple", $len), 0, &fields)
+            }
+        }
+    )*};
+}
+
+impl_encodable_tuple! {
+    1 => (0 A);
+    2 => (0 A, 1 B);
+    3 => (0 A, 1 B, 2 C);
+    4 => (0 A, 1 B, 2 C, 3 D);
+    5 => (0 A, 1 B, 2 C, 3 D, 4 E);
+    6 => (0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+    7 => (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+    8 => (0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+}
+
+/// A scalar `Value` as its own gob type, e.g. for an `interface{}` slot. Composite values
+/// need type definitions, which only a `GobWriter` can send; encoding one here fails.
+impl GobEncodable for Value {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        match self {
+            Value::Bool(v) => encoder.write_bool(*v),
+            Value::Int(v) => encoder.write_int(*v),
+            Value::Uint(v) => encoder.write_uint(*v),
+            Value::Float(v) => encoder.write_float(*v),
+            Value::Complex(re, im) => {
+                encoder.write_float(*re)?;
+                encoder.write_float(*im)
+            }
+            Value::Bytes(v) => encoder.write_bytes(v),
+            Value::String(v) => encoder.write_string(v),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                format!("{} value needs a type definition; encode it with GobWriter::encode", self.kind()))),
+        }
+    }
+    fn type_id(&self) -> i64 {
+        match self {
+            Value::Bool(_) => 1,
+            Value::Int(_) => 2,
+            Value::Uint(_) => 3,
+            Value::Float(_) => 4,
+            Value::Complex(..) => 7,
+            Value::Bytes(_) => 5,
+            Value::String(_) => 6,
+            _ => 0,
+        }
+    }
+    fn type_name(&self) -> &'static str {
+        // Same names as GobWriter uses for interface values
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int64",
+            Value::Uint(_) => "uint64",
+            Value::Float(_) => "float64",
+            Value::Complex(..) => "complex128",
+            Value::Bytes(_) => "[]byte",
+            Value::String(_) => "string",
+            _ => "",
+        }
+    }
+}
+
+// A Go map: the count, then each key and its element. Go decodes entries in any order.
+fn encode_map<'a, W, K, V, I>(len: usize, entries: I, encoder: &mut Encoder<W>) -> Result<()>
+where
+    W: std::io::Write,
+    K: GobEncodable + 'a,
+    V: GobEncodable + 'a,
+    I: Iterator<Item = (&'a K, &'a V)>,
+{
+    encoder.write_uint(len as u64)?;
+    for (k, v) in entries {
+        k.encode(encoder)?;
+        v.encode(encoder)?;
+    }
+    Ok(())
+}
+
+fn write_map_type_definition<W: std::io::Write, K: GobEncodable, V: GobEncodable>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+    let key_id = K::write_type_definition(writer)?;
+    let elem_id = V::write_type_definition(writer)?;
+    writer.define_map(key_id, elem_id)
+}
+
+impl<K: GobEncodable, V: GobEncodable, S> GobEncodable for std::collections::HashMap<K, V, S> {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        encode_map(self.len(), self.iter(), encoder)
+    }
+    fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+        write_map_type_definition::<W, K, V>(writer)
+    }
+}
+
+impl<K: GobEncodable, V: GobEncodable> GobEncodable for std::collections::BTreeMap<K, V> {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        encode_map(self.len(), self.iter(), encoder)
+    }
+    fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+        write_map_type_definition::<W, K, V>(writer)
     }
-    fn type_id(&self) -> i64 { 5 } // ByteSlice
-    fn type_name(&self) -> &'static str { "[]byte" }
 }
 
 // Helper function to encode a value as a Gob interface{}
 // Interface format: [TypeName] [TypeID] [Length] [Value]
+// The id is the type's static `type_id`, and no definition is sent: fine for predefined
+// types and `#[Gob(id = ...)]` structs already defined on the stream. A type whose id is
+// only assigned per stream, such as a tuple, an array or a slice of structs, has none, so
+// it is an error here rather than an id 0 Go would reject. For a whole
+// interface message, `GobWriter::encode_interface_typed` sends the definitions too.
 pub fn encode_as_interface<W: std::io::Write, T: GobEncodable>(
     value: &T,
     encoder: &mut Encoder<W>,
 ) -> Result<()> {
+    if value.type_id() == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!(
+            "{} has no fixed type id to send in an interface; use GobWriter::encode_interface_typed", std::any::type_name::<T>())));
+    }
     // Get type information from the trait
-    let type_name = value.type_name();
-    let type_id = value.type_id();
-    
-    // Encode the value to a temporary buffer to get its length
-    let mut value_buf = Vec::new();
-    let mut value_encoder = Encoder::new(&mut value_buf);
-    value.encode(&mut value_encoder)?;
-    
-    // Encode interface wrapper
-    encoder.write_string(type_name)?; // Type name
-    encoder.write_int(type_id)?; // Type ID
-    encoder.write_uint((value_buf.len() + 1) as u64)?; // Value length (+1 for the 0 byte)
-    encoder.write_u8(0)?; // The mystery 0 byte expected by decode_interface
-    encoder.write_all(&value_buf)?; // Value bytes
-    
-    Ok(())
+    encoder.write_interface_wrapper(value.type_name(), value.type_id(), value)
 }
 
 #[cfg(test)]
@@ -229,13 +548,48 @@ mod tests {
             enc.write_uint(val).unwrap();
             assert_eq!(buf, expected, "Failed encoding {}", val);
 
-            let mut cursor = Cursor::new(buf);
-            let mut dec = Decoder::new(cursor);
+            let cursor = Cursor::new(buf);
+            let mut dec = Decoder::new_unframed(cursor);
             let decoded = dec.read_uint().unwrap();
             assert_eq!(decoded, val, "Failed decoding {}", val);
         }
     }
 
+    #[test]
+    fn test_float_encoding() {
+        // Byte-reversed bits: 1.0 is 0x3ff0000000000000, sent as the uint 0xf03f.
+        let mut buf = Vec::new();
+        Encoder::new(&mut buf).write_float(1.0).unwrap();
+        assert_eq!(buf, [0xfe, 0xf0, 0x3f]);
+
+        let values = [0.0, -0.0, 1.5, -2.25, f64::INFINITY, f64::NEG_INFINITY, f64::MIN_POSITIVE / 4.0, f64::from_bits(1), f64::MAX, f64::NAN];
+        for v in values {
+            let mut buf = Vec::new();
+            Encoder::new(&mut buf).write_float(v).unwrap();
+            let decoded = Decoder::new_unframed(buf.as_slice()).read_float().unwrap();
+            assert_eq!(decoded.to_bits(), v.to_bits(), "round trip of {:e}", v);
+        }
+    }
+
+    #[test]
+    fn test_float32_encoding() {
+        let values = [0.0f32, -0.0, 0.1, f32::MAX, f32::INFINITY, f32::NEG_INFINITY, f32::from_bits(1), f32::NAN];
+        for v in values {
+            let mut buf = Vec::new();
+            Encoder::new(&mut buf).write_float32(v).unwrap();
+            // Same bytes as the widened f64, as Go sends a float32.
+            let mut wide = Vec::new();
+            Encoder::new(&mut wide).write_float(v as f64).unwrap();
+            assert_eq!(buf, wide);
+            let decoded = Decoder::new_unframed(buf.as_slice()).read_float32().unwrap();
+            assert_eq!(decoded.to_bits(), v.to_bits(), "round trip of {:e}", v);
+        }
+
+        let mut buf = Vec::new();
+        Encoder::new(&mut buf).write_float(1e300).unwrap();
+        assert!(Decoder::new_unframed(buf.as_slice()).read_float32().is_err());
+    }
+
     #[test]
     fn test_int_encoding() {
         let tests = vec![
@@ -251,13 +605,49 @@ mod tests {
             let mut enc = Encoder::new(&mut buf);
             enc.write_int(val).unwrap();
 
-            let mut cursor = Cursor::new(buf);
-            let mut dec = Decoder::new(cursor);
+            let cursor = Cursor::new(buf);
+            let mut dec = Decoder::new_unframed(cursor);
             let decoded = dec.read_int().unwrap();
             assert_eq!(decoded, val, "Failed decoding {}", val);
         }
     }
     
+    #[test]
+    fn test_encoded_sizes() {
+        for v in [0u64, 1, 127, 128, 255, 256, 65535, 65536, u32::MAX as u64, u64::MAX] {
+            let mut buf = Vec::new();
+            Encoder::new(&mut buf).write_uint(v).unwrap();
+            assert_eq!(crate::codec::measure_uint(v), buf.len(), "uint {}", v);
+        }
+        for v in [0i64, -1, 63, 64, -64, -65, i64::MIN, i64::MAX] {
+            let mut buf = Vec::new();
+            Encoder::new(&mut buf).write_int(v).unwrap();
+            assert_eq!(crate::codec::measure_int(v), buf.len(), "int {}", v);
+        }
+    }
+
+    #[test]
+    fn test_bytes_written() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.write_uint(300).unwrap();
+        enc.write_string("hi").unwrap();
+        encode_as_interface(&1i64, &mut enc).unwrap();
+        assert_eq!(enc.bytes_written(), 6 + 10);
+        let buf = enc.into_inner().unwrap();
+        assert_eq!(buf.len(), 16);
+    }
+
+    #[test]
+    fn test_into_inner_flushes() {
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(std::io::BufWriter::new(&mut out));
+        enc.write_string("tail").unwrap();
+        let inner = enc.into_inner().unwrap();
+        assert!(inner.buffer().is_empty());
+        drop(inner);
+        assert_eq!(out, b"\x04tail");
+    }
+
     #[test]
     fn test_string_encoding() {
         let val = "Hello World";
@@ -265,9 +655,51 @@ mod tests {
         let mut enc = Encoder::new(&mut buf);
         enc.write_string(val).unwrap();
 
-        let mut cursor = Cursor::new(buf);
-        let mut dec = Decoder::new(cursor);
+        let cursor = Cursor::new(buf);
+        let mut dec = Decoder::new_unframed(cursor);
         let decoded = dec.read_string().unwrap();
         assert_eq!(decoded, val);
     }
+
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    impl GobEncodable for Point {
+        fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+            encoder.write_uint(1)?;
+            encoder.write_int(self.x)?;
+            encoder.write_uint(1)?;
+            encoder.write_int(self.y)?;
+            encoder.write_uint(0)
+        }
+        fn type_id(&self) -> i64 { 65 }
+        fn type_name(&self) -> &'static str { "main.Point" }
+        fn is_struct(&self) -> bool { true }
+    }
+
+    fn interface_bytes<T: GobEncodable>(val: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_as_interface(val, &mut Encoder::new(&mut buf)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_interface_scalar_singleton_delta() {
+        // Byte sequences as written by Go's encoder (see normal-session.bin).
+        assert_eq!(interface_bytes(&"uname".to_string()), b"\x06string\x0c\x07\x00\x05uname");
+        assert_eq!(interface_bytes(&1i64), b"\x05int64\x04\x02\x00\x02");
+        assert_eq!(interface_bytes(&false), b"\x04bool\x02\x02\x00\x00");
+        assert_eq!(
+            interface_bytes(&2.5f64),
+            b"\x07float64\x08\x04\x00\xfe\x04\x40"
+        );
+    }
+
+    #[test]
+    fn test_interface_struct_has_no_singleton_delta() {
+        let buf = interface_bytes(&Point { x: 10, y: 20 });
+        assert_eq!(buf, b"\x0amain.Point\xff\x82\x05\x01\x14\x01\x28\x00");
+    }
 }
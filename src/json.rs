@@ -0,0 +1,92 @@
+//! Converting decoded values to `serde_json` values, for callers that only need JSON.
+
+use std::io::Read;
+use serde_json::{Map, Number};
+use crate::{Decoder, Result, Value};
+
+/// Decodes every value in a gob stream and converts each with [`Value::to_json`],
+/// returning them as a JSON array in stream order.
+pub fn to_json<R: Read>(reader: R) -> Result<serde_json::Value> {
+    let values = Decoder::new(reader).values().map(|v| v.map(|v| v.to_json())).collect::<Result<Vec<_>>>()?;
+    Ok(serde_json::Value::Array(values))
+}
+
+impl Value {
+    /// Converts to JSON for display or logging. The conversion is lossy: bytes become a
+    /// base64 string, structs an object keyed by field name (the type name is dropped),
+    /// map keys their `Display` form, complex numbers a `[real, imag]` pair, and non-finite
+    /// floats `null`.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Nil => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Int(i) => serde_json::Value::from(*i),
+            Value::Uint(u) => serde_json::Value::from(*u),
+            Value::Float(f) => Number::from_f64(*f).map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::Complex(re, im) => serde_json::Value::Array(vec![Value::Float(*re).to_json(), Value::Float(*im).to_json()]),
+            Value::String(s) => serde_json::Value::String(s.clone()),
+            Value::Bytes(b) | Value::GobEncoded(_, b) | Value::Raw(_, b) => serde_json::Value::String(base64(b)),
+            Value::Array(items) => serde_json::Value::Array(items.iter().map(Value::to_json).collect()),
+            Value::Map(map) => json_object(map.iter()),
+            Value::OrderedMap(entries) => json_object(entries.iter().map(|(k, v)| (k, v))),
+            Value::Struct(_, fields) => {
+                serde_json::Value::Object(fields.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+            }
+            Value::OrderedStruct(_, fields) => {
+                serde_json::Value::Object(fields.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+            }
+        }
+    }
+}
+
+fn json_object<'a>(entries: impl Iterator<Item = (&'a Value, &'a Value)>) -> serde_json::Value {
+    let object: Map<String, serde_json::Value> = entries.map(|(k, v)| {
+        let key = match k {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        (key, v.to_json())
+    }).collect();
+    serde_json::Value::Object(object)
+}
+
+// Standard alphabet with padding, as Go's encoding/json writes []byte.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_to_json() {
+        let mut fields = BTreeMap::new();
+        fields.insert("Id".to_string(), Value::Uint(u64::MAX));
+        fields.insert("Raw".to_string(), Value::Bytes(b"gob!x".to_vec()));
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("user"), Value::Struct("User".to_string(), fields));
+        map.insert(Value::Int(7), Value::Float(1.5));
+        let stream = crate::to_vec(&Value::Map(map)).unwrap();
+
+        let expected = json!([{
+            "7": 1.5,
+            "user": {"Id": u64::MAX, "Raw": "Z29iIXg="},
+        }]);
+        assert_eq!(to_json(stream.as_slice()).unwrap(), expected);
+    }
+}
@@ -1,8 +1,48 @@
+//! Go `encoding/gob` streams in Rust.
+//!
+//! The quickest way in is the top-level helpers, which read and write complete gob
+//! streams (type definitions and message headers included):
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use gobx::Value;
+//!
+//! let mut map = BTreeMap::new();
+//! map.insert(Value::from("uid"), Value::Int(1));
+//! let bytes = gobx::to_vec(&Value::Map(map.clone())).unwrap();
+//! assert_eq!(gobx::from_slice(&bytes).unwrap(), Value::Map(map));
+//!
+//! let bytes = gobx::to_vec_typed(&42i64).unwrap();
+//! assert_eq!(gobx::from_slice_typed::<i64>(&bytes).unwrap(), 42);
+//! ```
+//!
+//! For more than one value per stream, use [`GobWriter`] and [`Decoder`] directly.
+//!
+//! Built without the default `std` feature, the crate is `no_std` and holds only
+//! [`codec`], which decodes gob primitives and message framing from byte slices.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
 // mod object; // Removed
+#[cfg(feature = "tokio")]
+mod async_decode;
+pub mod codec;
+#[cfg(feature = "std")]
 mod encode;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "std")]
 pub mod decode;
+#[cfg(feature = "std")]
+mod time;
+#[cfg(feature = "std")]
+pub mod transcode;
+#[cfg(feature = "std")]
 pub mod types;
+#[cfg(feature = "std")]
 pub mod value;
+#[cfg(feature = "std")]
+pub mod writer;
 
 // #[derive(Debug, thiserror::Error)]
 // pub enum Error {
@@ -11,20 +51,74 @@ pub mod value;
 // }
 
 
+#[cfg(feature = "std")]
 pub type Result<T> = std::result::Result<T, std::io::Error>;
 
-pub use decode::{Decoder, GobDecodable};
+#[cfg(feature = "tokio")]
+pub use async_decode::AsyncDecoder;
+#[cfg(feature = "std")]
+pub use decode::{Decoder, DuplicateKeyPolicy, GobDecodable, RawMessage};
+#[cfg(feature = "std")]
 pub use encode::{Encoder, GobEncodable, encode_as_interface};
+#[cfg(feature = "json")]
+pub use json::to_json;
+#[cfg(feature = "std")]
 pub use value::Value;
+#[cfg(feature = "std")]
+pub use transcode::{transcode, MessageSummary, TranscodeReport};
+#[cfg(feature = "std")]
+pub use writer::{GobWriter, MapOrder, StreamError};
 
 // Re-export macro
+#[cfg(feature = "std")]
 pub use gob_macro::Gob;
+#[cfg(feature = "std")]
 pub use gob_macro::Gob as gob;
 
+/// The derive form of the [`Gob`](macro@crate::Gob) attribute, for `#[derive(Gob)]`. It
+/// generates the same impls and leaves the item as written. The arguments that go in
+/// `#[Gob(...)]` move into the item's own `#[gob(...)]`:
+///
+/// ```
+/// use gobx::derive::Gob;
+///
+/// #[derive(Gob, Debug, Default, PartialEq)]
+/// #[gob(rename_all = "PascalCase")]
+/// struct Point {
+///     x: i64,
+///     #[gob(name = "Y")]
+///     y_pos: i64,
+/// }
+///
+/// let bytes = gobx::to_vec_typed(&Point { x: 1, y_pos: 2 }).unwrap();
+/// assert_eq!(gobx::from_slice_typed::<Point>(&bytes).unwrap(), Point { x: 1, y_pos: 2 });
+/// ```
+#[cfg(feature = "std")]
+pub mod derive {
+    pub use gob_macro::GobDerive as Gob;
+}
+
 pub trait GobType {
     const ID: i64;
 }
 
+/// A type with its own byte format, the counterpart of a Go type implementing
+/// `GobEncoder`/`GobDecoder`. On the wire its value is a byte slice holding whatever
+/// `gob_encode` returns, described by a gobEncoderType definition.
+///
+/// `#[gob(self_encoding)]` under `#[Gob]` routes a struct's encode and decode through this trait.
+#[cfg(feature = "std")]
+pub trait GobSelfEncoder: Sized {
+    fn gob_encode(&self) -> Result<Vec<u8>>;
+    fn gob_decode(bytes: &[u8]) -> Result<Self>;
+
+    /// Name sent in the type definition. Defaults to the Rust type's name without its path.
+    fn gob_type_name() -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
 #[macro_export]
 macro_rules! define_type_id {
     ($name:ty, $id:expr) => {
@@ -33,3 +127,53 @@ macro_rules! define_type_id {
         }
     };
 }
+
+define_type_id!(bool, 1);
+define_type_id!(i64, 2);
+define_type_id!(u64, 3);
+define_type_id!(i8, 2);
+define_type_id!(i16, 2);
+define_type_id!(i32, 2);
+define_type_id!(isize, 2);
+define_type_id!(u8, 3);
+define_type_id!(u16, 3);
+define_type_id!(u32, 3);
+define_type_id!(usize, 3);
+define_type_id!(f64, 4);
+define_type_id!(f32, 4);
+define_type_id!(char, 2);
+#[cfg(feature = "std")]
+define_type_id!(Vec<u8>, 5);
+#[cfg(feature = "std")]
+define_type_id!(String, 6);
+
+/// Encodes `value` as a complete gob stream holding one message.
+#[cfg(feature = "std")]
+pub fn to_vec(value: &Value) -> Result<Vec<u8>> {
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode(value)?;
+    writer.into_inner()
+}
+
+/// Encodes `value` as a complete gob stream holding one message of its own type, with
+/// the type definitions it needs. See [`GobWriter::encode_typed`].
+#[cfg(feature = "std")]
+pub fn to_vec_typed<T: GobEncodable>(value: &T) -> Result<Vec<u8>> {
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_typed(value)?;
+    writer.into_inner()
+}
+
+/// Decodes the first value in a gob stream.
+#[cfg(feature = "std")]
+pub fn from_slice(bytes: &[u8]) -> Result<Value> {
+    Decoder::new(bytes).read_next()?.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "gob stream holds no value")
+    })
+}
+
+/// Decodes the first value in a gob stream into `T`.
+#[cfg(feature = "std")]
+pub fn from_slice_typed<T: GobDecodable>(bytes: &[u8]) -> Result<T> {
+    Decoder::new(bytes).decode_into()
+}
@@ -1,155 +1,184 @@
-use gobx::{Decoder, Encoder, Gob, GobDecodable};
+use gobx::{Decoder, GobWriter, Value};
 use std::env;
 use std::fs::File;
-use std::io::{BufReader, Read, Seek};
+use std::io::{BufReader, BufWriter, Write};
 use std::process;
 
-#[Gob(id = 64, interpret_as = "map[interface{}]interface{}")]
-#[derive(Debug, Default)]
-struct UserInfo {
-    uid: i64,
-    uname: String,
-    email: String,
-    #[gob(name="_old_uid")] // Not supported by current macro
-    old_uid: String,
-    #[gob(name="userHasTwoFactorAuth")]
-    two_factor_auth: bool,
+fn usage(program: &str) -> ! {
+    eprintln!("Usage:");
+    eprintln!("  {} decode <gob_file>             print each value as JSON", program);
+    eprintln!("  {} print <gob_file>              print each value the way Go's %v would", program);
+    eprintln!("  {} inspect <gob_file>            dump the type definitions in the stream", program);
+    eprintln!("  {} encode <json_file> [out_file] encode a JSON value as gob (stdout by default)", program);
+    eprintln!("  {} transcode <gob_file> <out_file> decode and re-encode every message, reporting differences", program);
+    process::exit(1);
 }
 
-fn main() {
-    
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <gob_file>", args[0]);
-        process::exit(1);
-    }
-
-    let filename = &args[1];
+fn open_decoder(filename: &str) -> Decoder<BufReader<File>> {
     let file = File::open(filename).unwrap_or_else(|err| {
         eprintln!("Error opening file {}: {}", filename, err);
         process::exit(1);
     });
+    Decoder::new_buffered(file)
+}
+
+fn decode(filename: &str) -> gobx::Result<()> {
+    let mut decoder = open_decoder(filename);
+    while let Some(value) = decoder.read_next()? {
+        let json = serde_json::to_string_pretty(&value.to_json()).map_err(std::io::Error::other)?;
+        println!("{}", json);
+    }
+    Ok(())
+}
+
+fn print(filename: &str) -> gobx::Result<()> {
+    let mut decoder = open_decoder(filename);
+    while let Some(value) = decoder.read_next()? {
+        println!("{}", value);
+    }
+    Ok(())
+}
 
-    println!("Decoding {}...", filename);
-    let mut reader = BufReader::new(file);
-    
-    // Read original bytes for comparison
-    let mut original_bytes = Vec::new();
-    reader.read_to_end(&mut original_bytes).unwrap();
-    
-    // Reset reader for decoding
-    reader.get_mut().seek(std::io::SeekFrom::Start(0)).unwrap();
-    let mut decoder = Decoder::new(reader);
-    
-    // We will collect values to re-encode them
-    let mut values = Vec::new();
-
-    loop {
-        match decoder.read_next() {
-            Ok(Some(v)) => {
-                println!("Decoded Value: {:?}", v);
-                values.push(v);
-            }
-            Ok(None) => break,
-            Err(e) => {
-                eprintln!("Decoder error: {:?}", e);
-                // We might want to stop or continue depending on error
-                // For goth-session.bin, we fixed the EOF error, so it should finish cleanly.
-                break;
-            }
-        }
+fn inspect(filename: &str) -> gobx::Result<()> {
+    let mut decoder = open_decoder(filename);
+    let mut count = 0;
+    while decoder.read_next()?.is_some() {
+        count += 1;
     }
-    
-    // Test Encoding (Round Trip) for supported types
-    println!("\n--- Testing Encoder ---");
-    let mut buffer = Vec::new();
-    let mut encoder = Encoder::new(&mut buffer);
-    
-    for v in &values {
-        // We only test primitive encoding for now via Value::encode
-        // This won't produce a full valid Gob stream (missing TypeIDs/Length headers for top-level messages)
-        // But verifies the content encoding logic.
-        match v.encode(&mut encoder) {
-            Ok(_) => println!("Encoded successfully: {:?}", v),
-            Err(e) => println!("Skipping encoding for {:?}: {}", v, e),
-        }
+    println!("{} value message(s)", count);
+
+    let mut types: Vec<_> = decoder.types().iter().collect();
+    types.sort_by_key(|(id, _)| **id);
+    for (id, schema) in types {
+        println!("{:>4}: {:?}", id, schema);
     }
-    
-    if !buffer.is_empty() {
-        println!("Encoded {} bytes.", buffer.len());
-        // Hex dump first few bytes
-        println!("Encoded Hex:  {:?}", &buffer[..std::cmp::min(buffer.len(), 20)]);
-        println!("Original Hex: {:?}", &original_bytes[..std::cmp::min(original_bytes.len(), 20)]);
-        
-        if buffer.len() == original_bytes.len() {
-             if buffer == original_bytes {
-                 println!("SUCCESS: Encoded bytes match original file exactly!");
-             } else {
-                 println!("WARNING: Byte mismatch despite same length.");
-             }
-        } else {
-             println!("WARNING: Length mismatch (Encoded: {}, Original: {})", buffer.len(), original_bytes.len());
-             println!("Note: This is expected if the Encoder implementation is partial (missing Type definitions/headers).");
-        }
+    Ok(())
+}
+
+fn encode(json_file: &str, out_file: Option<&String>) -> gobx::Result<()> {
+    let json = std::fs::read_to_string(json_file)?;
+    let value: Value = serde_json::from_str(&json).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let out: Box<dyn Write> = match out_file {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut writer = GobWriter::new(BufWriter::new(out));
+    writer.encode(&value)?;
+    writer.flush()
+}
+
+fn transcode(filename: &str, out_file: Option<&String>) -> gobx::Result<()> {
+    let Some(out_file) = out_file else {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "transcode needs an output file"));
+    };
+    let input = BufReader::new(File::open(filename)?);
+    let report = gobx::transcode(input, BufWriter::new(File::create(out_file)?))?;
+    for (i, m) in report.messages.iter().enumerate() {
+        let status = if m.copied_raw { "copied" } else if m.matched { "same" } else { "differs" };
+        println!("{:>4}: {:<24} {:>6} -> {:<6} {}", i, m.type_name, m.input_size, m.output_size, status);
+    }
+    println!("{} bytes written", report.bytes_written);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        usage(&args[0]);
+    }
+
+    let result = match args[1].as_str() {
+        "decode" => decode(&args[2]),
+        "print" => print(&args[2]),
+        "inspect" => inspect(&args[2]),
+        "encode" => encode(&args[2], args.get(3)),
+        "transcode" => transcode(&args[2], args.get(3)),
+        _ => usage(&args[0]),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use redis::Commands;
-    
+    use gobx::Gob;
+
+    #[Gob(id = 64, interpret_as = "map[interface{}]interface{}")]
+    #[derive(Debug, Default)]
+    struct UserInfo {
+        uid: i64,
+        uname: String,
+        email: String,
+        // Go keeps this one as a string
+        #[gob(name="_old_uid", as_string)]
+        old_uid: i64,
+        #[gob(name="userHasTwoFactorAuth")]
+        two_factor_auth: bool,
+    }
+
+    // Fixtures are written by gen_fixtures.go; see the comment at its top.
+    const NORMAL_SESSION_2: &[u8] = include_bytes!("../normal-session-2.bin");
+
+    fn check_user_info(user_info: &UserInfo) {
+        assert_eq!(user_info.uid, 1);
+        assert_eq!(user_info.uname, "dsotsen");
+        assert_eq!(user_info.email, "dsotsen@qq.com");
+        assert_eq!(user_info.old_uid, 1);
+        assert!(!user_info.two_factor_auth);
+    }
+
     #[test]
     fn test_decode_user_info() {
-        let client = redis::Client::open("redis://cdn.mixstudio.tech:30002/0").unwrap();
-        let mut con = client.get_connection().unwrap();
-        //let _: () = con.set("test_key", "test_value").unwrap();
-        let buffer: Vec<u8> = con.get("aaac32bd1d759408").unwrap();
-        std::fs::write("normal-session-2.bin", &buffer).unwrap();
-        //assert_eq!(value, "test_value");
-        // let filename = "normal-session.bin";
-        // let mut file = File::open(filename).expect("Failed to open normal-session.bin");
-        // let mut buffer = Vec::new();
-        // file.read_to_end(&mut buffer).expect("Failed to read file");
-        let cursor = std::io::Cursor::new(&buffer);
-        let mut decoder = Decoder::new(cursor);
-        //println!("Test: Decoding generic values from {}", filename);
+        let mut decoder = Decoder::new(NORMAL_SESSION_2);
         let user_info: UserInfo = decoder.decode_into().expect("Failed to decode UserInfo");
-        println!("Decoded UserInfo: {:?}", user_info);
-        assert_eq!(user_info.uid, 1);
-        assert_eq!(user_info.uname, "dsotsen");
-        assert_eq!(user_info.old_uid, "1");
-        assert_eq!(user_info.two_factor_auth, false);
+        check_user_info(&user_info);
     }
 
     #[test]
     fn test_encode_user_info() {
-        //let client = redis::Client::open("redis://cdn.mixstudio.tech:30002/0").unwrap();
-        //let mut con = client.get_connection().unwrap();
-        //
-        //let buffer: Vec<u8> = con.get("aaac32bd1d759408").unwrap();
         let user_info = UserInfo {
             uname: "dsotsen".to_string(),
             email: "dsotsen@qq.com".to_string(),
             two_factor_auth: false,
-            old_uid: "1".to_string(),
+            old_uid: 1,
             uid: 1,
         };
-        // Test basic encoding works (doesn't crash)
-        let mut buffer = Vec::new();
-        let mut encoder = Encoder::new(&mut buffer);
-        
-        // Note: UserInfo.encode() currently encodes as struct (field deltas), not as map
-        // even though it has interpret_as="map[...]". The encode side needs more work.
-        // For now, just verify it doesn't crash.
-        user_info.encode(&mut encoder).expect("Failed to encode UserInfo");
-        
-        // Verify we got some data
-        assert!(!buffer.is_empty(), "Encoded buffer should not be empty");
-        // let _: () = con.set("aaac32bd1d759409", &buffer).unwrap();
-        println!("Encoded UserInfo to {} bytes", buffer.len());
-        
-        let file_buffer = std::fs::read("normal-session-2.bin").unwrap();
-        assert_eq!(buffer, file_buffer);
+        let mut writer = GobWriter::new(Vec::new());
+        user_info.encode_message(&mut writer).expect("Failed to encode UserInfo");
+        let buffer = writer.into_inner().unwrap();
+
+        // Same definition as Go's: map[interface{}]interface{} as id 64.
+        assert_eq!(buffer[..14], NORMAL_SESSION_2[..14]);
+
+        // Go orders map entries randomly, so compare decoded values rather than bytes.
+        let expected = Decoder::new(NORMAL_SESSION_2).read_next().unwrap();
+        let actual = Decoder::new(buffer.as_slice()).read_next().unwrap();
+        assert_eq!(actual, expected);
+
+        let decoded: UserInfo = Decoder::new(buffer.as_slice()).decode_into().unwrap();
+        check_user_info(&decoded);
+    }
+
+    // Reads a live session from Redis. Run with `cargo test -- --ignored`; the server and
+    // key default to the ones the fixtures were captured from and can be overridden with
+    // GOBX_REDIS_URL and GOBX_REDIS_KEY.
+    #[test]
+    #[ignore = "needs a live Redis server"]
+    fn test_decode_user_info_from_redis() {
+        use redis::Commands;
+
+        let url = env::var("GOBX_REDIS_URL").unwrap_or_else(|_| "redis://cdn.mixstudio.tech:30002/0".to_string());
+        let key = env::var("GOBX_REDIS_KEY").unwrap_or_else(|_| "aaac32bd1d759408".to_string());
+        let client = redis::Client::open(url).unwrap();
+        let mut con = client.get_connection().unwrap();
+        let buffer: Vec<u8> = con.get(key).unwrap();
+
+        let mut decoder = Decoder::new(buffer.as_slice());
+        let user_info: UserInfo = decoder.decode_into().expect("Failed to decode UserInfo");
+        check_user_info(&user_info);
     }
 }
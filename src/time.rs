@@ -0,0 +1,217 @@
+//! Go's `time.Time` as `std::time::SystemTime`, and `time.Duration` as `std::time::Duration`.
+//!
+//! `time.Time` is a `GobEncoder`: on the wire it is a byte slice holding what its
+//! `MarshalBinary` returns. Version 1 is 15 bytes: the version, the seconds since
+//! January 1, year 1 UTC (8 bytes, big-endian), the nanoseconds (4 bytes) and the zone
+//! offset in minutes (2 bytes, -1 for UTC). Version 2 adds a byte of offset seconds.
+//! The monotonic clock reading, and the 1885-based wall clock form Go keeps in memory,
+//! are never sent.
+//!
+//! `time.Duration` is an int64 of nanoseconds, so it is a plain gob int. It may be
+//! negative, which a `Duration` can't be.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::{Decoder, Encoder, GobDecodable, GobEncodable, GobSelfEncoder, GobType, Result};
+
+// Seconds from January 1, year 1 to the Unix epoch.
+const UNIX_TO_GO: i64 = 62_135_596_800;
+
+fn invalid(msg: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Parses the bytes of a Go `time.Time`. The zone offset only affects how Go displays
+/// the time, so it is dropped.
+pub(crate) fn parse_go_time(bytes: &[u8]) -> Result<SystemTime> {
+    match (bytes.first(), bytes.len()) {
+        (Some(1), 15) | (Some(2), 16) => {}
+        (Some(1 | 2), len) => return Err(invalid(format!("time.Time: invalid length {}", len))),
+        (Some(version), _) => return Err(invalid(format!("time.Time: unsupported version {}", version))),
+        (None, _) => return Err(invalid("time.Time: no data")),
+    }
+    let sec = i64::from_be_bytes(bytes[1..9].try_into().unwrap());
+    let nsec = u32::from_be_bytes(bytes[9..13].try_into().unwrap());
+    if nsec >= 1_000_000_000 {
+        return Err(invalid(format!("time.Time: {} nanoseconds out of range", nsec)));
+    }
+    let unix = sec.checked_sub(UNIX_TO_GO).ok_or_else(|| invalid("time.Time: seconds out of range"))?;
+    let time = if unix >= 0 {
+        UNIX_EPOCH.checked_add(Duration::new(unix as u64, nsec))
+    } else {
+        // `unix` seconds and then `nsec` forward again.
+        UNIX_EPOCH.checked_sub(Duration::from_secs(unix.unsigned_abs()))
+            .and_then(|t| t.checked_add(Duration::from_nanos(nsec as u64)))
+    };
+    time.ok_or_else(|| invalid(format!("time.Time: {}s from the Unix epoch is out of range for SystemTime", unix)))
+}
+
+/// The bytes Go's `MarshalBinary` gives `t` as a UTC time: version 1, offset -1.
+pub(crate) fn format_go_time(t: SystemTime) -> Result<Vec<u8>> {
+    let out_of_range = || std::io::Error::new(std::io::ErrorKind::InvalidInput, "SystemTime is out of range for time.Time");
+    let (unix, nsec) = match t.duration_since(UNIX_EPOCH) {
+        Ok(after) => (i64::try_from(after.as_secs()).map_err(|_| out_of_range())?, after.subsec_nanos()),
+        Err(before) => {
+            let before = before.duration();
+            let secs = i64::try_from(before.as_secs()).map_err(|_| out_of_range())?;
+            match before.subsec_nanos() {
+                0 => (-secs, 0),
+                n => (-secs - 1, 1_000_000_000 - n),
+            }
+        }
+    };
+    let sec = unix.checked_add(UNIX_TO_GO).ok_or_else(out_of_range)?;
+    let mut bytes = Vec::with_capacity(15);
+    bytes.push(1);
+    bytes.extend_from_slice(&sec.to_be_bytes());
+    bytes.extend_from_slice(&nsec.to_be_bytes());
+    bytes.extend_from_slice(&(-1i16).to_be_bytes());
+    Ok(bytes)
+}
+
+/// A Go `time.Time`, sent as `Time` like Go names it in type definitions.
+impl GobSelfEncoder for SystemTime {
+    fn gob_encode(&self) -> Result<Vec<u8>> {
+        format_go_time(*self)
+    }
+    fn gob_decode(bytes: &[u8]) -> Result<Self> {
+        parse_go_time(bytes)
+    }
+    fn gob_type_name() -> &'static str {
+        "Time"
+    }
+}
+
+/// Its type id is assigned by the stream, so only `GobWriter::encode_interface_typed` can
+/// put a `SystemTime` in an interface; `encode_as_interface` refuses it.
+impl GobType for SystemTime {
+    const ID: i64 = 0;
+}
+
+impl GobEncodable for SystemTime {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        encoder.write_bytes(&self.gob_encode()?)
+    }
+    fn type_name(&self) -> &'static str { "time.Time" }
+    fn write_type_definition<W: std::io::Write>(writer: &mut crate::GobWriter<W>) -> Result<i64> {
+        writer.define_self_encoded::<Self>()
+    }
+}
+
+impl GobDecodable for SystemTime {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        parse_go_time(&decoder.read_bytes()?)
+    }
+}
+
+/// A Go `time.Duration`: an int of nanoseconds. Durations over 292 years don't fit.
+impl GobEncodable for Duration {
+    fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
+        let nanos = i64::try_from(self.as_nanos()).map_err(|_| std::io::Error::new(
+            std::io::ErrorKind::InvalidInput, format!("{:?} is out of range for time.Duration", self)))?;
+        encoder.write_int(nanos)
+    }
+    fn type_id(&self) -> i64 { 2 } // Int
+    fn type_name(&self) -> &'static str { "time.Duration" }
+    fn write_type_definition<W: std::io::Write>(_: &mut crate::GobWriter<W>) -> Result<i64> { Ok(2) }
+}
+
+/// A Go `time.Duration`; a negative one is an error.
+impl GobDecodable for Duration {
+    fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> Result<Self> {
+        duration_from_nanos(decoder.read_int()?)
+    }
+}
+
+// Nanoseconds from the wire as a Duration, which can't be negative.
+pub(crate) fn duration_from_nanos(nanos: i64) -> Result<Duration> {
+    u64::try_from(nanos).map(Duration::from_nanos)
+        .map_err(|_| invalid(format!("time.Duration {}ns is negative", nanos)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    fn at(unix: i64, nanos: u32) -> SystemTime {
+        if unix >= 0 {
+            UNIX_EPOCH + Duration::new(unix as u64, nanos)
+        } else {
+            UNIX_EPOCH - Duration::from_secs(unix.unsigned_abs()) + Duration::from_nanos(nanos as u64)
+        }
+    }
+
+    #[test]
+    fn test_go_time_bytes() {
+        // MarshalBinary of time.Date(2024, 1, 2, 3, 4, 5, 6, time.UTC).
+        let utc = b"\x01\x00\x00\x00\x0e\xdd\x25\x74\x25\x00\x00\x00\x06\xff\xff";
+        let t = at(1_704_164_645, 6);
+        assert_eq!(parse_go_time(utc).unwrap(), t);
+        assert_eq!(format_go_time(t).unwrap(), utc);
+        // The same instant in time.FixedZone("", 3600): offset 60 minutes.
+        assert_eq!(parse_go_time(b"\x01\x00\x00\x00\x0e\xdd\x25\x74\x25\x00\x00\x00\x06\x00\x3c").unwrap(), t);
+        // And at +05:30:30, which needs version 2 for the offset seconds.
+        assert_eq!(parse_go_time(b"\x02\x00\x00\x00\x0e\xdd\x25\x74\x25\x00\x00\x00\x06\x01\x4a\x1e").unwrap(), t);
+
+        // Go keeps times from 1885 to 2157 in a shorter form in memory, but sends the same
+        // absolute seconds either side of it.
+        let cases: [(&[u8], SystemTime); 3] = [
+            (b"\x01\x00\x00\x00\x0d\xd7\xb1\x7f\x80\x00\x00\x00\x00\xff\xff", at(-2_682_288_000, 0)),
+            (b"\x01\x00\x00\x00\x0d\xd7\xb1\x7f\x7f\x3b\x9a\xc9\xff\xff\xff", at(-2_682_288_001, 999_999_999)),
+            // The zero time.Time, January 1, year 1.
+            (b"\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xff\xff", at(-UNIX_TO_GO, 0)),
+        ];
+        for (bytes, t) in cases {
+            assert_eq!(parse_go_time(bytes).unwrap(), t);
+            assert_eq!(format_go_time(t).unwrap(), bytes);
+        }
+
+        assert_eq!(parse_go_time(&utc[..14]).unwrap_err().to_string(), "time.Time: invalid length 14");
+        assert_eq!(parse_go_time(b"\x03").unwrap_err().to_string(), "time.Time: unsupported version 3");
+    }
+
+    #[test]
+    fn test_durations() {
+        // Go: enc.Encode(1500 * time.Millisecond), a plain int.
+        let d = Duration::from_millis(1500);
+        let bytes = crate::to_vec_typed(&d).unwrap();
+        assert_eq!(bytes, crate::to_vec_typed(&1_500_000_000i64).unwrap());
+        assert_eq!(crate::from_slice_typed::<Duration>(&bytes).unwrap(), d);
+
+        let err = crate::from_slice_typed::<Duration>(&crate::to_vec_typed(&-5i64).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "time.Duration -5ns is negative");
+        let err = crate::to_vec_typed(&Duration::from_secs(300 * 365 * 86400)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_system_time_messages() {
+        // Go: enc.Encode(time.Date(2024, 1, 2, 3, 4, 5, 6, time.UTC)). time.Time is a
+        // GobEncoder, defined as a gobEncoderType named "Time".
+        let go = b"\x0f\x7f\x05\x01\x01\x04Time\x01\xff\x80\x00\x00\x00\
+            \x13\xff\x80\x00\x0f\x01\x00\x00\x00\x0e\xdd\x25\x74\x25\x00\x00\x00\x06\xff\xff";
+        let t = at(1_704_164_645, 6);
+        assert_eq!(crate::from_slice_typed::<SystemTime>(go).unwrap(), t);
+        assert_eq!(crate::to_vec_typed(&t).unwrap(), go);
+        let value = crate::from_slice(go).unwrap();
+        assert!(matches!(&value, Value::GobEncoded(name, _) if name == "Time"));
+        assert_eq!(value.as_system_time(), Some(t));
+        assert_eq!(Value::from("2024-01-02").as_system_time(), None);
+
+        // Its type id is assigned per stream, so it can't go in a bare interface. A GobWriter
+        // sends the definition, then the interface under its registered name and that id, as
+        // Go's enc.Encode(&v) does for a v interface{} holding it.
+        let err = crate::encode_as_interface(&t, &mut Encoder::new(Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        let mut buf = Vec::new();
+        crate::GobWriter::new(&mut buf).encode_interface_typed(&t).unwrap();
+        let mut expected = go[..16].to_vec();
+        expected.extend_from_slice(b"\x20\x10\x00\x09time.Time\xff\x80\x11\x00\
+            \x0f\x01\x00\x00\x00\x0e\xdd\x25\x74\x25\x00\x00\x00\x06\xff\xff");
+        assert_eq!(buf, expected);
+        let value = crate::from_slice(&buf).unwrap();
+        assert!(matches!(&value, Value::GobEncoded(name, _) if name == "time.Time"));
+        assert_eq!(value.as_system_time(), Some(t));
+    }
+}
@@ -0,0 +1,144 @@
+//! Copying a gob stream through `Decoder` and `GobWriter`, for checking that the two agree.
+
+use std::io::{Read, Write};
+use crate::{Decoder, Encoder, GobWriter, RawMessage, Result};
+
+/// What happened to one value message during [`transcode`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSummary {
+    pub type_name: String,
+    /// Size of the value message read, header included, definitions excluded.
+    pub input_size: usize,
+    /// Size of the value message written, header included, definitions excluded.
+    pub output_size: usize,
+    /// Whether the written value message is byte-for-byte the one read.
+    pub matched: bool,
+    /// Set when the value couldn't be decoded or re-encoded and was copied as read.
+    pub copied_raw: bool,
+}
+
+/// Result of [`transcode`]: one summary per value message, in stream order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TranscodeReport {
+    pub messages: Vec<MessageSummary>,
+    /// Total bytes written, type definitions included.
+    pub bytes_written: u64,
+}
+
+impl TranscodeReport {
+    /// Whether every message was re-encoded to exactly the bytes it was read from.
+    pub fn all_matched(&self) -> bool {
+        self.messages.iter().all(|m| m.matched)
+    }
+}
+
+/// Reads every message of a gob stream, decodes it to a `Value`, and re-encodes it with a
+/// `GobWriter` to `writer`. Values the decoder or encoder can't handle are copied through
+/// unchanged instead. Type definitions are renumbered by the writer, so a value message
+/// only matches when the writer happens to assign the same ids as the source.
+pub fn transcode<R: Read, W: Write>(reader: R, mut writer: W) -> Result<TranscodeReport> {
+    let mut decoder = Decoder::new(reader);
+    let mut out = GobWriter::new(Vec::new());
+    let mut report = TranscodeReport::default();
+
+    while let Some(raw) = decoder.read_raw_message()? {
+        let reencoded = raw.decode().and_then(|value| out.encode(&value));
+        let copied_raw = reencoded.is_err();
+        if copied_raw {
+            out.write_raw_message(&raw)?;
+        }
+
+        let written = std::mem::take(out.get_mut());
+        let input = frame(&raw)?;
+        let output = last_message(&written);
+        report.messages.push(MessageSummary {
+            type_name: raw.type_name(),
+            input_size: input.len(),
+            output_size: output.len(),
+            matched: input == output,
+            copied_raw,
+        });
+        writer.write_all(&written)?;
+    }
+
+    writer.flush()?;
+    report.bytes_written = out.bytes_written();
+    Ok(report)
+}
+
+// The value message as it appeared on the source stream.
+fn frame(raw: &RawMessage) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    let mut enc = Encoder::new(&mut buf);
+    enc.write_uint((crate::codec::measure_int(raw.type_id) + raw.payload.len()) as u64)?;
+    enc.write_int(raw.type_id)?;
+    enc.write_all(&raw.payload)?;
+    Ok(buf)
+}
+
+// The last of the whole messages in `buf`: definitions are written before the value.
+fn last_message(mut buf: &[u8]) -> &[u8] {
+    let mut last = buf;
+    while let Some(&first) = buf.first() {
+        let (len, header) = if first < 128 {
+            (first as usize, 1)
+        } else {
+            let n = (!first).wrapping_add(1) as usize;
+            let len = buf[1..=n].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            (len, 1 + n)
+        };
+        let end = (header + len).min(buf.len());
+        last = &buf[..end];
+        buf = &buf[end..];
+    }
+    last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_transcode_own_output_matches() {
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("uid"), Value::Int(1));
+        map.insert(Value::from("name"), Value::from("ann"));
+        let mut input = Vec::new();
+        let mut writer = GobWriter::new(&mut input);
+        writer.encode(&Value::Map(map)).unwrap();
+        writer.encode(&Value::from("done")).unwrap();
+
+        let mut output = Vec::new();
+        let report = transcode(input.as_slice(), &mut output).unwrap();
+        assert_eq!(report.messages.len(), 2);
+        assert!(report.all_matched());
+        assert_eq!(report.messages[1].type_name, "string");
+        assert_eq!(output, input);
+        assert_eq!(report.bytes_written, output.len() as u64);
+    }
+
+    #[test]
+    fn test_transcode_go_session() {
+        let input = include_bytes!("../goth-session.bin");
+        let mut output = Vec::new();
+        let report = transcode(&input[..], &mut output).unwrap();
+        assert_eq!(report.messages.len(), 1);
+        assert!(!report.messages[0].copied_raw);
+        assert_eq!(crate::from_slice(&output).unwrap(), crate::from_slice(input).unwrap());
+    }
+
+    #[test]
+    fn test_transcode_copies_undecodable_values() {
+        // Go: enc.Encode([]int{1, 2}). Slices decode, but GobWriter can't encode them yet.
+        let mut input = b"\x0c\xff\x81\x02\x01\x02\xff\x82\x00\x01\x04\x00\x00".to_vec();
+        input.extend_from_slice(b"\x06\xff\x82\x00\x02\x02\x04");
+        let mut output = Vec::new();
+        let report = transcode(input.as_slice(), &mut output).unwrap();
+        assert!(report.messages[0].copied_raw);
+        assert_eq!(report.messages[0].type_name, "type 65");
+        assert!(report.messages[0].matched);
+        assert_eq!(output, input);
+    }
+}
@@ -1,5 +1,7 @@
 
-#[derive(Debug, Clone)]
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CommonType {
     pub name: String,
     pub id: i64,
@@ -21,15 +23,17 @@ pub struct MapType {
     pub elem: i64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StructType {
     pub common: CommonType,
     pub fields: Vec<FieldType>,
 }
 
-#[derive(Debug, Clone)]
+/// A struct field: its Go name and type id. The name borrows a `&'static str` where it
+/// can, so `#[Gob]` structs list theirs in a `GOB_FIELDS` constant.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FieldType {
-    pub name: String,
+    pub name: Cow<'static, str>,
     pub id: i64,
 }
 
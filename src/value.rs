@@ -3,21 +3,60 @@ use std::cmp::Ordering;
 use serde::{Serialize, Deserialize};
 use crate::{Encoder, Result};
 
+/// With the `serde-tagged` feature, `Value` serializes as `{"type": <variant>, "value": <content>}`
+/// so formats like JSON deserialize back into the exact same variant (Int vs Uint, Bytes vs Array).
+/// The default untagged form is more readable but cannot round-trip.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+#[cfg_attr(not(feature = "serde-tagged"), serde(untagged))]
+#[cfg_attr(feature = "serde-tagged", serde(tag = "type", content = "value"))]
 pub enum Value {
     Nil,
     Bool(bool),
     Int(i64),
     Uint(u64),
     Float(f64),
+    /// A Go complex128, or a complex64 widened to it: the real and imaginary parts.
+    Complex(f64, f64),
     String(String),
     #[serde(with = "serde_bytes")]
     Bytes(Vec<u8>),
     Array(Vec<Value>),
     // Using BTreeMap for consistent ordering and Hash/Eq requirements
+    #[cfg_attr(feature = "serde-tagged", serde(with = "map_entries"))]
     Map(BTreeMap<Value, Value>), 
+    /// A map with its entries in a chosen order, e.g. the order they were decoded in
+    /// (see `Decoder::set_preserve_map_order`). Encodes like `Map`.
+    OrderedMap(Vec<(Value, Value)>),
     Struct(String, BTreeMap<String, Value>), // Name, Fields
+    /// A struct with its fields in a chosen order, e.g. the order of the Go type's
+    /// definition (see `Decoder::set_preserve_field_order`). `GobWriter` defines and sends
+    /// the fields in this order.
+    OrderedStruct(String, Vec<(String, Value)>),
+    /// A value of a Go type with its own encoding (a `GobEncoder`, `BinaryMarshaler` or
+    /// `TextMarshaler`): the type name and the bytes that encoder produced.
+    GobEncoded(String, #[serde(with = "serde_bytes")] Vec<u8>),
+    /// A value left undecoded (see `Decoder::set_raw_type_ids`): its wire type id, as
+    /// numbered by the stream it came from, and its encoded bytes. `GobWriter` sends the
+    /// bytes back unchanged.
+    Raw(i64, #[serde(with = "serde_bytes")] Vec<u8>),
+}
+
+// Map keys are arbitrary values, which JSON objects can't hold, so the tagged form
+// writes maps as a list of [key, value] pairs.
+#[cfg(feature = "serde-tagged")]
+mod map_entries {
+    use super::Value;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(map: &BTreeMap<Value, Value>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<Value, Value>, D::Error> {
+        let entries = Vec::<(Value, Value)>::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
 }
 
 impl From<&str> for Value {
@@ -53,12 +92,23 @@ impl TryFrom<Value> for i64 {
     fn try_from(v: Value) -> std::result::Result<Self, Self::Error> {
         match v {
             Value::Int(i) => Ok(i),
-            Value::Uint(u) => Ok(u as i64), // Loose conversion
+            Value::Uint(u) => i64::try_from(u).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} overflows i64", u))),
             _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Expected Int, got {:?}", v))),
         }
     }
 }
 
+impl TryFrom<Value> for u64 {
+    type Error = std::io::Error;
+    fn try_from(v: Value) -> std::result::Result<Self, Self::Error> {
+        match v {
+            Value::Uint(u) => Ok(u),
+            Value::Int(i) => u64::try_from(i).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} is negative, expected Uint", i))),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Expected Uint, got {:?}", v))),
+        }
+    }
+}
+
 impl TryFrom<Value> for bool {
     type Error = std::io::Error;
     fn try_from(v: Value) -> std::result::Result<Self, Self::Error> {
@@ -69,34 +119,272 @@ impl TryFrom<Value> for bool {
     }
 }
 
-impl Into<Value> for i64 {
-    fn into(self) -> Value {
-        Value::Int(self)
+// Map-mode fields of the narrower integer widths take an Int or Uint that fits.
+macro_rules! impl_try_from_value_int {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<Value> for $ty {
+            type Error = std::io::Error;
+            fn try_from(v: Value) -> std::result::Result<Self, Self::Error> {
+                let converted = match v {
+                    Value::Int(i) => <$ty>::try_from(i).ok(),
+                    Value::Uint(u) => <$ty>::try_from(u).ok(),
+                    _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Expected Int, got {:?}", v))),
+                };
+                converted.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:?} overflows {}", v, stringify!($ty))))
+            }
+        }
+    )*};
+}
+
+impl_try_from_value_int!(i8, i16, i32, isize, u8, u16, u32, usize);
+
+// A Go time.Duration, sent as an int of nanoseconds.
+impl TryFrom<Value> for std::time::Duration {
+    type Error = std::io::Error;
+    fn try_from(v: Value) -> std::result::Result<Self, Self::Error> {
+        match v {
+            Value::Int(i) => crate::time::duration_from_nanos(i),
+            Value::Uint(u) => Ok(std::time::Duration::from_nanos(u)),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Expected Int, got {:?}", v))),
+        }
+    }
+}
+
+// A Go rune, sent as an int.
+impl TryFrom<Value> for char {
+    type Error = std::io::Error;
+    fn try_from(v: Value) -> std::result::Result<Self, Self::Error> {
+        let code = u32::try_from(v.clone())?;
+        char::from_u32(code).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:?} is not a valid char", v)))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Int(v)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(v: u64) -> Self {
+        Value::Uint(v)
+    }
+}
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Value::Int(v as i64)
     }
 }
 
-impl Into<Value> for u64 {
-    fn into(self) -> Value {
-        Value::Uint(self)
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Float(v)
     }
 }
-impl Into<Value> for f64 {
-    fn into(self) -> Value {
-        Value::Float(self)
+
+impl From<Vec<u8>> for Value {
+    fn from(v: Vec<u8>) -> Self {
+        Value::Bytes(v)
     }
 }
 
-impl Into<Value> for Vec<u8> {
-    fn into(self) -> Value {
-        Value::Bytes(self)
+impl From<Vec<Value>> for Value {
+    fn from(v: Vec<Value>) -> Self {
+        Value::Array(v)
     }
 }
 
+/// `Value::from([("a", 1), ("b", 2)])` is a map with those entries.
+impl<K: Into<Value>, V: Into<Value>, const N: usize> From<[(K, V); N]> for Value {
+    fn from(entries: [(K, V); N]) -> Self {
+        Value::Map(entries.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+/// A struct named "Struct", the name the decoder gives structs it reads; see [`Value::struct_from`].
+impl From<Vec<(String, Value)>> for Value {
+    fn from(fields: Vec<(String, Value)>) -> Self {
+        fields.into_iter().collect()
+    }
+}
+
+/// Collects fields into a struct named "Struct". Use [`Value::struct_from`] or
+/// [`GobWriter::encode_with_name`](crate::GobWriter::encode_with_name) to send another name.
+impl FromIterator<(String, Value)> for Value {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(fields: I) -> Self {
+        Value::struct_from("Struct", fields)
+    }
+}
 
 // Type alias for map[interface{}]interface{}
 pub type GobMap = BTreeMap<Value, Value>;
 
+/// Builds a [`Value::Map`]; see [`Value::map`].
+#[derive(Debug, Clone, Default)]
+pub struct MapBuilder(GobMap);
+
+impl MapBuilder {
+    pub fn insert(mut self, key: impl Into<Value>, value: impl Into<Value>) -> Self {
+        self.0.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Value::Map(self.0)
+    }
+}
+
+impl From<MapBuilder> for Value {
+    fn from(builder: MapBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Builds a [`Value::Struct`]; see [`Value::struct_`].
+#[derive(Debug, Clone)]
+pub struct StructBuilder {
+    name: String,
+    fields: BTreeMap<String, Value>,
+}
+
+impl StructBuilder {
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.fields.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Value::Struct(self.name, self.fields)
+    }
+}
+
+impl From<StructBuilder> for Value {
+    fn from(builder: StructBuilder) -> Self {
+        builder.build()
+    }
+}
+
 impl Value {
+    /// Name of the variant, e.g. "Int" or "Struct", for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Nil => "Nil",
+            Value::Bool(_) => "Bool",
+            Value::Int(_) => "Int",
+            Value::Uint(_) => "Uint",
+            Value::Float(_) => "Float",
+            Value::Complex(..) => "Complex",
+            Value::String(_) => "String",
+            Value::Bytes(_) => "Bytes",
+            Value::Array(_) => "Array",
+            Value::Map(_) => "Map",
+            Value::OrderedMap(_) => "OrderedMap",
+            Value::Struct(..) => "Struct",
+            Value::OrderedStruct(..) => "OrderedStruct",
+            Value::GobEncoded(..) => "GobEncoded",
+            Value::Raw(..) => "Raw",
+        }
+    }
+
+    /// The message of a Go `error` sent through an `interface{}`. Go's own
+    /// `*errors.errorString` can't be gob-encoded, so this covers the shapes error types
+    /// that can be sent take: a `GobEncoded` value whose bytes are the UTF-8 message (what
+    /// a `GobEncode` returning `[]byte(e.Error())` sends), a struct with a single string
+    /// field, or a plain string.
+    ///
+    /// ```
+    /// use gobx::Value;
+    ///
+    /// let err = Value::GobEncoded("*rpc.Error".to_string(), b"not found".to_vec());
+    /// assert_eq!(err.as_error_message(), Some("not found"));
+    /// let err = Value::struct_("errorString").field("S", "boom").build();
+    /// assert_eq!(err.as_error_message(), Some("boom"));
+    /// ```
+    pub fn as_error_message(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            Value::GobEncoded(_, bytes) => std::str::from_utf8(bytes).ok(),
+            Value::Struct(_, fields) if fields.len() == 1 => match fields.values().next() {
+                Some(Value::String(s)) => Some(s),
+                _ => None,
+            },
+            Value::OrderedStruct(_, fields) => match fields.as_slice() {
+                [(_, Value::String(s))] => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// A string holding a number as that number: Int for "-3", Uint for integers past
+    /// i64, Float for "1.5". Anything else is returned unchanged.
+    pub fn parse_numeric(self) -> Value {
+        let Value::String(s) = &self else { return self };
+        let s = s.trim();
+        if let Ok(v) = s.parse::<i64>() {
+            Value::Int(v)
+        } else if let Ok(v) = s.parse::<u64>() {
+            Value::Uint(v)
+        } else if let Ok(v) = s.parse::<f64>() {
+            Value::Float(v)
+        } else {
+            self
+        }
+    }
+
+    /// Starts a map value:
+    ///
+    /// ```
+    /// use gobx::Value;
+    ///
+    /// let payload = Value::map()
+    ///     .insert("uid", 1)
+    ///     .insert("user", Value::struct_("User").field("Name", "ann"))
+    ///     .build();
+    /// let bytes = gobx::to_vec(&payload).unwrap();
+    /// assert_eq!(gobx::from_slice(&bytes).unwrap().to_string(), "map[uid:1 user:{Name:ann}]");
+    /// ```
+    pub fn map() -> MapBuilder {
+        MapBuilder::default()
+    }
+
+    /// Starts a struct value named `name`, the type name sent in its definition.
+    pub fn struct_(name: impl Into<String>) -> StructBuilder {
+        StructBuilder { name: name.into(), fields: BTreeMap::new() }
+    }
+
+    /// A struct value with the given fields, e.g. from a `HashMap` of dynamic fields.
+    /// A later field with the same name replaces an earlier one.
+    pub fn struct_from<I: IntoIterator<Item = (String, Value)>>(name: impl Into<String>, fields: I) -> Value {
+        Value::Struct(name.into(), fields.into_iter().collect())
+    }
+
+    /// The bytes of a Go `[N]byte`, which decodes as an array of N uints, or of a byte
+    /// slice that happens to be N long. None for anything else.
+    pub fn as_fixed_bytes<const N: usize>(&self) -> Option<[u8; N]> {
+        match self {
+            Value::Bytes(bytes) => bytes.as_slice().try_into().ok(),
+            Value::Array(items) if items.len() == N => {
+                let mut out = [0u8; N];
+                for (b, item) in out.iter_mut().zip(items) {
+                    let Value::Uint(v) = item else { return None };
+                    *b = u8::try_from(*v).ok()?;
+                }
+                Some(out)
+            }
+            _ => None,
+        }
+    }
+
+    /// The instant a Go `time.Time` holds, for the `GobEncoded` value it decodes as. None
+    /// for anything else, or a time `SystemTime` can't represent.
+    pub fn as_system_time(&self) -> Option<std::time::SystemTime> {
+        match self {
+            Value::GobEncoded(_, bytes) => crate::time::parse_go_time(bytes).ok(),
+            _ => None,
+        }
+    }
+
     pub fn encode<W: std::io::Write>(&self, encoder: &mut Encoder<W>) -> Result<()> {
          // This is a naive implementation that just encodes the value itself.
          // In real Gob, we need to transmit Type Definitions (WireTypes) first if they are new.
@@ -116,8 +404,13 @@ impl Value {
              Value::Int(v) => encoder.write_int(*v),
              Value::Uint(v) => encoder.write_uint(*v),
              Value::Float(v) => encoder.write_float(*v),
+             Value::Complex(re, im) => {
+                 encoder.write_float(*re)?;
+                 encoder.write_float(*im)
+             }
              Value::String(v) => encoder.write_string(v),
-             Value::Bytes(v) => encoder.write_bytes(v),
+             Value::Bytes(v) | Value::GobEncoded(_, v) => encoder.write_bytes(v),
+             Value::Raw(_, bytes) => encoder.write_all(bytes),
              Value::Array(v) => {
                  encoder.write_uint(v.len() as u64)?;
                  for item in v {
@@ -133,7 +426,15 @@ impl Value {
                  }
                  Ok(())
              }
-             Value::Struct(_name, fields) => {
+             Value::OrderedMap(entries) => {
+                 encoder.write_uint(entries.len() as u64)?;
+                 for (k, v) in entries {
+                     k.encode(encoder)?;
+                     v.encode(encoder)?;
+                 }
+                 Ok(())
+             }
+             Value::Struct(..) | Value::OrderedStruct(..) => {
                  // Structs in Gob are delta-encoded.
                  // We need to know the field numbers from the schema.
                  // Without schema, we can't properly encode a struct that a standard Gob decoder would understand
@@ -144,7 +445,7 @@ impl Value {
                  
                  // Let's just iterate and assume field numbers increment (1, 2, 3...)?
                  // Or maybe we just skip implementation for generic structs for now without schema awareness.
-                 Err(std::io::Error::new(std::io::ErrorKind::Other, "Encoding generic structs not yet supported without schema"))
+                 Err(std::io::Error::other("Encoding generic structs not yet supported without schema"))
              }
          }
     }
@@ -158,11 +459,16 @@ impl PartialEq for Value {
             (Value::Int(a), Value::Int(b)) => a == b,
             (Value::Uint(a), Value::Uint(b)) => a == b,
             (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Complex(r1, i1), Value::Complex(r2, i2)) => r1.to_bits() == r2.to_bits() && i1.to_bits() == i2.to_bits(),
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Bytes(a), Value::Bytes(b)) => a == b,
             (Value::Array(a), Value::Array(b)) => a == b,
             (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::OrderedMap(a), Value::OrderedMap(b)) => a == b,
             (Value::Struct(n1, f1), Value::Struct(n2, f2)) => n1 == n2 && f1 == f2,
+            (Value::OrderedStruct(n1, f1), Value::OrderedStruct(n2, f2)) => n1 == n2 && f1 == f2,
+            (Value::GobEncoded(n1, b1), Value::GobEncoded(n2, b2)) => n1 == n2 && b1 == b2,
+            (Value::Raw(t1, b1), Value::Raw(t2, b2)) => t1 == t2 && b1 == b2,
             _ => false,
         }
     }
@@ -202,6 +508,10 @@ impl Ord for Value {
             (Float(_), _) => Ordering::Less,
             (_, Float(_)) => Ordering::Greater,
 
+            (Complex(r1, i1), Complex(r2, i2)) => (r1.to_bits(), i1.to_bits()).cmp(&(r2.to_bits(), i2.to_bits())),
+            (Complex(..), _) => Ordering::Less,
+            (_, Complex(..)) => Ordering::Greater,
+
             (String(a), String(b)) => a.cmp(b),
             (String(_), _) => Ordering::Less,
             (_, String(_)) => Ordering::Greater,
@@ -217,6 +527,10 @@ impl Ord for Value {
             (Map(a), Map(b)) => a.cmp(b),
             (Map(_), _) => Ordering::Less,
             (_, Map(_)) => Ordering::Greater,
+
+            (OrderedMap(a), OrderedMap(b)) => a.cmp(b),
+            (OrderedMap(_), _) => Ordering::Less,
+            (_, OrderedMap(_)) => Ordering::Greater,
             
             (Struct(n1, f1), Struct(n2, f2)) => {
                 match n1.cmp(n2) {
@@ -224,6 +538,164 @@ impl Ord for Value {
                     ord => ord,
                 }
             }
+            (Struct(..), _) => Ordering::Less,
+            (_, Struct(..)) => Ordering::Greater,
+
+            (OrderedStruct(n1, f1), OrderedStruct(n2, f2)) => (n1, f1).cmp(&(n2, f2)),
+            (OrderedStruct(..), _) => Ordering::Less,
+            (_, OrderedStruct(..)) => Ordering::Greater,
+
+            (GobEncoded(n1, b1), GobEncoded(n2, b2)) => (n1, b1).cmp(&(n2, b2)),
+            (GobEncoded(..), _) => Ordering::Less,
+            (_, GobEncoded(..)) => Ordering::Greater,
+
+            (Raw(t1, b1), Raw(t2, b2)) => (t1, b1).cmp(&(t2, b2)),
+        }
+    }
+}
+
+/// Renders the value the way Go's `fmt` prints it with `%v`, except that struct fields are
+/// named as with `%+v`: `map[a:1 b:[1 2]]`, `{Age:30 Name:ann}`, `<nil>`. Byte slices
+/// print as hex (like `%x`), and floats use Go's shortest `%g` form (`1e+06`, `0.5`).
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Nil => f.write_str("<nil>"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Uint(u) => write!(f, "{}", u),
+            Value::Float(v) => write_go_float(f, *v),
+            Value::Complex(re, im) => {
+                // Go prints (1+2i); the imaginary part always carries its sign.
+                f.write_str("(")?;
+                write_go_float(f, *re)?;
+                if im.is_nan() || (im.is_sign_positive() && im.is_finite()) {
+                    f.write_str("+")?;
+                }
+                write_go_float(f, *im)?;
+                f.write_str("i)")
+            }
+            Value::String(s) => f.write_str(s),
+            Value::Bytes(b) | Value::GobEncoded(_, b) | Value::Raw(_, b) => b.iter().try_for_each(|byte| write!(f, "{:02x}", byte)),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 { f.write_str(" ")?; }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Map(map) => write_go_map(f, map.iter()),
+            Value::OrderedMap(entries) => write_go_map(f, entries.iter().map(|(k, v)| (k, v))),
+            Value::Struct(_, fields) => write_go_struct(f, fields.iter()),
+            Value::OrderedStruct(_, fields) => write_go_struct(f, fields.iter().map(|(k, v)| (k, v))),
         }
     }
 }
+
+fn write_go_struct<'a>(f: &mut std::fmt::Formatter<'_>, fields: impl Iterator<Item = (&'a String, &'a Value)>) -> std::fmt::Result {
+    f.write_str("{")?;
+    for (i, (name, v)) in fields.enumerate() {
+        if i > 0 { f.write_str(" ")?; }
+        write!(f, "{}:{}", name, v)?;
+    }
+    f.write_str("}")
+}
+
+fn write_go_map<'a>(f: &mut std::fmt::Formatter<'_>, entries: impl Iterator<Item = (&'a Value, &'a Value)>) -> std::fmt::Result {
+    f.write_str("map[")?;
+    for (i, (k, v)) in entries.enumerate() {
+        if i > 0 { f.write_str(" ")?; }
+        write!(f, "{}:{}", k, v)?;
+    }
+    f.write_str("]")
+}
+
+// Go's %v for a float64 is strconv's shortest 'g' format, which switches to an exponent
+// (at least two digits, always signed) below 1e-4 and from 1e+06 up.
+fn write_go_float(f: &mut std::fmt::Formatter<'_>, v: f64) -> std::fmt::Result {
+    if v.is_nan() {
+        return f.write_str("NaN");
+    }
+    if v.is_infinite() {
+        return f.write_str(if v > 0.0 { "+Inf" } else { "-Inf" });
+    }
+    let sci = format!("{:e}", v);
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let exp: i32 = exp.parse().unwrap_or(0);
+    if v != 0.0 && !(-4..6).contains(&exp) {
+        let sign = if exp < 0 { '-' } else { '+' };
+        write!(f, "{}e{}{:02}", mantissa, sign, exp.abs())
+    } else {
+        write!(f, "{}", v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_like_go() {
+        let mut fields = BTreeMap::new();
+        fields.insert("Age".to_string(), Value::Int(30));
+        fields.insert("Name".to_string(), Value::from("ann"));
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("user"), Value::Struct("User".to_string(), fields));
+        map.insert(Value::from("ids"), Value::Array(vec![Value::Uint(1), Value::Uint(2)]));
+        map.insert(Value::from("raw"), Value::Bytes(vec![0xde, 0xad]));
+        map.insert(Value::from("none"), Value::Nil);
+        assert_eq!(Value::Map(map).to_string(), "map[ids:[1 2] none:<nil> raw:dead user:{Age:30 Name:ann}]");
+
+        let floats = [(3.0, "3"), (0.5, "0.5"), (123456.0, "123456"), (1e6, "1e+06"), (1.5e-7, "1.5e-07"), (0.0001, "0.0001"), (f64::NEG_INFINITY, "-Inf")];
+        for (v, go) in floats {
+            assert_eq!(Value::Float(v).to_string(), go);
+        }
+    }
+
+    #[test]
+    fn test_builders() {
+        let built = Value::map().insert("a", 1).insert(Value::Uint(2), Value::struct_("P").field("X", 1.5)).build();
+        let mut fields = BTreeMap::new();
+        fields.insert("X".to_string(), Value::Float(1.5));
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("a"), Value::Int(1));
+        map.insert(Value::Uint(2), Value::Struct("P".to_string(), fields));
+        assert_eq!(built, Value::Map(map));
+
+        assert_eq!(Value::from([("a", 1), ("b", 2)]), Value::map().insert("a", 1).insert("b", 2).build());
+        assert_eq!(Value::from(vec![Value::Int(1)]), Value::Array(vec![Value::Int(1)]));
+
+        let dynamic = std::collections::HashMap::from([("X".to_string(), Value::Float(1.5))]);
+        assert_eq!(Value::struct_from("P", dynamic), Value::struct_("P").field("X", 1.5).build());
+        let collected: Value = [("X".to_string(), Value::Int(1))].into_iter().collect();
+        assert_eq!(collected, Value::from(vec![("X".to_string(), Value::Int(1))]));
+        assert_eq!(collected, Value::struct_("Struct").field("X", 1).build());
+    }
+
+    #[test]
+    fn test_parse_numeric() {
+        assert_eq!(Value::from(" 42").parse_numeric(), Value::Int(42));
+        assert_eq!(Value::from("18446744073709551615").parse_numeric(), Value::Uint(u64::MAX));
+        assert_eq!(Value::from("1.5").parse_numeric(), Value::Float(1.5));
+        assert_eq!(Value::from("ann").parse_numeric(), Value::from("ann"));
+        assert_eq!(Value::Bool(true).parse_numeric().kind(), "Bool");
+    }
+
+    #[cfg(feature = "serde-tagged")]
+    #[test]
+    fn test_tagged_json_round_trip() {
+        let mut fields = BTreeMap::new();
+        fields.insert("Id".to_string(), Value::Uint(7));
+        fields.insert("Raw".to_string(), Value::Bytes(vec![1, 2, 3]));
+        let mut map = BTreeMap::new();
+        map.insert(Value::Int(1), Value::Array(vec![Value::Uint(1), Value::Int(2), Value::Float(3.0)]));
+        map.insert(Value::from("s"), Value::Struct("User".to_string(), fields));
+        map.insert(Value::Bool(true), Value::Nil);
+        let value = Value::Map(map);
+
+        let json = serde_json::to_value(&value).unwrap();
+        let back: Value = serde_json::from_value(json).unwrap();
+        assert_eq!(back, value);
+    }
+}
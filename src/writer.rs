@@ -1,12 +1,59 @@
-use std::collections::{HashMap, BTreeMap};
-use std::io::{Write, Seek, Cursor};
-use crate::{Encoder, Result, Value};
-use crate::decode::TypeSchema;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use crate::{codec, Encoder, GobEncodable, GobSelfEncoder, GobType, Result, Value};
+use crate::encode::{put_buf, take_buf};
+use crate::decode::{Decoder, RawMessage, TypeSchema};
 
+/// Encodes `Value`s as a gob stream, sending each type definition once before its first use.
+///
+/// Finish the stream with `into_inner` (or at least `flush`) so a buffering writer's tail
+/// is written and any error is reported; dropping the writer does not flush.
 pub struct GobWriter<W: Write> {
     encoder: Encoder<W>,
-    type_ids: HashMap<String, i64>, // Name/Signature -> ID
+    type_ids: HashMap<String, i64>, // Structural signature -> ID
+    reserved: HashMap<String, i64>, // Struct name -> explicitly chosen ID
+    defined: BTreeMap<i64, TypeSchema>, // Definitions already sent on this stream
     next_id: i64,
+    scratch: Vec<Vec<u8>>, // Reused message and interface value buffers
+    raw_types: HashMap<i64, Vec<u8>>, // Definitions passed through by write_raw_message
+    type_names: HashMap<String, String>, // Value::Struct name -> name sent on the wire
+    map_order: MapOrder,
+}
+
+// Ids below this are gob's predefined types. Go hands out user ids from here too.
+const FIRST_USER_ID: i64 = 64;
+
+// Predefined id for interface{}.
+const INTERFACE_ID: i64 = 8;
+
+// encode_stream flushes after this many messages.
+const STREAM_FLUSH_EVERY: usize = 256;
+
+/// An item of `GobWriter::encode_stream` failed to encode. Items before `index` were
+/// written; nothing of the failing item's value message was.
+#[derive(Debug, thiserror::Error)]
+#[error("failed to encode stream item {index}: {source}")]
+pub struct StreamError {
+    pub index: usize,
+    #[source]
+    pub source: std::io::Error,
+}
+
+impl From<StreamError> for std::io::Error {
+    fn from(e: StreamError) -> Self {
+        std::io::Error::new(e.source.kind(), e)
+    }
+}
+
+/// Order in which `GobWriter` writes map entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapOrder {
+    /// By key, for `Value::OrderedMap` as well as `Value::Map`, so output depends only on content.
+    #[default]
+    Sorted,
+    /// `Value::OrderedMap` entries in their stored order, e.g. as decoded from a Go stream,
+    /// so decoding and re-encoding keeps Go's byte layout. `Value::Map` is always by key.
+    Insertion,
 }
 
 impl<W: Write> GobWriter<W> {
@@ -14,7 +61,13 @@ impl<W: Write> GobWriter<W> {
         Self {
             encoder: Encoder::new(writer),
             type_ids: HashMap::new(),
-            next_id: 65,
+            reserved: HashMap::new(),
+            defined: BTreeMap::new(),
+            next_id: FIRST_USER_ID,
+            scratch: Vec::new(),
+            raw_types: HashMap::new(),
+            type_names: HashMap::new(),
+            map_order: MapOrder::default(),
         }
     }
 
@@ -22,56 +75,586 @@ impl<W: Write> GobWriter<W> {
         self.encoder.flush()
     }
 
+    /// Total bytes written to the stream so far, type definitions included.
+    pub fn bytes_written(&self) -> u64 {
+        self.encoder.bytes_written()
+    }
+
+    /// Flushes and returns the underlying writer, finishing the stream.
+    pub fn into_inner(self) -> Result<W> {
+        self.encoder.into_inner()
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        self.encoder.get_mut()
+    }
+
+    /// Type definitions this writer has emitted so far, by type id.
+    /// Each is sent once per writer, before the first message that needs it.
+    pub fn defined_types(&self) -> &BTreeMap<i64, TypeSchema> {
+        &self.defined
+    }
+
+    /// Declares definitions the peer already has, e.g. sent by an earlier writer on the
+    /// same connection, so this writer uses their ids and doesn't send them again. The
+    /// schemas are as `defined_types` or `Decoder::types` give them; a struct is matched
+    /// by its field names and types. Fails on a predefined id or one this writer already
+    /// defined differently.
+    pub fn preload_types(&mut self, types: &[(i64, TypeSchema)]) -> Result<()> {
+        for (id, schema) in types {
+            if *id < FIRST_USER_ID {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} is reserved for predefined gob types", id)));
+            }
+            match self.defined.get(id) {
+                Some(existing) if existing == schema => continue,
+                Some(_) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} was already sent with a different definition", id))),
+                None if self.id_in_use(*id) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} is already used by this writer", id))),
+                None => {}
+            }
+            self.adopt_definition(*id, schema.clone());
+        }
+        Ok(())
+    }
+
+    // Records a definition sent by someone else so this writer's own values of the same
+    // shape use its id. Structs are matched later, by `preloaded_struct`.
+    fn adopt_definition(&mut self, id: i64, schema: TypeSchema) {
+        match &schema {
+            TypeSchema::Map(key_id, elem_id) => {
+                self.type_ids.entry(format!("Map({},{})", key_id, elem_id)).or_insert(id);
+            }
+            TypeSchema::Slice(elem_id) => {
+                self.type_ids.entry(format!("Slice({})", elem_id)).or_insert(id);
+            }
+            _ => {}
+        }
+        self.defined.insert(id, schema);
+    }
+
+    // A preloaded struct definition with these fields, not yet tied to a struct name.
+    fn preloaded_struct(&self, fields: &[(String, i64)]) -> Option<i64> {
+        self.defined.iter().find_map(|(&id, schema)| match schema {
+            TypeSchema::Struct(defined) if !self.type_ids.values().any(|&v| v == id)
+                && defined.len() == fields.len()
+                && defined.iter().zip(fields).all(|(d, f)| d.1 == f.1 && d.2 == f.0) => Some(id),
+            _ => None,
+        })
+    }
+
     fn get_type_id(&mut self, schema_key: &str) -> Option<i64> {
         self.type_ids.get(schema_key).cloned()
     }
 
-    fn assign_type_id(&mut self, schema_key: String) -> i64 {
-        let id = self.next_id;
-        self.next_id += 1;
+    /// Pins the type id used for structs named `name`, e.g. to match a `GobType::ID`
+    /// the peer expects. Automatically assigned ids skip reserved ones.
+    /// Fails if `id` is a predefined gob id or already belongs to another type.
+    pub fn reserve_id(&mut self, name: &str, id: i64) -> Result<()> {
+        if id < FIRST_USER_ID {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} is reserved for predefined gob types", id)));
+        }
+        if let Some(&existing) = self.reserved.get(name) {
+            if existing == id {
+                return Ok(());
+            }
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type {} already reserved id {}", name, existing)));
+        }
+        if self.id_in_use(id) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} is already in use", id)));
+        }
+        self.reserved.insert(name.to_string(), id);
+        Ok(())
+    }
+
+    /// Sends structs named `name` (the name in `Value::Struct`) as `wire_name`, both in
+    /// their type definition and as the concrete type name inside interfaces. Go looks up
+    /// interface values by their registered name, e.g. "main.User" for `gob.Register(User{})`;
+    /// the name in the definition is informational. Applies to structs defined after the call.
+    pub fn set_type_name(&mut self, name: &str, wire_name: &str) {
+        self.type_names.insert(name.to_string(), wire_name.to_string());
+    }
+
+    pub fn set_map_order(&mut self, order: MapOrder) {
+        self.map_order = order;
+    }
+
+    pub fn map_order(&self) -> MapOrder {
+        self.map_order
+    }
+
+    fn wire_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.type_names.get(name).map_or(name, String::as_str)
+    }
+
+    fn id_in_use(&self, id: i64) -> bool {
+        self.type_ids.values().any(|&v| v == id) || self.reserved.values().any(|&v| v == id)
+            || self.raw_types.contains_key(&id) || self.defined.contains_key(&id)
+    }
+
+    fn assign_type_id(&mut self, schema_key: String, name: Option<&str>) -> Result<i64> {
+        let id = match name.and_then(|n| self.reserved.get(n)).copied() {
+            Some(id) => {
+                // A reserved id names exactly one shape; a second shape under the same name would alias it.
+                if self.type_ids.values().any(|&v| v == id) {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!(
+                        "type id {} reserved for {} is already used by a different shape", id, name.unwrap_or_default())));
+                }
+                id
+            }
+            None => {
+                while self.id_in_use(self.next_id) {
+                    self.next_id += 1;
+                }
+                let id = self.next_id;
+                self.next_id += 1;
+                id
+            }
+        };
         self.type_ids.insert(schema_key, id);
-        id
+        Ok(id)
     }
 
     // High level encode
-    pub fn encode(&mut self, value: &Value) -> Result<()> {
+    /// Writes `value` as one message and returns the bytes this call wrote,
+    /// including any type definitions it had to send first.
+    pub fn encode(&mut self, value: &Value) -> Result<usize> {
         // We treat the top level value as the message.
         // We usually assume it's a Map or Struct.
         
+        // A nil interface only exists inside another value; there is no message for it.
+        if let Value::Nil = value {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "cannot encode Value::Nil as a top-level gob message"));
+        }
+
+        let start = self.bytes_written();
+
         // 1. Determine Type ID and ensure definition is sent.
         let type_id = self.ensure_type_defined(value)?;
 
         // 2. Encode Message: [Length] [TypeID] [Value]
+        self.write_value_message(value, type_id)?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Like `encode` for a `Value::Struct` or `Value::OrderedStruct`, but sends the struct under `wire_name` whatever
+    /// its `Value` name or `set_type_name` mapping. Nested structs are unaffected.
+    pub fn encode_with_name(&mut self, value: &Value, wire_name: &str) -> Result<usize> {
+        let start = self.bytes_written();
+        let type_id = match value {
+            Value::Struct(_, fields) => self.ensure_struct_type(wire_name, fields)?,
+            Value::OrderedStruct(_, fields) => self.ensure_struct_type(wire_name, fields.iter().map(|(k, v)| (k, v)))?,
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("encode_with_name expects a Struct, got {:?}", value))),
+        };
+        self.write_value_message(value, type_id)?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Writes one message per item and returns how many were written. Each type definition
+    /// is sent once, before the first message that uses it, and the writer is flushed
+    /// periodically and at the end.
+    ///
+    /// On failure the error carries the index of the failing item. Pass `iter.by_ref()`
+    /// to be able to skip that item and call `encode_stream` again with the rest.
+    pub fn encode_stream<I: IntoIterator<Item = Value>>(&mut self, iter: I) -> std::result::Result<usize, StreamError> {
+        self.write_stream(iter, |writer, value| writer.encode(&value))
+    }
+
+    /// Like `encode_stream`, for Rust values written with `encode_typed`.
+    pub fn encode_stream_typed<T, I>(&mut self, iter: I) -> std::result::Result<usize, StreamError>
+    where
+        T: GobEncodable,
+        I: IntoIterator<Item = T>,
+    {
+        self.write_stream(iter, |writer, value| writer.encode_typed(&value))
+    }
+
+    fn write_stream<T, I, F>(&mut self, iter: I, mut encode: F) -> std::result::Result<usize, StreamError>
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(&mut Self, T) -> Result<usize>,
+    {
+        let mut count = 0;
+        for item in iter {
+            encode(self, item).map_err(|source| StreamError { index: count, source })?;
+            count += 1;
+            if count % STREAM_FLUSH_EVERY == 0 {
+                self.flush().map_err(|source| StreamError { index: count - 1, source })?;
+            }
+        }
+        self.flush().map_err(|source| StreamError { index: count.saturating_sub(1), source })?;
+        Ok(count)
+    }
+
+    /// Writes `value` as one message of its own type, sending the definitions it needs
+    /// first (see [`GobEncodable::write_type_definition`]). A `#[Gob]` struct goes out
+    /// under its configured id and name; predefined types need no definition.
+    /// Returns the bytes written.
+    pub fn encode_typed<T: GobEncodable>(&mut self, value: &T) -> Result<usize> {
+        let start = self.bytes_written();
+        let type_id = T::write_type_definition(self)?;
+        if !self.is_known_type(type_id) || type_id == INTERFACE_ID {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                format!("no wire type definition is available for type id {}", type_id)));
+        }
+        self.write_body_message(type_id, !value.is_struct(), |enc| value.encode(enc))?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Writes a self-encoding value as one message, sending a gobEncoderType definition
+    /// for `T` first if this stream hasn't seen one. The definition uses `T::ID` when it
+    /// is a user id (64 or above), like `reserve_id`, and an assigned id otherwise.
+    pub fn encode_self_encoded<T: GobSelfEncoder + GobType>(&mut self, value: &T) -> Result<usize> {
+        let start = self.bytes_written();
+        let bytes = value.gob_encode()?;
+        let type_id = self.define_self_encoded::<T>()?;
+
+        self.write_body_message(type_id, true, |enc| enc.write_bytes(&bytes))?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Sends the gobEncoderType definition for `T`, as `encode_self_encoded` does, without
+    /// a value, and returns its id.
+    pub fn define_self_encoded<T: GobSelfEncoder + GobType>(&mut self) -> Result<i64> {
+        self.ensure_gob_encoder_type(T::gob_type_name(), T::ID)
+    }
+
+    fn ensure_gob_encoder_type(&mut self, name: &str, fixed_id: i64) -> Result<i64> {
+        let key = format!("GobEncoder {}", name);
+        if let Some(id) = self.get_type_id(&key) {
+            return Ok(id);
+        }
+        if fixed_id >= FIRST_USER_ID {
+            self.reserve_id(name, fixed_id)?;
+        }
+        let id = self.assign_type_id(key, Some(name))?;
+
+        // WireType { GobEncoderT (field 4): gobEncoderType { CommonType { Name, Id } } }
+        let mut content = Vec::new();
+        let mut enc = Encoder::new(&mut content);
+        enc.write_uint(5)?;
+        enc.write_uint(1)?;
+        enc.write_uint(1)?;
+        enc.write_string(name)?;
+        enc.write_uint(1)?;
+        enc.write_int(id)?;
+        enc.write_uint(0)?; // End CommonType
+        enc.write_uint(0)?; // End gobEncoderType
+        enc.write_uint(0)?; // End WireType
+        self.write_message(-id, &content)?;
+
+        self.defined.insert(id, TypeSchema::GobEncoder(name.to_string()));
+        Ok(id)
+    }
+
+    /// Writes `value` as one message holding an `interface{}`, as Go's `enc.Encode(&v)` does
+    /// for a `v interface{}`: the concrete type's definitions first, then its name (the
+    /// `type_name`, which for a `#[Gob]` struct is its `name`), the id this stream gave it,
+    /// and the value. A Go decoder gets it back with `dec.Decode(&v)` and can type-assert
+    /// `v` once the type is registered under that name. Returns the bytes written.
+    pub fn encode_interface_typed<T: GobEncodable>(&mut self, value: &T) -> Result<usize> {
+        let name = value.type_name();
+        if name.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                format!("{} has no type name to send in an interface", std::any::type_name::<T>())));
+        }
+        let start = self.bytes_written();
+        let type_id = T::write_type_definition(self)?;
+        if !self.is_known_type(type_id) || type_id == INTERFACE_ID {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                format!("no wire type definition is available for type id {}", type_id)));
+        }
+        self.write_body_message(INTERFACE_ID, true, |enc| enc.write_interface_wrapper(name, type_id, value))?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Encodes a `Value::Map` (or `Value::OrderedMap`) as a gob map with the given key and element type ids,
+    /// instead of the ones inferred from its contents. Use 8 (interface{}) for a slot
+    /// that holds mixed types, e.g. `encode_map_as(&v, 6, 8)` for `map[string]interface{}`.
+    /// Every key and element must match its concrete slot type; a `Nil` there is sent as
+    /// that type's zero value.
+    /// Returns the bytes written, like `encode`.
+    pub fn encode_map_as(&mut self, value: &Value, key_id: i64, elem_id: i64) -> Result<usize> {
+        let entries: Vec<(&Value, &Value)> = match value {
+            Value::Map(map) => map.iter().collect(),
+            Value::OrderedMap(entries) => entries.iter().map(|(k, v)| (k, v)).collect(),
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("encode_map_as expects a Map, got {:?}", value))),
+        };
+        for (k, v) in entries {
+            self.check_slot(key_id, k)?;
+            self.check_slot(elem_id, v)?;
+        }
+        let start = self.bytes_written();
+        let type_id = self.ensure_map_type(key_id, elem_id)?;
+        self.write_value_message(value, type_id)?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Writes a map message whose body comes from `body` (the entry count, then the
+    /// entries), typed as a map with the given key and element type ids. The map type
+    /// definition is sent first if needed, e.g. for a `#[Gob(interpret_as = "map[...]")]`
+    /// struct: `writer.encode_map_with(8, 8, |enc| user.encode(enc))`. Its generated
+    /// `encode_message` does the same with the key and element types it was declared with.
+    pub fn encode_map_with<F>(&mut self, key_id: i64, elem_id: i64, body: F) -> Result<usize>
+    where
+        F: FnOnce(&mut Encoder<&mut Vec<u8>>) -> Result<()>,
+    {
+        for id in [key_id, elem_id] {
+            if !self.is_known_type(id) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("map slot type id {} has not been defined on this stream", id)));
+            }
+        }
+        let start = self.bytes_written();
+        let type_id = self.ensure_map_type(key_id, elem_id)?;
+        self.write_body_message(type_id, true, body)?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Sends the definition of a struct type with the given Go name and fields (name and
+    /// type id, in wire order), unless this stream already has it, and returns its id.
+    /// Like `encode_self_encoded`, a `fixed_id` of 64 or above is used as the id (see
+    /// `reserve_id`); otherwise one is assigned. Field types must already be defined.
+    /// `#[Gob]` structs call this from their generated `write_type_definition`.
+    pub fn define_struct(&mut self, name: &str, fixed_id: i64, fields: &[(String, i64)]) -> Result<i64> {
+        for (fname, fid) in fields {
+            if !self.is_known_type(*fid) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("field {} of {} has type id {}, which has not been defined on this stream", fname, name, fid)));
+            }
+        }
+        let field_sig: Vec<String> = fields.iter().map(|(fname, fid)| format!("{}:{}", fname, fid)).collect();
+        let key = format!("Struct {}{{{}}}", name, field_sig.join(","));
+        if let Some(id) = self.get_type_id(&key) {
+            return Ok(id);
+        }
+        if let Some(id) = self.preloaded_struct(fields) {
+            self.type_ids.insert(key, id);
+            return Ok(id);
+        }
+        if fixed_id >= FIRST_USER_ID {
+            self.reserve_id(name, fixed_id)?;
+        }
+        let id = self.assign_type_id(key, Some(name))?;
+        self.send_struct_type_def(id, name, fields.to_vec())?;
+        Ok(id)
+    }
+
+    /// Sends a mapType definition with the given key and element type ids unless this
+    /// stream already has one, and returns its id.
+    pub fn define_map(&mut self, key_id: i64, elem_id: i64) -> Result<i64> {
+        for id in [key_id, elem_id] {
+            if !self.is_known_type(id) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("map key or element type id {} has not been defined on this stream", id)));
+            }
+        }
+        self.ensure_map_type(key_id, elem_id)
+    }
+
+    /// Sends a sliceType definition with elements of type `elem_id` unless this stream
+    /// already has one, and returns its id.
+    pub fn define_slice(&mut self, elem_id: i64) -> Result<i64> {
+        if !self.is_known_type(elem_id) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("slice element type id {} has not been defined on this stream", elem_id)));
+        }
+        let key = format!("Slice({})", elem_id);
+        if let Some(id) = self.get_type_id(&key) {
+            return Ok(id);
+        }
+        let id = self.assign_type_id(key, None)?;
+
+        // WireType { SliceT (field 1): sliceType { CommonType { Id }, Elem } }. Unnamed,
+        // like the map types.
+        let mut content = Vec::new();
+        let mut enc = Encoder::new(&mut content);
+        enc.write_uint(2)?;
+        enc.write_uint(1)?;
+        enc.write_uint(2)?;
+        enc.write_int(id)?;
+        enc.write_uint(0)?; // End CommonType
+        enc.write_uint(1)?;
+        enc.write_int(elem_id)?;
+        enc.write_uint(0)?; // End sliceType
+        enc.write_uint(0)?; // End WireType
+        self.write_message(-id, &content)?;
+
+        self.defined.insert(id, TypeSchema::Slice(elem_id));
+        Ok(id)
+    }
+
+    /// Sends an arrayType definition for `len` elements of type `elem_id` unless this
+    /// stream already has one, and returns its id.
+    pub fn define_array(&mut self, elem_id: i64, len: usize) -> Result<i64> {
+        if !self.is_known_type(elem_id) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("array element type id {} has not been defined on this stream", elem_id)));
+        }
+        let key = format!("Array({};{})", elem_id, len);
+        if let Some(id) = self.get_type_id(&key) {
+            return Ok(id);
+        }
+        let id = self.assign_type_id(key, None)?;
+
+        // WireType { ArrayT (field 0): arrayType { CommonType { Id }, Elem, Len } }
+        let mut content = Vec::new();
+        let mut enc = Encoder::new(&mut content);
+        enc.write_uint(1)?;
+        enc.write_uint(1)?;
+        enc.write_uint(2)?;
+        enc.write_int(id)?;
+        enc.write_uint(0)?; // End CommonType
+        enc.write_uint(1)?;
+        enc.write_int(elem_id)?;
+        enc.write_uint(1)?;
+        enc.write_int(len as i64)?;
+        enc.write_uint(0)?; // End arrayType
+        enc.write_uint(0)?; // End WireType
+        self.write_message(-id, &content)?;
+
+        self.defined.insert(id, TypeSchema::Array(elem_id, len));
+        Ok(id)
+    }
+
+    /// Writes a struct message whose body comes from `body` (field deltas, values and the
+    /// closing 0), for a struct type already sent with `define_struct`.
+    /// Returns the bytes written.
+    pub fn encode_struct_with<F>(&mut self, type_id: i64, body: F) -> Result<usize>
+    where
+        F: FnOnce(&mut Encoder<&mut Vec<u8>>) -> Result<()>,
+    {
+        if !matches!(self.defined.get(&type_id), Some(TypeSchema::Struct(_))) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} is not a struct defined on this stream", type_id)));
+        }
+        let start = self.bytes_written();
+        self.write_body_message(type_id, false, body)?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    // Frames the body written by `body` as a value message of `type_id`. Non-struct
+    // values are singletons and get the zero field delta first.
+    fn write_body_message<F>(&mut self, type_id: i64, singleton: bool, body: F) -> Result<()>
+    where
+        F: FnOnce(&mut Encoder<&mut Vec<u8>>) -> Result<()>,
+    {
+        let mut content_buf = take_buf(&mut self.scratch);
+        let result = (|| {
+            let mut enc = Encoder::new(&mut content_buf);
+            if singleton {
+                enc.write_uint(0)?;
+            }
+            body(&mut enc)?;
+            self.write_message(type_id, &content_buf)
+        })();
+        put_buf(&mut self.scratch, content_buf);
+        result
+    }
+
+    /// Writes a message read with `Decoder::read_raw_message`, first sending any of its
+    /// type definitions this stream hasn't seen. Ids are kept as numbered by the source
+    /// stream, since interface values inside the payload refer to them, so they must not
+    /// clash with types this writer numbered itself. Returns the bytes written, like `encode`.
+    pub fn write_raw_message(&mut self, message: &RawMessage) -> Result<usize> {
+        let start = self.bytes_written();
+        self.define_raw_types(&message.definitions)?;
+        if !self.is_known_type(message.type_id) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} has not been defined on this stream", message.type_id)));
+        }
+        self.write_message(message.type_id, &message.payload)?;
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Appends already-encoded gob messages, e.g. a slice of another stream being proxied:
+    /// each framed message in `bytes` in turn, which must end on a message boundary.
+    /// Value messages are written verbatim. Type definitions are taken as by
+    /// `define_raw_types`, so one this stream already has is dropped rather than sent twice,
+    /// and the same id rules apply. Returns the bytes written.
+    pub fn write_raw_bytes(&mut self, bytes: &[u8]) -> Result<usize> {
+        let start = self.bytes_written();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let (type_id, body, used) = codec::decode_message(rest)?;
+            if type_id < 0 {
+                self.define_raw_types(&[(-type_id, body.to_vec())])?;
+            } else if self.is_known_type(type_id) {
+                self.encoder.write_all(&rest[..used])?;
+            } else {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} has not been defined on this stream", type_id)));
+            }
+            rest = &rest[used..];
+        }
+        Ok((self.bytes_written() - start) as usize)
+    }
+
+    /// Sends type definitions taken from another stream, keeping their ids, unless this
+    /// stream already has them: the definitions of a `RawMessage`, or those
+    /// `Decoder::raw_definitions` gives for `Value::Raw` values about to be encoded. As
+    /// with `write_raw_message`, the ids must not clash with types this writer numbered.
+    pub fn define_raw_types(&mut self, definitions: &[(i64, Vec<u8>)]) -> Result<()> {
+        for (id, wire) in definitions {
+            match self.raw_types.get(id) {
+                Some(sent) if sent == wire => continue,
+                Some(_) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} was already sent with a different definition", id))),
+                None if self.id_in_use(*id) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("type id {} is already used by this writer", id))),
+                None => {}
+            }
+            let schema = Decoder::new_unframed(wire.as_slice()).decode_wire_type()?;
+            self.write_message(-id, wire)?;
+            self.raw_types.insert(*id, wire.clone());
+            self.adopt_definition(*id, schema);
+        }
+        Ok(())
+    }
+
+    // Whether `value` goes on the wire as a struct, with no singleton delta in front.
+    fn is_struct_value(&self, value: &Value) -> bool {
+        match value {
+            Value::Struct(..) | Value::OrderedStruct(..) => true,
+            Value::Raw(id, _) => matches!(self.defined.get(id), Some(TypeSchema::Struct(_))),
+            _ => false,
+        }
+    }
+
+    // Predefined ids, plus anything this writer has sent a definition for.
+    fn is_known_type(&self, id: i64) -> bool {
+        matches!(id, 1..=INTERFACE_ID) || self.defined.contains_key(&id)
+    }
+
+    fn check_slot(&mut self, slot_id: i64, value: &Value) -> Result<()> {
+        if slot_id == INTERFACE_ID {
+            return Ok(());
+        }
+        // Nil fits any concrete slot as that type's zero value.
+        if matches!(value, Value::Nil) {
+            if !self.is_known_type(slot_id) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("map slot type id {} has not been defined on this stream", slot_id)));
+            }
+            return Ok(());
+        }
+        let id = self.ensure_type_defined(value)?;
+        if id != slot_id {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{:?} does not fit a map slot of type id {}", value, slot_id)));
+        }
+        Ok(())
+    }
+
+    fn write_value_message(&mut self, value: &Value, type_id: i64) -> Result<()> {
         // We need to capture the value bytes to know length.
-        let mut value_buf = Vec::new();
-        let mut sub_writer = GobWriter::new(&mut value_buf);
-        // Share type registry? 
-        // Ideally yes, but for simplicity, let's assume we pass down context or re-use writer logic without creating new structs.
-        // Actually, we need to separate "Encode Definition" from "Encode Value".
-        
-        // Let's refactor: `encode_value_content` writes into a buffer.
-        let mut content_buf = Vec::new();
-        {
+        let mut content_buf = take_buf(&mut self.scratch);
+        let result = (|| {
              let mut sub_encoder = Encoder::new(&mut content_buf);
+             // Go sends a top-level non-struct value as a singleton: a zero field delta first.
+             if !self.is_struct_value(value) {
+                 sub_encoder.write_uint(0)?;
+             }
              self.encode_value_body(&mut sub_encoder, value, type_id)?;
-        }
+             self.write_message(type_id, &content_buf)
+        })();
+        put_buf(&mut self.scratch, content_buf);
+        result
+    }
 
-        // 3. Write Length
-        // Length covers TypeID + Content.
-        // We need to encode TypeID into bytes to measure it?
-        // Wait, TypeID is just an Int.
-        // [Length of (TypeID + Content)] [TypeID] [Content]
-        
-        let mut type_id_buf = Vec::new();
-        let mut type_id_enc = Encoder::new(&mut type_id_buf);
-        type_id_enc.write_int(type_id)?;
-        
-        let total_len = type_id_buf.len() + content_buf.len();
+    // Writes [Length of (TypeID + Content)] [TypeID] [Content].
+    // Definitions pass the negated id.
+    fn write_message(&mut self, type_id: i64, content: &[u8]) -> Result<()> {
+        let total_len = codec::measure_int(type_id) + content.len();
         self.encoder.write_uint(total_len as u64)?;
-        self.encoder.write_all(&type_id_buf)?;
-        self.encoder.write_all(&content_buf)?;
-        
-        Ok(())
+        self.encoder.write_int(type_id)?;
+        self.encoder.write_all(content)
     }
 
     fn ensure_type_defined(&mut self, value: &Value) -> Result<i64> {
@@ -80,46 +663,92 @@ impl<W: Write> GobWriter<W> {
             Value::Int(_) => Ok(2),
             Value::Uint(_) => Ok(3),
             Value::Float(_) => Ok(4),
+            Value::Complex(..) => Ok(7),
             Value::Bytes(_) => Ok(5),
             Value::String(_) => Ok(6),
-            Value::Map(_) => {
-                // Assume Map<interface{}, interface{}> for generic map
-                let key = "Map(8,8)".to_string();
-                if let Some(id) = self.get_type_id(&key) {
-                    return Ok(id);
-                }
-                
-                let id = self.assign_type_id(key);
-                self.send_map_type_def(id, 8, 8)?;
-                Ok(id)
+            Value::Map(map) => {
+                // A homogeneous map gets concrete slots (map[string]string rather than
+                // map[interface{}]interface{}); anything mixed, nil or empty stays interface{}.
+                let key_id = self.infer_slot_type(map.keys())?;
+                let elem_id = self.infer_slot_type(map.values())?;
+                self.ensure_map_type(key_id, elem_id)
+            }
+            Value::OrderedMap(entries) => {
+                let key_id = self.infer_slot_type(entries.iter().map(|(k, _)| k))?;
+                let elem_id = self.infer_slot_type(entries.iter().map(|(_, v)| v))?;
+                self.ensure_map_type(key_id, elem_id)
             }
             Value::Struct(name, fields) => {
-                // We need a signature for the struct logic.
-                // Using name is risky if different structs have same name.
-                // But gob assumes name uniqueness often or structure uniqueness.
-                // Let's use name for now.
-                // Note: Fields need to be sorted for deterministic signature?
-                // BTreeMap sorts by key.
-                
-                if let Some(id) = self.get_type_id(name) {
-                    return Ok(id);
-                }
+                let name = self.wire_name(name).to_string();
+                self.ensure_struct_type(&name, fields)
+            }
+            Value::OrderedStruct(name, fields) => {
+                let name = self.wire_name(name).to_string();
+                self.ensure_struct_type(&name, fields.iter().map(|(k, v)| (k, v)))
+            }
+            Value::GobEncoded(name, _) => {
+                let name = self.wire_name(name).to_string();
+                self.ensure_gob_encoder_type(&name, 0)
+            }
+            // Raw bytes are only meaningful under the type id they were read with, so
+            // the stream must already have it (see `define_raw_types`).
+            Value::Raw(id, _) if self.is_known_type(*id) => Ok(*id),
+            Value::Raw(id, _) => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("raw value of type id {} has no definition on this stream; pass its definitions to define_raw_types first", id))),
+            Value::Array(_) => Err(std::io::Error::other("Array encode not impl")),
+            // Nil has no concrete type. The only slot that can hold it is an interface
+            // (a map entry or a struct field declared as interface{}).
+            Value::Nil => Ok(8),
+        }
+    }
 
-                // We must define field types first.
-                // This might be recursive.
-                let mut field_defs = Vec::new();
-                for (fname, fval) in fields {
-                    let fid = self.ensure_type_defined(fval)?;
-                    field_defs.push((fname.clone(), fid));
-                }
+    fn ensure_struct_type<'a>(&mut self, name: &str, fields: impl IntoIterator<Item = (&'a String, &'a Value)>) -> Result<i64> {
+        // We must define field types first.
+        // This might be recursive.
+        let mut field_defs = Vec::new();
+        for (fname, fval) in fields {
+            let fid = self.ensure_type_defined(fval)?;
+            field_defs.push((fname.clone(), fid));
+        }
 
-                let id = self.assign_type_id(name.clone());
-                self.send_struct_type_def(id, name, field_defs)?;
-                Ok(id)
+        // The signature is the full shape (name plus field names and type ids, in order),
+        // so two different structs that share a name don't alias.
+        let field_sig: Vec<String> = field_defs.iter().map(|(fname, fid)| format!("{}:{}", fname, fid)).collect();
+        let key = format!("Struct {}{{{}}}", name, field_sig.join(","));
+        if let Some(id) = self.get_type_id(&key) {
+            return Ok(id);
+        }
+        if let Some(id) = self.preloaded_struct(&field_defs) {
+            self.type_ids.insert(key, id);
+            return Ok(id);
+        }
+
+        let id = self.assign_type_id(key, Some(name))?;
+        self.send_struct_type_def(id, name, field_defs)?;
+        Ok(id)
+    }
+
+    fn infer_slot_type<'a>(&mut self, values: impl Iterator<Item = &'a Value>) -> Result<i64> {
+        let mut slot = None;
+        for v in values {
+            let id = self.ensure_type_defined(v)?;
+            match slot {
+                None => slot = Some(id),
+                Some(s) if s != id => return Ok(INTERFACE_ID),
+                _ => {}
             }
-            Value::Array(_) => Err(std::io::Error::new(std::io::ErrorKind::Other, "Array encode not impl")),
-            Value::Nil => Ok(0), // ?
         }
+        Ok(slot.unwrap_or(INTERFACE_ID))
+    }
+
+    fn ensure_map_type(&mut self, key_id: i64, elem_id: i64) -> Result<i64> {
+        let key = format!("Map({},{})", key_id, elem_id);
+        if let Some(id) = self.get_type_id(&key) {
+            return Ok(id);
+        }
+
+        let id = self.assign_type_id(key, None)?;
+        self.send_map_type_def(id, key_id, elem_id)?;
+        Ok(id)
     }
 
     fn send_map_type_def(&mut self, id: i64, key_id: i64, elem_id: i64) -> Result<()> {
@@ -138,14 +767,20 @@ impl<W: Write> GobWriter<W> {
         enc.write_uint(4)?; 
         
         // MapType struct:
-        // Field 0: CommonType (name, id). We usually skip or write empty?
-        // Decoder: Field 0 (CommonType) -> ignored/read.
+        // Field 0: CommonType (name, id)
         // Field 1: KeyID
         // Field 2: ElemID
         
-        // We write KeyID (Field 1).
-        // Delta = 1 - (-1) = 2.
+        // CommonType (Field 0). Delta = 1.
+        // Map types are unnamed in Go, so like Go we send only the Id (Field 1, delta 2).
+        enc.write_uint(1)?;
         enc.write_uint(2)?;
+        enc.write_int(id)?;
+        enc.write_uint(0)?;
+
+        // We write KeyID (Field 1).
+        // Delta = 1 - 0 = 1.
+        enc.write_uint(1)?;
         enc.write_int(key_id)?;
         
         // ElemID (Field 2).
@@ -160,15 +795,9 @@ impl<W: Write> GobWriter<W> {
         enc.write_uint(0)?;
         
         // Write Message
-        let mut type_id_buf = Vec::new();
-        let mut t_enc = Encoder::new(&mut type_id_buf);
-        t_enc.write_int(-id)?; // Negative for definition
-        
-        let len = type_id_buf.len() + content.len();
-        self.encoder.write_uint(len as u64)?;
-        self.encoder.write_all(&type_id_buf)?;
-        self.encoder.write_all(&content)?;
+        self.write_message(-id, &content)?; // Negative for definition
         
+        self.defined.insert(id, TypeSchema::Map(key_id, elem_id));
         Ok(())
     }
 
@@ -214,18 +843,18 @@ impl<W: Write> GobWriter<W> {
         // Slice length
         enc.write_uint(fields.len() as u64)?;
         
-        for (fname, fid) in fields {
+        for (fname, fid) in &fields {
             // FieldType struct:
             // Field 0: Name
             // Field 1: Id
             
             // Name (Field 0)
             enc.write_uint(1)?;
-            enc.write_string(&fname)?;
+            enc.write_string(fname)?;
             
             // Id (Field 1)
             enc.write_uint(1)?;
-            enc.write_int(fid)?;
+            enc.write_int(*fid)?;
             
             // End FieldType
             enc.write_uint(0)?;
@@ -238,15 +867,10 @@ impl<W: Write> GobWriter<W> {
         enc.write_uint(0)?;
         
         // Send Message
-        let mut type_id_buf = Vec::new();
-        let mut t_enc = Encoder::new(&mut type_id_buf);
-        t_enc.write_int(-id)?;
-        
-        let len = type_id_buf.len() + content.len();
-        self.encoder.write_uint(len as u64)?;
-        self.encoder.write_all(&type_id_buf)?;
-        self.encoder.write_all(&content)?;
+        self.write_message(-id, &content)?;
         
+        let schema_fields = fields.into_iter().map(|(fname, fid)| (0, fid, fname)).collect();
+        self.defined.insert(id, TypeSchema::Struct(schema_fields));
         Ok(())
     }
 
@@ -259,83 +883,145 @@ impl<W: Write> GobWriter<W> {
             Value::Int(v) => enc.write_int(*v)?,
             Value::Uint(v) => enc.write_uint(*v)?,
             Value::Float(v) => enc.write_float(*v)?,
+            Value::Complex(re, im) => {
+                enc.write_float(*re)?;
+                enc.write_float(*im)?;
+            }
             Value::String(v) => enc.write_string(v)?,
-            Value::Bytes(v) => enc.write_bytes(v)?,
-            Value::Map(m) => {
-                // Map encoding: Count, then (Key, Val) pairs.
-                enc.write_uint(m.len() as u64)?;
-                for (k, v) in m {
-                    // For Map<interface, interface>, we need to encode values AS interfaces.
-                    // This means wrapping them.
-                    self.encode_interface_value(enc, k)?;
-                    self.encode_interface_value(enc, v)?;
-                }
-            },
-            Value::Struct(_, fields) => {
-                // Struct encoding: Field deltas.
-                // We assume `fields` contains all fields defined in the type, in order?
-                // Or we need to map names to indices.
-                // But `Value::Struct` is BTreeMap (sorted by name).
-                // Our `send_struct_type_def` used iteration order of BTreeMap (sorted).
-                // So field indices are 0, 1, 2... in name-sorted order.
-                
-                let mut current_idx = -1;
-                let mut idx = 0;
-                for (name, val) in fields {
-                     // Check if not nil/empty/zero? Gob omits zero values.
-                     // For now, send everything.
-                     
-                     let delta = (idx as i64) - current_idx;
-                     enc.write_uint(delta as u64)?;
-                     current_idx = idx as i64;
-                     
-                     // Encode field value
-                     // If field is interface? We need schema to know.
-                     // But we are constructing schema on fly.
-                     // If `val` matches the `fid` we used in definition.
-                     // `fid` came from `ensure_type_defined`.
-                     // If `val` is struct/map, `fid` is concrete type ID.
-                     // If the FIELD TYPE was defined as interface, we wrap.
-                     // BUT here we defined the field type AS the concrete type ID!
-                     // So we don't wrap?
-                     
-                     // Wait. In `ensure_type_defined` for Struct:
-                     // `let fid = self.ensure_type_defined(fval)?;`
-                     // This returns the CONCRETE type ID of the value.
-                     // So we defined the struct as having fields of these specific concrete types.
-                     // So we do NOT wrap in interface.
-                     // We just encode the body recursively.
-                     let fid = self.ensure_type_defined(val)?;
-                     self.encode_value_body(enc, val, fid)?;
-                     
-                     idx += 1;
-                }
-                enc.write_uint(0)?; // End of struct
-            },
+            Value::Bytes(v) | Value::GobEncoded(_, v) => enc.write_bytes(v)?,
+            Value::Raw(_, bytes) => enc.write_all(bytes)?,
+            Value::Map(m) => self.encode_map_entries(enc, m.iter(), m.len(), type_id)?,
+            Value::OrderedMap(entries) if self.map_order == MapOrder::Insertion => {
+                self.encode_map_entries(enc, entries.iter().map(|(k, v)| (k, v)), entries.len(), type_id)?
+            }
+            Value::OrderedMap(entries) => {
+                let mut sorted: Vec<_> = entries.iter().map(|(k, v)| (k, v)).collect();
+                sorted.sort_by(|a, b| a.0.cmp(b.0));
+                self.encode_map_entries(enc, sorted.into_iter(), entries.len(), type_id)?
+            }
+            Value::Struct(_, fields) => self.encode_struct_fields(enc, fields.values())?,
+            Value::OrderedStruct(_, fields) => self.encode_struct_fields(enc, fields.iter().map(|(_, v)| v))?,
              _ => {}
         }
         Ok(())
     }
 
+    // Struct encoding: field deltas, in the order `ensure_struct_type` defined the fields.
+    fn encode_struct_fields<'a, E: Write>(&mut self, enc: &mut Encoder<E>, fields: impl Iterator<Item = &'a Value>) -> Result<()> {
+        // We assume `fields` contains all fields defined in the type, in order?
+        // Or we need to map names to indices.
+        // But `Value::Struct` is BTreeMap (sorted by name), and `OrderedStruct` keeps its order.
+        // Our `send_struct_type_def` used the same iteration order.
+        // So field indices are 0, 1, 2... in that order.
+        
+        let mut current_idx = -1;
+        for (idx, val) in fields.enumerate() {
+             // Check if not nil/empty/zero? Gob omits zero values.
+             // For now, send everything except Nil: the field is declared as
+             // interface{} and a nil interface is its zero value, so it is omitted.
+             if let Value::Nil = val {
+                 continue;
+             }
+             
+             let delta = (idx as i64) - current_idx;
+             enc.write_uint(delta as u64)?;
+             current_idx = idx as i64;
+             
+             // Encode field value
+             // If field is interface? We need schema to know.
+             // But we are constructing schema on fly.
+             // If `val` matches the `fid` we used in definition.
+             // `fid` came from `ensure_type_defined`.
+             // If `val` is struct/map, `fid` is concrete type ID.
+             // If the FIELD TYPE was defined as interface, we wrap.
+             // BUT here we defined the field type AS the concrete type ID!
+             // So we don't wrap?
+             
+             // Wait. In `ensure_type_defined` for Struct:
+             // `let fid = self.ensure_type_defined(fval)?;`
+             // This returns the CONCRETE type ID of the value.
+             // So we defined the struct as having fields of these specific concrete types.
+             // So we do NOT wrap in interface.
+             // We just encode the body recursively.
+             let fid = self.ensure_type_defined(val)?;
+             self.encode_value_body(enc, val, fid)?;
+        }
+        enc.write_uint(0)?; // End of struct
+        Ok(())
+    }
+
+    // Map encoding: Count, then (Key, Val) pairs.
+    fn encode_map_entries<'a, E: Write>(&mut self, enc: &mut Encoder<E>, entries: impl Iterator<Item = (&'a Value, &'a Value)>, len: usize, type_id: i64) -> Result<()> {
+        let (key_id, elem_id) = match self.defined.get(&type_id) {
+            Some(TypeSchema::Map(k, e)) => (*k, *e),
+            _ => (INTERFACE_ID, INTERFACE_ID),
+        };
+        enc.write_uint(len as u64)?;
+        for (k, v) in entries {
+            // Interface slots wrap each value; concrete slots hold the bare body.
+            self.encode_slot_value(enc, k, key_id)?;
+            self.encode_slot_value(enc, v, elem_id)?;
+        }
+        Ok(())
+    }
+
+    fn encode_slot_value<E: Write>(&mut self, enc: &mut Encoder<E>, value: &Value, slot_id: i64) -> Result<()> {
+        if slot_id == INTERFACE_ID {
+            return self.encode_interface_value(enc, value);
+        }
+        if matches!(value, Value::Nil) {
+            return self.encode_zero_body(enc, slot_id);
+        }
+        let type_id = self.ensure_type_defined(value)?;
+        self.encode_value_body(enc, value, type_id)
+    }
+
+    // The body of type `type_id`'s zero value. Nearly every type's is a single 0: false, 0,
+    // an empty string, slice or map, or a struct with no fields sent.
+    fn encode_zero_body<E: Write>(&mut self, enc: &mut Encoder<E>, type_id: i64) -> Result<()> {
+        match self.defined.get(&type_id) {
+            None if type_id == 7 => {
+                enc.write_float(0.0)?;
+                enc.write_float(0.0)
+            }
+            Some(&TypeSchema::Array(elem_id, len)) => {
+                enc.write_uint(len as u64)?;
+                for _ in 0..len {
+                    self.encode_zero_body(enc, elem_id)?;
+                }
+                Ok(())
+            }
+            _ => enc.write_uint(0),
+        }
+    }
+
     fn encode_interface_value<E: Write>(&mut self, enc: &mut Encoder<E>, value: &Value) -> Result<()> {
         // Interface encoding: Name, TypeID, Length, Value.
         
         // 1. Concrete Name
+        // Owned because the rest of this function borrows `self` mutably.
+        let struct_name;
         let name = match value {
+            Value::Struct(n, _) | Value::OrderedStruct(n, _) | Value::GobEncoded(n, _) => {
+                struct_name = self.wire_name(n).to_string();
+                struct_name.as_str()
+            }
             Value::Bool(_) => "bool",
             Value::Int(_) => "int64", // Standard for gob numbers is often int64? Go decoder saw "int64" for 1, and "int" for -1?
-            Value::Uint(_) => "uint",
+            Value::Uint(_) => "uint64", // "uint" would overflow a 32-bit Go peer
             Value::Float(_) => "float64",
+            Value::Complex(..) => "complex128",
             Value::String(_) => "string",
             Value::Bytes(_) => "[]byte",
-            Value::Struct(n, _) => n,
-            Value::Map(_) => "map[interface{}]interface{}", // Approximate
+            Value::Map(_) | Value::OrderedMap(_) => "map[interface{}]interface{}", // Approximate
             Value::Nil => "",
+            // The name Go registered for the type isn't part of the raw bytes.
+            Value::Raw(id, _) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("raw value of type id {} cannot be sent in an interface", id))),
             _ => "unknown",
         };
         
         enc.write_string(name)?;
-        if name == "" { return Ok(()); }
+        if name.is_empty() { return Ok(()); }
         
         // 2. Concrete Type ID.
         // We might need to send definition if not sent.
@@ -358,27 +1044,561 @@ impl<W: Write> GobWriter<W> {
         enc.write_int(type_id)?;
         
         // 3. Length of value
-        let mut val_buf = Vec::new();
-        let mut val_enc = Encoder::new(&mut val_buf);
-        
-        // 00 byte skip rule for interfaces?
-        // My decoder checks for 0 byte.
-        // Go gob decoder expects 0 byte if the value is NOT empty?
-        // Actually, gob spec: "Interface values are encoded as... Length... Value".
-        // The value itself might start with 0?
-        // But my decoder logic: `let b = self.read_u8()?; if b != 0 { stash }`.
-        // This implies sometimes there IS a 0 byte that is NOT part of the value?
-        // No, it implies that the first byte MIGHT be 0, and if so we assume it's part of the stream (or skip?).
-        // Actually, the `read_u8` then `stash` implies we just peeked.
-        // It does NOT imply we skipped.
-        // So we write standard value.
-        
-        self.encode_value_body(&mut val_enc, value, type_id)?;
-        
-        enc.write_uint(val_buf.len() as u64)?;
-        enc.write_all(&val_buf)?;
+        // The value buffer is pooled; a big interface map would otherwise allocate once per entry.
+        let mut val_buf = take_buf(&mut self.scratch);
+        let result = (|| {
+            let mut val_enc = Encoder::new(&mut val_buf);
         
-        Ok(())
+            // Non-struct concrete values are singletons: a zero field delta precedes the value.
+            if !matches!(value, Value::Struct(..) | Value::OrderedStruct(..)) {
+                val_enc.write_uint(0)?;
+            }
+        
+            self.encode_value_body(&mut val_enc, value, type_id)?;
+        
+            enc.write_uint(val_buf.len() as u64)?;
+            enc.write_all(&val_buf)
+        })();
+        put_buf(&mut self.scratch, val_buf);
+        result
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decoder;
+    use std::collections::BTreeMap;
+
+    fn decode_one(buf: &[u8]) -> Value {
+        let mut dec = Decoder::new(buf);
+        dec.read_next().unwrap().expect("expected a value message")
+    }
+
+    #[test]
+    fn test_nil_in_interface_map() {
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("a"), Value::Nil);
+        map.insert(Value::from("b"), Value::Int(1));
+        let value = Value::Map(map);
+
+        let mut buf = Vec::new();
+        GobWriter::new(&mut buf).encode(&value).unwrap();
+        assert_eq!(decode_one(&buf), value);
+    }
+
+    #[test]
+    fn test_nil_struct_field_is_omitted() {
+        let mut fields = BTreeMap::new();
+        fields.insert("A".to_string(), Value::Nil);
+        fields.insert("B".to_string(), Value::Int(7));
+
+        let mut buf = Vec::new();
+        GobWriter::new(&mut buf).encode(&Value::Struct("S".to_string(), fields)).unwrap();
+
+        let Value::Struct(_, decoded) = decode_one(&buf) else { panic!("expected a struct") };
+        assert_eq!(decoded.get("A"), None);
+        assert_eq!(decoded.get("B"), Some(&Value::Int(7)));
+    }
+
+    #[test]
+    fn test_top_level_nil_is_rejected() {
+        let mut buf = Vec::new();
+        let err = GobWriter::new(&mut buf).encode(&Value::Nil).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(buf.is_empty());
+    }
+
+    // Splits a stream into (type id, message length) pairs.
+    fn message_headers(buf: &[u8]) -> Vec<(i64, usize)> {
+        let mut dec = Decoder::new_unframed(buf);
+        let mut headers = Vec::new();
+        while let Ok(len) = dec.read_uint() {
+            let body = dec.read_exact_bytes(len as usize).unwrap();
+            let type_id = Decoder::new_unframed(body.as_slice()).read_int().unwrap();
+            headers.push((type_id, len as usize));
+        }
+        headers
+    }
+
+    fn user(name: &str, age: i64) -> Value {
+        let mut fields = BTreeMap::new();
+        fields.insert("Name".to_string(), Value::from(name));
+        fields.insert("Age".to_string(), Value::Int(age));
+        Value::Struct("User".to_string(), fields)
+    }
+
+    #[test]
+    fn test_type_defined_once_across_encodes() {
+        let mut buf = Vec::new();
+        let mut writer = GobWriter::new(&mut buf);
+        writer.encode(&user("ann", 30)).unwrap();
+        writer.encode(&user("bob", 41)).unwrap();
+
+        let defined = writer.defined_types().clone();
+        assert_eq!(defined.len(), 1);
+        let (&id, schema) = defined.iter().next().unwrap();
+        assert!(matches!(schema, TypeSchema::Struct(fields) if fields.len() == 2));
+
+        let ids: Vec<i64> = message_headers(&buf).into_iter().map(|(type_id, _)| type_id).collect();
+        assert_eq!(ids, vec![-id, id, id]);
+
+        let mut dec = Decoder::new(buf.as_slice());
+        let Some(Value::Struct(_, first)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+        let Some(Value::Struct(_, second)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+        assert_eq!(first.get("Name"), Some(&Value::from("ann")));
+        assert_eq!(second.get("Age"), Some(&Value::Int(41)));
+        assert!(dec.read_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_same_name_different_shape_gets_new_id() {
+        let mut other = BTreeMap::new();
+        other.insert("Email".to_string(), Value::from("a@b.c"));
+
+        let mut buf = Vec::new();
+        let mut writer = GobWriter::new(&mut buf);
+        writer.encode(&user("ann", 30)).unwrap();
+        writer.encode(&Value::Struct("User".to_string(), other)).unwrap();
+        writer.encode(&user("bob", 41)).unwrap();
+
+        let ids: Vec<i64> = writer.defined_types().keys().copied().collect();
+        assert_eq!(ids, vec![64, 65]);
+    }
+
+    #[test]
+    fn test_reserved_ids() {
+        let mut buf = Vec::new();
+        let mut writer = GobWriter::new(&mut buf);
+        writer.reserve_id("User", 65).unwrap();
+        assert!(writer.reserve_id("Other", 65).is_err());
+        assert!(writer.reserve_id("Other", 8).is_err());
+
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("k"), Value::Int(1));
+        writer.encode(&Value::Map(map)).unwrap();
+        writer.encode(&user("ann", 30)).unwrap();
+        let ids: Vec<i64> = writer.defined_types().keys().copied().collect();
+        assert_eq!(ids, vec![64, 65]);
+        assert!(matches!(writer.defined_types()[&64], TypeSchema::Map(6, 2)));
+        assert!(matches!(writer.defined_types()[&65], TypeSchema::Struct(_)));
+
+        // The reserved id can't be reused by a differently shaped "User".
+        let mut other = BTreeMap::new();
+        other.insert("Email".to_string(), Value::from("a@b.c"));
+        assert!(writer.encode(&Value::Struct("User".to_string(), other)).is_err());
+
+        // Nor can an auto-assigned id be reserved afterwards.
+        assert!(writer.reserve_id("Other", 64).is_err());
+    }
+
+    #[test]
+    fn test_top_level_scalar_singleton_framing() {
+        // [length] [type id] [zero delta] [value], as Go's Encoder writes bare values.
+        let cases: Vec<(Value, &[u8])> = vec![
+            (Value::Int(5), b"\x03\x04\x00\x0a"),
+            (Value::Uint(300), b"\x05\x06\x00\xfe\x01\x2c"),
+            (Value::from("hi"), b"\x05\x0c\x00\x02hi"),
+            (Value::Bool(true), b"\x03\x02\x00\x01"),
+            (Value::Float(2.5), b"\x05\x08\x00\xfe\x04\x40"),
+            (Value::Bytes(vec![1, 2]), b"\x05\x0a\x00\x02\x01\x02"),
+        ];
+        for (value, expected) in cases {
+            let mut buf = Vec::new();
+            GobWriter::new(&mut buf).encode(&value).unwrap();
+            assert_eq!(buf, expected, "encoding {:?}", value);
+            assert_eq!(decode_one(&buf), value);
+        }
+    }
+
+    #[test]
+    fn test_typed_map_matches_go() {
+        // Go: enc.Encode(map[string]string{"name": "gob"})
+        let go: &[u8] = b"\x0d\x7f\x04\x01\x02\xff\x80\x00\x01\x0c\x01\x0c\x00\x00\
+                          \x0d\xff\x80\x00\x01\x04name\x03gob";
+        let value = decode_one(go);
+
+        let mut buf = Vec::new();
+        GobWriter::new(&mut buf).encode(&value).unwrap();
+        assert_eq!(buf, go);
+    }
+
+    #[test]
+    fn test_map_slot_inference() {
+        let mut mixed = BTreeMap::new();
+        mixed.insert(Value::from("a"), Value::Int(1));
+        mixed.insert(Value::from("b"), Value::from("x"));
+        let mut ints = BTreeMap::new();
+        ints.insert(Value::from("a"), Value::Int(1));
+
+        let mut buf = Vec::new();
+        let mut writer = GobWriter::new(&mut buf);
+        writer.encode(&Value::Map(mixed.clone())).unwrap();
+        writer.encode(&Value::Map(ints.clone())).unwrap();
+        writer.encode(&Value::Map(BTreeMap::new())).unwrap();
+        let schemas: Vec<TypeSchema> = writer.defined_types().values().cloned().collect();
+        assert!(matches!(schemas[..], [TypeSchema::Map(6, 8), TypeSchema::Map(6, 2), TypeSchema::Map(8, 8)]));
+
+        let mut dec = Decoder::new(buf.as_slice());
+        assert_eq!(dec.read_next().unwrap(), Some(Value::Map(mixed)));
+        assert_eq!(dec.read_next().unwrap(), Some(Value::Map(ints)));
+        assert_eq!(dec.read_next().unwrap(), Some(Value::Map(BTreeMap::new())));
+    }
+
+    #[test]
+    fn test_homogeneous_map_is_compact() {
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("a"), Value::from("x"));
+
+        let mut buf = Vec::new();
+        GobWriter::new(&mut buf).encode(&Value::Map(map)).unwrap();
+        // Go's bytes for map[string]string{"a": "x"}: a map type with string key and elem,
+        // then the entries with no interface wrapping.
+        let mut go = b"\x0d\x7f\x04\x01\x02\xff\x80\x00\x01\x0c\x01\x0c\x00\x00".to_vec();
+        go.extend_from_slice(b"\x08\xff\x80\x00\x01\x01a\x01x");
+        assert_eq!(buf, go);
+    }
+
+    #[test]
+    fn test_encode_map_as() {
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("a"), Value::from("x"));
+        let value = Value::Map(map);
+
+        let mut buf = Vec::new();
+        let mut writer = GobWriter::new(&mut buf);
+        writer.encode_map_as(&value, 8, 8).unwrap();
+        assert!(matches!(writer.defined_types()[&64], TypeSchema::Map(8, 8)));
+        assert_eq!(writer.encode_map_as(&value, 6, 2).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert!(writer.encode_map_as(&Value::Int(1), 6, 6).is_err());
+        assert_eq!(decode_one(&buf), value);
+    }
+
+    #[test]
+    fn test_encode_map_as_nil_elements() {
+        // A nil element goes out as the zero value of the slot's type, as Go would have it.
+        let value = Value::from([("a", Value::Nil), ("b", Value::from("x"))]);
+        let mut buf = Vec::new();
+        GobWriter::new(&mut buf).encode_map_as(&value, 6, 6).unwrap();
+        assert_eq!(decode_one(&buf), Value::from([("a", ""), ("b", "x")]));
+
+        let value = Value::from([("n", Value::Nil)]);
+        for (elem_id, zero) in [(1, Value::Bool(false)), (2, Value::Int(0)), (4, Value::Float(0.0)), (7, Value::Complex(0.0, 0.0))] {
+            let mut buf = Vec::new();
+            GobWriter::new(&mut buf).encode_map_as(&value, 6, elem_id).unwrap();
+            assert_eq!(decode_one(&buf), Value::from([("n", zero)]));
+        }
+
+        let mut buf = Vec::new();
+        let mut writer = GobWriter::new(&mut buf);
+        let array_id = writer.define_array(2, 3).unwrap();
+        writer.encode_map_as(&value, 6, array_id).unwrap();
+        assert_eq!(writer.encode_map_as(&value, 6, 99).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(decode_one(&buf), Value::from([("n", Value::Array(vec![Value::Int(0); 3]))]));
+    }
+
+    #[test]
+    fn test_encode_reports_bytes_written() {
+        let mut writer = GobWriter::new(Vec::new());
+        let first = writer.encode(&user("ann", 30)).unwrap();
+        let second = writer.encode(&user("bob", 41)).unwrap();
+        assert_eq!(writer.bytes_written(), (first + second) as u64);
+
+        let buf = writer.into_inner().unwrap();
+        let headers = message_headers(&buf);
+        // The first call also carried the definition; the second is a bare value message.
+        let (_, value_len) = headers[2];
+        assert_eq!(second, 1 + value_len);
+        assert_eq!(first + second, buf.len());
+    }
+
+    #[test]
+    fn test_encode_stream_round_trip() {
+        let mut writer = GobWriter::new(Vec::new());
+        let count = writer.encode_stream((0..1000).map(|i| user("u", i))).unwrap();
+        assert_eq!(count, 1000);
+        assert_eq!(writer.defined_types().len(), 1);
+
+        let buf = writer.into_inner().unwrap();
+        let mut dec = Decoder::new(buf.as_slice());
+        let values: Vec<Value> = dec.values().collect::<Result<_>>().unwrap();
+        assert_eq!(values.len(), 1000);
+        assert!(matches!(&values[999], Value::Struct(_, f) if f.get("Age") == Some(&Value::Int(999))));
+    }
+
+    #[test]
+    fn test_encode_stream_reports_failing_index() {
+        let mut items = vec![Value::Int(1), Value::Int(2), Value::Nil, Value::Int(4)].into_iter();
+        let mut writer = GobWriter::new(Vec::new());
+        let err = writer.encode_stream(items.by_ref()).unwrap_err();
+        assert_eq!(err.index, 2);
+        assert_eq!(err.source.kind(), std::io::ErrorKind::InvalidInput);
+
+        // Skip the bad item and carry on with the rest.
+        assert_eq!(writer.encode_stream(items).unwrap(), 1);
+        let buf = writer.into_inner().unwrap();
+        let values: Vec<Value> = Decoder::new(buf.as_slice()).values().collect::<Result<_>>().unwrap();
+        assert_eq!(values, vec![Value::Int(1), Value::Int(2), Value::Int(4)]);
+    }
+
+    #[test]
+    fn test_max_uint_round_trip() {
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("id"), Value::Uint(u64::MAX));
+        map.insert(Value::from("name"), Value::from("ann"));
+        let mut fields = BTreeMap::new();
+        fields.insert("Id".to_string(), Value::Uint(u64::MAX));
+        // Inside an interface, so the decoded struct keeps its name.
+        map.insert(Value::from("record"), Value::Struct("Record".to_string(), fields));
+        for value in [Value::Uint(u64::MAX), Value::Map(map)] {
+            let mut buf = Vec::new();
+            GobWriter::new(&mut buf).encode(&value).unwrap();
+            assert_eq!(decode_one(&buf), value);
+        }
+        assert!(i64::try_from(Value::Uint(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_wire_type_names() {
+        // Go: gob.Register(Point{}) in package main, then
+        //     var v map[string]interface{}; dec.Decode(&v); p := v["p"].(Point)
+        // The assertion succeeds when the concrete name is "main.Point".
+        let mut fields = BTreeMap::new();
+        fields.insert("X".to_string(), Value::Int(1));
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("p"), Value::Struct("Point".to_string(), fields.clone()));
+        map.insert(Value::from("n"), Value::Int(2)); // mixed elements, so interface{} slots
+
+        let mut buf = Vec::new();
+        let mut writer = GobWriter::new(&mut buf);
+        writer.set_type_name("Point", "main.Point");
+        writer.encode(&Value::Map(map)).unwrap();
+        let Value::Map(decoded) = decode_one(&buf) else { panic!("expected a map") };
+        assert!(matches!(&decoded[&Value::from("p")], Value::Struct(name, _) if name == "main.Point"));
+        assert!(buf.windows(10).any(|w| w == b"main.Point"));
+
+        let mut writer = GobWriter::new(Vec::new());
+        writer.encode_with_name(&Value::Struct("Struct".to_string(), fields), "Point").unwrap();
+        assert!(writer.encode_with_name(&Value::Int(1), "Point").is_err());
+        let buf = writer.into_inner().unwrap();
+        assert!(buf.windows(6).any(|w| w == b"\x05Point"));
+        assert!(!buf.windows(6).any(|w| w == b"Struct"));
+    }
+
+    #[test]
+    fn test_insertion_order_keeps_go_bytes() {
+        // Go wrote this map in its own (random) iteration order.
+        let input = include_bytes!("../normal-session.bin");
+        let mut dec = Decoder::new(&input[..]);
+        dec.set_preserve_map_order(true);
+        let value = dec.read_next().unwrap().unwrap();
+        assert!(matches!(value, Value::OrderedMap(_)));
+
+        let mut writer = GobWriter::new(Vec::new());
+        writer.set_map_order(MapOrder::Insertion);
+        writer.encode_map_as(&value, 8, 8).unwrap();
+        assert_eq!(writer.into_inner().unwrap(), input);
+
+        // Sorted order writes the same bytes as the equivalent Value::Map.
+        let Value::OrderedMap(entries) = value.clone() else { unreachable!() };
+        let mut writer = GobWriter::new(Vec::new());
+        writer.encode(&value).unwrap();
+        assert_eq!(writer.into_inner().unwrap(), crate::to_vec(&Value::Map(entries.into_iter().collect())).unwrap());
+    }
+
+    #[test]
+    fn test_ordered_struct_keeps_field_order() {
+        // Go declares sessions.Session's fields as ID, Values, Options, IsNew.
+        let input = include_bytes!("../goth-session.bin");
+        let mut dec = Decoder::new(&input[..]);
+        dec.set_preserve_field_order(true);
+        let Some(Value::Map(map)) = dec.read_next().unwrap() else { panic!("expected a map") };
+        let session = &map[&Value::from("_gothic_session")];
+        let Value::OrderedStruct(name, fields) = session else { panic!("expected an ordered struct, got {:?}", session) };
+        assert_eq!(name, "*sessions.Session");
+        let names: Vec<_> = fields.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["ID", "Values", "Options"]);
+        assert!(matches!(&fields[2].1, Value::OrderedStruct(_, options) if options[0] == ("MaxAge".to_string(), Value::Int(-1))));
+        assert_eq!(session.to_string(), "{ID:17634d7885249bfc Values:map[] Options:{MaxAge:-1}}");
+
+        // The writer defines the fields in that order too, so decoding and re-encoding
+        // reproduces its own output byte for byte.
+        let value = Value::OrderedStruct("Point".to_string(), vec![("Y".to_string(), Value::Int(2)), ("X".to_string(), Value::Int(1))]);
+        let mut writer = GobWriter::new(Vec::new());
+        writer.encode(&value).unwrap();
+        assert_eq!(writer.defined_types()[&64], TypeSchema::Struct(vec![(0, 2, "Y".to_string()), (0, 2, "X".to_string())]));
+        let bytes = writer.into_inner().unwrap();
+        let mut dec = Decoder::new(bytes.as_slice());
+        dec.set_preserve_field_order(true);
+        let decoded = dec.read_next().unwrap().unwrap();
+        assert_eq!(decoded, Value::OrderedStruct("Struct".to_string(), vec![("Y".to_string(), Value::Int(2)), ("X".to_string(), Value::Int(1))]));
+        let mut writer = GobWriter::new(Vec::new());
+        writer.encode_with_name(&decoded, "Point").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), bytes);
+
+        // Without the option the fields come back sorted by name.
+        let Value::Struct(_, sorted) = decode_one(&bytes) else { panic!("expected a struct") };
+        assert_eq!(sorted.keys().collect::<Vec<_>>(), ["X", "Y"]);
+    }
+
+    fn pass_through(input: &[u8]) -> Vec<u8> {
+        let mut dec = Decoder::new(input);
+        let mut out = Vec::new();
+        let mut writer = GobWriter::new(&mut out);
+        while let Some(message) = dec.read_raw_message().unwrap() {
+            writer.write_raw_message(&message).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn test_raw_message_pass_through() {
+        // The goth session's interface value has its definitions sent mid-value.
+        for input in [&include_bytes!("../goth-session.bin")[..], &include_bytes!("../normal-session.bin")[..]] {
+            let out = pass_through(input);
+            assert_eq!(decode_one(&out), decode_one(input));
+        }
+
+        // Two messages of the same type: the definition is forwarded once.
+        let mut input = Vec::new();
+        let mut writer = GobWriter::new(&mut input);
+        writer.encode(&user("ann", 30)).unwrap();
+        writer.encode(&user("bob", 40)).unwrap();
+        let out = pass_through(&input);
+        assert_eq!(out, input);
+        assert_eq!(message_headers(&out).iter().filter(|(id, _)| *id < 0).count(), 1);
+    }
+
+    #[test]
+    fn test_raw_values_pass_through() {
+        // A proxy that rewrites an envelope without modelling its payload.
+        let extra = Value::from([("retries", Value::Int(2)), ("queue", Value::from("bulk"))]);
+        let payload = Value::struct_("Payload").field("Id", 7).field("Extra", extra).build();
+        let envelope = Value::struct_("Envelope").field("Kind", "job").field("Payload", payload.clone()).build();
+        let mut input = Vec::new();
+        let mut writer = GobWriter::new(&mut input);
+        writer.reserve_id("Payload", 70).unwrap();
+        writer.encode(&envelope).unwrap();
+        writer.encode(&payload).unwrap();
+
+        let mut dec = Decoder::new(input.as_slice());
+        dec.set_raw_type_ids([70]);
+        let Some(Value::Struct(name, mut fields)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+        assert!(matches!(fields["Payload"], Value::Raw(70, _)));
+        fields.insert("Kind".to_string(), Value::from("retry"));
+        let rewritten = Value::Struct(name, fields);
+        let message = dec.read_next().unwrap().unwrap();
+        assert!(matches!(message, Value::Raw(70, _)));
+
+        let mut out = Vec::new();
+        let mut writer = GobWriter::new(&mut out);
+        assert_eq!(writer.encode(&rewritten).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        writer.define_raw_types(&dec.raw_definitions(&rewritten)).unwrap();
+        writer.encode(&rewritten).unwrap();
+        writer.encode(&message).unwrap();
+
+        let mut dec = Decoder::new(out.as_slice());
+        let expected = Value::struct_("Envelope").field("Kind", "retry").field("Payload", payload.clone()).build();
+        assert_eq!(dec.read_next().unwrap().unwrap().to_string(), expected.to_string());
+        assert_eq!(dec.read_next().unwrap().unwrap().to_string(), payload.to_string());
+    }
+
+    #[test]
+    fn test_write_raw_bytes() {
+        // Two streams holding the same User definition (id 64) spliced into one.
+        let map = Value::from([("a", Value::Int(1))]);
+        let mut first = Vec::new();
+        let mut writer = GobWriter::new(&mut first);
+        writer.encode(&user("ann", 30)).unwrap();
+        writer.encode(&map).unwrap();
+        let second = crate::to_vec(&user("bob", 40)).unwrap();
+        let mut out = Vec::new();
+        let mut writer = GobWriter::new(&mut out);
+        assert_eq!(writer.write_raw_bytes(&first).unwrap(), first.len());
+        let written = writer.write_raw_bytes(&second).unwrap();
+        // The writer's own values reuse the adopted definitions.
+        writer.encode(&user("cy", 50)).unwrap();
+        writer.encode(&map).unwrap();
+
+        let headers = message_headers(&out);
+        assert_eq!(headers.iter().filter(|(id, _)| *id < 0).count(), 2);
+        assert_eq!(written, second.len() - headers[0].1 - 1);
+        let values: Vec<String> = Decoder::new(out.as_slice()).values().map(|v| v.unwrap().to_string()).collect();
+        assert_eq!(values, ["{Age:30 Name:ann}", "map[a:1]", "{Age:40 Name:bob}", "{Age:50 Name:cy}", "map[a:1]"]);
+
+        // A value of a type nobody defined, and a cut-off message.
+        let value_message = &second[headers[0].1 + 1..];
+        let mut writer = GobWriter::new(Vec::new());
+        assert_eq!(writer.write_raw_bytes(value_message).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(writer.write_raw_bytes(&first[..first.len() - 1]).unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_raw_message_id_clash() {
+        let mut input = Vec::new();
+        GobWriter::new(&mut input).encode(&user("ann", 30)).unwrap();
+        let message = Decoder::new(input.as_slice()).read_raw_message().unwrap().unwrap();
+
+        let mut writer = GobWriter::new(Vec::new());
+        writer.reserve_id("Other", message.type_id).unwrap();
+        assert!(writer.write_raw_message(&message).is_err());
+    }
+
+    #[test]
+    fn test_encode_stream_typed() {
+        let mut writer = GobWriter::new(Vec::new());
+        assert_eq!(writer.encode_stream_typed(vec!["a".to_string(), "b".to_string()]).unwrap(), 2);
+        let buf = writer.into_inner().unwrap();
+        let values: Vec<Value> = Decoder::new(buf.as_slice()).values().collect::<Result<_>>().unwrap();
+        assert_eq!(values, vec![Value::from("a"), Value::from("b")]);
+    }
+
+    #[test]
+    fn test_encode_typed() {
+        // Same bytes as the dynamic path for a predefined type.
+        let mut writer = GobWriter::new(Vec::new());
+        writer.encode_typed(&-3i64).unwrap();
+        assert_eq!(writer.into_inner().unwrap(), crate::to_vec(&Value::Int(-3)).unwrap());
+
+        let words = vec!["a".to_string(), "b".to_string()];
+        let buf = crate::to_vec_typed(&words).unwrap();
+        assert_eq!(decode_one(&buf), Value::Array(vec![Value::from("a"), Value::from("b")]));
+        assert_eq!(crate::from_slice_typed::<Vec<String>>(&buf).unwrap(), words);
+
+        // A type with no definition of its own is rejected.
+        struct Opaque;
+        impl GobEncodable for Opaque {
+            fn encode<W: std::io::Write>(&self, _: &mut Encoder<W>) -> Result<()> { Ok(()) }
+        }
+        assert_eq!(GobWriter::new(Vec::new()).encode_typed(&Opaque).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_preload_types() {
+        let user = Value::struct_("User").field("Name", "ann").field("Tags", Value::map().insert("a", 1)).build();
+        let mut first = GobWriter::new(Vec::new());
+        first.encode(&user).unwrap();
+        let known: Vec<(i64, TypeSchema)> = first.defined_types().iter().map(|(id, s)| (*id, s.clone())).collect();
+        let mut stream = first.into_inner().unwrap();
+
+        // A fresh writer on the same connection sends only the value message.
+        let mut second = GobWriter::new(Vec::new());
+        second.preload_types(&known).unwrap();
+        let written = second.encode(&user).unwrap();
+        second.encode(&Value::map().insert("b", 2).build()).unwrap();
+        let frame = second.into_inner().unwrap();
+        assert!(written < frame.len());
+        assert!(!frame.windows(4).any(|w| w == b"User"));
+        stream.extend_from_slice(&frame);
+
+        let values: Vec<Value> = Decoder::new(stream.as_slice()).values().collect::<Result<_>>().unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[2], Value::map().insert("b", 2).build());
+
+        let mut writer = GobWriter::new(Vec::new());
+        assert!(writer.preload_types(&[(2, TypeSchema::Int)]).is_err());
+        writer.preload_types(&known).unwrap();
+        assert!(writer.preload_types(&[(known[0].0, TypeSchema::Slice(6))]).is_err());
+    }
+}
@@ -0,0 +1,6 @@
+// Diagnostics for misused #[Gob] / #[gob] attributes. After changing a message, regenerate
+// the expected output with `TRYBUILD=overwrite cargo test --test compile_fail`.
+#[test]
+fn attribute_misuse() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,151 @@
+// Every type here is declared twice, once with #[Gob(...)] and once with #[derive(Gob)]
+// and #[gob(...)], and each test runs against both: the two forms must agree byte for byte.
+use gobx::{Decoder, Value};
+use std::collections::BTreeMap;
+
+macro_rules! both_forms {
+    ($( $(#[gob($($arg:tt)*)])? $kind:ident $name:ident $body:tt )*) => {
+        mod attr {
+            #[allow(unused_imports)]
+            use super::*;
+            $(
+                #[gobx::Gob($($($arg)*)?)]
+                #[derive(Debug, Default, PartialEq, Clone)]
+                pub $kind $name $body
+            )*
+        }
+
+        mod derived {
+            #[allow(unused_imports)]
+            use super::*;
+            $(
+                #[derive(gobx::derive::Gob, Debug, Default, PartialEq, Clone)]
+                $(#[gob($($arg)*)])?
+                pub $kind $name $body
+            )*
+        }
+    };
+}
+
+// Runs `body` with the attribute types in scope, then with the derived ones, and checks
+// that both produce the same bytes.
+macro_rules! in_both {
+    ($body:block) => {{
+        let from_attr: Vec<u8> = { use attr::*; $body };
+        let from_derive: Vec<u8> = { use derived::*; $body };
+        assert_eq!(from_attr, from_derive);
+        from_attr
+    }};
+}
+
+both_forms! {
+    #[gob(id = 90, name = "Point")]
+    struct Point {
+        pub x: i64,
+        pub y: i64,
+    }
+
+    struct Audit {
+        pub created: i64,
+    }
+
+    #[gob(rename_all = "PascalCase")]
+    struct Record {
+        pub name: String,
+        pub tags: Vec<String>,
+        #[gob(interface)]
+        pub note: String,
+        pub home: Option<Point>,
+        #[gob(name = "ID", default)]
+        pub record_id: u64,
+        #[gob(flatten)]
+        pub audit: Audit,
+    }
+
+    #[gob(interpret_as = "map[string]interface{}")]
+    struct Session {
+        pub user: String,
+        pub uid: i64,
+        #[gob(extra)]
+        pub rest: BTreeMap<Value, Value>,
+    }
+
+    #[gob(interpret_as = "[]string")]
+    struct Names {
+        pub items: Vec<String>,
+    }
+
+    enum Level {
+        #[default]
+        Low,
+        High,
+    }
+
+    #[gob(repr = "string", rename_all = "snake_case")]
+    enum Role {
+        #[default]
+        PowerUser,
+        #[gob(other)]
+        Guest,
+    }
+}
+
+#[test]
+fn test_structs() {
+    let bytes = in_both!({
+        let record = Record {
+            name: "ann".to_string(),
+            tags: vec!["a".to_string()],
+            note: "hi".to_string(),
+            home: Some(Point { x: 1, y: -2 }),
+            record_id: 7,
+            audit: Audit { created: 1700 },
+        };
+        let bytes = gobx::to_vec_typed(&record).unwrap();
+        assert_eq!(gobx::from_slice_typed::<Record>(&bytes).unwrap(), record);
+        bytes
+    });
+    let Value::Struct(_, fields) = gobx::from_slice(&bytes).unwrap() else { panic!("expected a struct") };
+    let names: Vec<_> = fields.keys().map(String::as_str).collect();
+    // Flattened fields keep the names their own type gives them.
+    assert_eq!(names, ["Home", "ID", "Name", "Note", "Tags", "created"]);
+}
+
+#[test]
+fn test_map_and_slice_modes() {
+    let mut go = BTreeMap::new();
+    go.insert(Value::from("user"), Value::from("ann"));
+    go.insert(Value::from("uid"), Value::Int(3));
+    go.insert(Value::from("theme"), Value::from("dark"));
+    let go = gobx::to_vec(&Value::Map(go)).unwrap();
+    in_both!({
+        let session: Session = Decoder::new(go.as_slice()).decode_into().unwrap();
+        assert_eq!(session.uid, 3);
+        assert_eq!(session.rest[&Value::from("theme")], Value::from("dark"));
+        let bytes = gobx::to_vec_typed(&session).unwrap();
+        assert_eq!(gobx::from_slice_typed::<Session>(&bytes).unwrap(), session);
+        bytes
+    });
+
+    in_both!({
+        let names = Names { items: vec!["a".to_string(), "b".to_string()] };
+        let bytes = gobx::to_vec_typed(&names).unwrap();
+        assert_eq!(gobx::from_slice_typed::<Names>(&bytes).unwrap(), names);
+        bytes
+    });
+}
+
+#[test]
+fn test_enums() {
+    in_both!({
+        let bytes = gobx::to_vec_typed(&Level::High).unwrap();
+        assert_eq!(gobx::from_slice_typed::<Level>(&bytes).unwrap(), Level::High);
+        bytes
+    });
+    in_both!({
+        assert_eq!(Role::gob_from_str("admin").unwrap(), Role::Guest);
+        let bytes = gobx::to_vec_typed(&Role::PowerUser).unwrap();
+        assert_eq!(gobx::from_slice(&bytes).unwrap(), Value::from("power_user"));
+        bytes
+    });
+}
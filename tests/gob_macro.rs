@@ -0,0 +1,1613 @@
+use gobx::{Decoder, Encoder, Gob, GobWriter, Value};
+use std::collections::BTreeMap;
+
+#[Gob(id = 70)]
+#[derive(Debug, Default, PartialEq)]
+struct Envelope {
+    kind: String,
+    #[gob(interface)]
+    payload: String,
+    seq: i64,
+}
+
+#[test]
+fn test_interface_field_round_trip() {
+    let envelope = Envelope {
+        kind: "greeting".to_string(),
+        payload: "hi".to_string(),
+        seq: 3,
+    };
+    let mut buf = Vec::new();
+    envelope.encode(&mut Encoder::new(&mut buf)).unwrap();
+
+    // Field 1 is written as an interface: name, type id, byte count, singleton delta, value.
+    let mut expected = b"\x01\x08greeting\x01\x06string\x0c\x04\x00\x02hi".to_vec();
+    expected.extend_from_slice(b"\x01\x06\x00");
+    assert_eq!(buf, expected);
+
+    let mut dec = Decoder::new_unframed(buf.as_slice());
+    assert_eq!(Envelope::decode(&mut dec).unwrap(), envelope);
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct UserV1 {
+    age: i64,
+    name: String,
+}
+
+fn struct_value(name: &str, fields: Vec<(&str, Value)>) -> Value {
+    Value::Struct(name.to_string(), fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn user_v2_stream() -> Vec<u8> {
+    // A newer producer that added Tags and Zip after the fields UserV1 knows.
+    let mut tags = BTreeMap::new();
+    tags.insert(Value::from("role"), Value::from("admin"));
+    let user = struct_value("User", vec![
+        ("Age", Value::Int(30)),
+        ("Name", Value::from("ann")),
+        ("Tags", Value::Map(tags)),
+        ("Zip", struct_value("Zip", vec![("Code", Value::from("12345"))])),
+    ]);
+    let mut buf = Vec::new();
+    GobWriter::new(&mut buf).encode(&user).unwrap();
+    buf
+}
+
+#[test]
+fn test_unknown_fields_error_by_default() {
+    let buf = user_v2_stream();
+    let mut dec = Decoder::new(buf.as_slice());
+    assert!(dec.decode_into::<UserV1>().is_err());
+}
+
+#[test]
+fn test_zero_valued_struct() {
+    // Go sends UserV1{} as an empty body: just the 0 that ends the struct. Two of them in
+    // a row must each decode without reading into the next message.
+    let mut buf = Vec::new();
+    let mut writer = GobWriter::new(&mut buf);
+    writer.define_struct("UserV1", 0, &[("age".to_string(), 2), ("name".to_string(), 6)]).unwrap();
+    writer.into_inner().unwrap();
+    buf.extend_from_slice(b"\x03\xff\x80\x00\x03\xff\x80\x00");
+
+    let mut dec = Decoder::new(buf.as_slice());
+    assert_eq!(dec.decode_into::<UserV1>().unwrap(), UserV1::default());
+    assert_eq!(dec.decode_into::<UserV1>().unwrap(), UserV1::default());
+    assert!(dec.read_next().unwrap().is_none());
+
+    let buf = gobx::to_vec_typed(&UserV1::default()).unwrap();
+    assert_eq!(gobx::from_slice_typed::<UserV1>(&buf).unwrap(), UserV1::default());
+}
+
+#[test]
+fn test_ignore_unknown_fields() {
+    let buf = user_v2_stream();
+    let mut dec = Decoder::new(buf.as_slice());
+    dec.set_ignore_unknown_fields(true);
+    let user: UserV1 = dec.decode_into().unwrap();
+    assert_eq!(user, UserV1 { age: 30, name: "ann".to_string() });
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct CommonFields {
+    created: String,
+    id: i64,
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Account {
+    #[gob(flatten)]
+    common: CommonFields,
+    name: String,
+}
+
+#[test]
+fn test_flatten_reads_parent_fields() {
+    // The producer lists Created, Id and Name side by side in one struct.
+    let account = struct_value("Account", vec![
+        ("Created", Value::from("2024-01-01")),
+        ("Id", Value::Int(7)),
+        ("Name", Value::from("ann")),
+    ]);
+    let mut buf = Vec::new();
+    GobWriter::new(&mut buf).encode(&account).unwrap();
+
+    let decoded: Account = Decoder::new(buf.as_slice()).decode_into().unwrap();
+    assert_eq!(decoded, Account {
+        common: CommonFields { created: "2024-01-01".to_string(), id: 7 },
+        name: "ann".to_string(),
+    });
+    assert_eq!(Account::GOB_FIELD_COUNT, 3);
+
+    // Encoding inlines the flattened fields the same way.
+    let mut body = Vec::new();
+    decoded.encode(&mut Encoder::new(&mut body)).unwrap();
+    assert_eq!(body, b"\x01\x0a2024-01-01\x01\x0e\x01\x03ann\x00");
+    assert_eq!(Account::decode(&mut Decoder::new_unframed(body.as_slice())).unwrap(), decoded);
+}
+
+// Stand-in for a Go type with GobEncode/GobDecode methods that write its decimal text.
+#[Gob(id = 80)]
+#[gob(self_encoding)]
+#[derive(Debug, Default, PartialEq)]
+struct Decimal {
+    units: i64,
+    cents: u8,
+}
+
+impl gobx::GobSelfEncoder for Decimal {
+    fn gob_encode(&self) -> gobx::Result<Vec<u8>> {
+        Ok(format!("{}.{:02}", self.units, self.cents).into_bytes())
+    }
+
+    fn gob_decode(bytes: &[u8]) -> gobx::Result<Self> {
+        let bad = || std::io::Error::new(std::io::ErrorKind::InvalidData, "bad decimal");
+        let text = std::str::from_utf8(bytes).map_err(|_| bad())?;
+        let (units, cents) = text.split_once('.').ok_or_else(bad)?;
+        Ok(Decimal { units: units.parse().map_err(|_| bad())?, cents: cents.parse().map_err(|_| bad())? })
+    }
+}
+
+#[test]
+fn test_self_encoding_round_trip() {
+    let price = Decimal { units: 1, cents: 50 };
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_self_encoded(&price).unwrap();
+    let buf = writer.into_inner().unwrap();
+
+    // A gobEncoderType definition for id 80, then the value as a byte slice, as Go sends them.
+    let mut expected = b"\x13\xff\x9f\x05\x01\x01\x07Decimal\x01\xff\xa0\x00\x00\x00".to_vec();
+    expected.extend_from_slice(b"\x08\xff\xa0\x00\x041.50");
+    assert_eq!(buf, expected);
+
+    let decoded: Decimal = Decoder::new(buf.as_slice()).decode_into().unwrap();
+    assert_eq!(decoded, price);
+    assert_eq!(Decoder::new(buf.as_slice()).read_next().unwrap(), Some(Value::GobEncoded("Decimal".to_string(), b"1.50".to_vec())));
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Record {
+    id: u64,
+    #[gob(interface)]
+    parent: u64,
+}
+
+#[test]
+fn test_max_uint_fields_round_trip() {
+    let record = Record { id: u64::MAX, parent: u64::MAX - 1 };
+    let mut buf = Vec::new();
+    record.encode(&mut Encoder::new(&mut buf)).unwrap();
+
+    let mut dec = Decoder::new_unframed(buf.as_slice());
+    assert_eq!(Record::decode(&mut dec).unwrap(), record);
+}
+
+#[Gob(id = 90, name = "Point")]
+#[derive(Debug, Default, PartialEq)]
+struct Point {
+    #[gob(name = "X")]
+    x: i64,
+    #[gob(name = "Y")]
+    y: i64,
+    #[gob(name = "Label", interface)]
+    label: String,
+}
+
+#[test]
+fn test_encode_message_sends_definition_once() {
+    let a = Point { x: 1, y: -2, label: "a".to_string() };
+    let b = Point { x: 3, y: 4, label: "b".to_string() };
+    let mut writer = GobWriter::new(Vec::new());
+    a.encode_message(&mut writer).unwrap();
+    b.encode_message(&mut writer).unwrap();
+    assert!(writer.defined_types().contains_key(&90));
+    let buf = writer.into_inner().unwrap();
+    // The second message is only the value: no definition is sent again.
+    assert_eq!(buf.windows(5).filter(|w| w == b"Point").count(), 1);
+
+    let mut dec = Decoder::new(buf.as_slice());
+    let Some(Value::Struct(_, fields)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+    assert_eq!(fields["X"], Value::Int(1));
+    assert_eq!(fields["Y"], Value::Int(-2));
+    assert_eq!(fields["Label"], Value::from("a"));
+    assert_eq!(dec.decode_into::<Point>().unwrap(), b);
+}
+
+fn default_retries() -> i64 {
+    3
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Settings {
+    name: String,
+    #[gob(default = "default_retries")]
+    retries: i64,
+    #[gob(default)]
+    verbose: bool,
+}
+
+#[test]
+fn test_default_fills_missing_fields() {
+    // An older producer that only knows about Name.
+    let old = struct_value("Settings", vec![("Name", Value::from("dev"))]);
+    let mut buf = Vec::new();
+    GobWriter::new(&mut buf).encode(&old).unwrap();
+    let decoded: Settings = Decoder::new(buf.as_slice()).decode_into().unwrap();
+    assert_eq!(decoded, Settings { name: "dev".to_string(), retries: 3, verbose: false });
+
+    // A field that is on the wire keeps its value.
+    let new = struct_value("Settings", vec![("Name", Value::from("dev")), ("Retries", Value::Int(5))]);
+    let mut buf = Vec::new();
+    GobWriter::new(&mut buf).encode(&new).unwrap();
+    let decoded: Settings = Decoder::new(buf.as_slice()).decode_into().unwrap();
+    assert_eq!(decoded.retries, 5);
+}
+
+#[Gob(interpret_as = "map[string]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+struct SettingsMap {
+    name: String,
+    #[gob(default = "default_retries")]
+    retries: i64,
+}
+
+#[test]
+fn test_default_fills_missing_map_keys() {
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_map_as(&Value::map().insert("name", "dev").build(), 6, 8).unwrap();
+    writer.encode_map_as(&Value::map().insert("name", "dev").insert("retries", 5i64).build(), 6, 8).unwrap();
+    let buf = writer.into_inner().unwrap();
+    let mut dec = Decoder::new(buf.as_slice());
+    assert_eq!(dec.decode_into::<SettingsMap>().unwrap(), SettingsMap { name: "dev".to_string(), retries: 3 });
+    assert_eq!(dec.decode_into::<SettingsMap>().unwrap().retries, 5);
+}
+
+#[Gob(id = 91, name = "Line")]
+#[derive(Debug, Default, PartialEq)]
+struct Line {
+    #[gob(name = "From")]
+    from: Point,
+    #[gob(name = "To")]
+    to: Point,
+    #[gob(name = "Width")]
+    width: i64,
+}
+
+#[test]
+fn test_nested_struct_round_trip() {
+    let line = Line {
+        from: Point { x: 1, y: 2, label: "a".to_string() },
+        to: Point { x: 0, y: -5, label: "b".to_string() },
+        width: 3,
+    };
+    assert_eq!(gobx::GobEncodable::type_id(&line.from), 90);
+    assert_eq!(gobx::GobEncodable::type_name(&line.from), "Point");
+    assert!(gobx::GobEncodable::is_struct(&line));
+
+    let mut buf = Vec::new();
+    line.encode(&mut Encoder::new(&mut buf)).unwrap();
+    // From is field 0 and holds Point's own field deltas: X, Y, then the Label interface.
+    assert!(buf.starts_with(b"\x01\x01\x02\x01\x04\x01\x06string"));
+    assert_eq!(Line::decode(&mut Decoder::new_unframed(buf.as_slice())).unwrap(), line);
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Shape {
+    #[gob(name = "Name")]
+    name: String,
+    #[gob(name = "Edge")]
+    edge: Line,
+    #[gob(name = "Price")]
+    price: Decimal,
+}
+
+#[test]
+fn test_nested_structs_through_encode_typed() {
+    let shape = Shape {
+        name: "seg".to_string(),
+        edge: Line {
+            from: Point { x: 1, y: 2, label: "a".to_string() },
+            to: Point { x: 3, y: 4, label: "b".to_string() },
+            width: 2,
+        },
+        price: Decimal { units: 9, cents: 99 },
+    };
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_typed(&shape).unwrap();
+    writer.encode_typed(&shape).unwrap();
+    let buf = writer.into_inner().unwrap();
+
+    // Inner definitions go out first, each once.
+    let find = |name: &[u8]| {
+        let hits: Vec<usize> = buf.windows(name.len()).enumerate().filter(|(_, w)| *w == name).map(|(i, _)| i).collect();
+        assert_eq!(hits.len(), 1, "{:?}", std::str::from_utf8(name));
+        hits[0]
+    };
+    assert!(find(b"Point") < find(b"Line") && find(b"Line") < find(b"Shape"));
+    assert!(find(b"Decimal") < find(b"Shape"));
+
+    let mut dec = Decoder::new(buf.as_slice());
+    let Some(Value::Struct(_, fields)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+    let Value::Struct(_, edge) = &fields["Edge"] else { panic!("expected a nested struct") };
+    let Value::Struct(_, to) = &edge["To"] else { panic!("expected a nested struct") };
+    assert_eq!(to["Y"], Value::Int(4));
+    assert_eq!(fields["Price"], Value::GobEncoded("Decimal".to_string(), b"9.99".to_vec()));
+    assert_eq!(dec.decode_into::<Shape>().unwrap(), shape);
+}
+
+// Written by gen_tagged_fixture.go.
+const TAGGED: &[u8] = include_bytes!("../tagged.bin");
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Tagged {
+    #[gob(name = "Name")]
+    name: String,
+    #[gob(name = "Tags")]
+    tags: Vec<String>,
+    #[gob(name = "Scores")]
+    scores: Vec<i64>,
+    #[gob(name = "Raw")]
+    raw: Vec<u8>,
+}
+
+#[test]
+fn test_slice_fields() {
+    let expected = Tagged {
+        name: "ann".to_string(),
+        tags: vec!["a".to_string(), "b".to_string()],
+        scores: vec![1, -2],
+        raw: Vec::new(),
+    };
+    let decoded: Tagged = Decoder::new(TAGGED).decode_into().unwrap();
+    assert_eq!(decoded, expected);
+
+    let value = Tagged { raw: b"\x00\xff".to_vec(), ..expected };
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_typed(&value).unwrap();
+    let slices: Vec<_> = writer.defined_types().values().filter(|s| matches!(s, gobx::decode::TypeSchema::Slice(_))).cloned().collect();
+    // Vec<u8> stays a byte slice; only the other two need a sliceType.
+    assert_eq!(slices, [gobx::decode::TypeSchema::Slice(6), gobx::decode::TypeSchema::Slice(2)]);
+    let buf = writer.into_inner().unwrap();
+    assert_eq!(Decoder::new(buf.as_slice()).decode_into::<Tagged>().unwrap(), value);
+}
+
+// Tagged again, with the Go names coming from the container attribute.
+#[Gob(rename_all = "PascalCase")]
+#[derive(Debug, Default, PartialEq)]
+struct TaggedRenamed {
+    name: String,
+    tags: Vec<String>,
+    scores: Vec<i64>,
+    raw: Vec<u8>,
+}
+
+#[Gob(interpret_as = "map[string]interface{}", rename_all = "camelCase")]
+#[derive(Debug, Default, PartialEq)]
+struct SessionKeys {
+    user_name: String,
+    has_two_factor: bool,
+    #[gob(name = "UID")]
+    user_id: i64,
+}
+
+#[Gob(repr = "string", rename_all = "SCREAMING_SNAKE_CASE")]
+#[derive(Debug, Default, PartialEq)]
+enum Tier {
+    #[default]
+    FreeTrial,
+    Paid,
+}
+
+#[test]
+fn test_rename_all() {
+    let decoded: TaggedRenamed = Decoder::new(TAGGED).decode_into().unwrap();
+    assert_eq!(decoded.tags, ["a", "b"]);
+    assert_eq!(decoded.scores, [1, -2]);
+
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_typed(&decoded).unwrap();
+    let names: Vec<_> = writer.defined_types().values().find_map(|s| match s {
+        gobx::decode::TypeSchema::Struct(fields) => Some(fields.iter().map(|f| f.2.clone()).collect::<Vec<_>>()),
+        _ => None,
+    }).unwrap();
+    assert_eq!(names, ["Name", "Tags", "Scores", "Raw"]);
+    let buf = writer.into_inner().unwrap();
+    assert_eq!(gobx::from_slice_typed::<Tagged>(&buf).unwrap().name, "ann");
+
+    let keys = SessionKeys { user_name: "ann".to_string(), has_two_factor: true, user_id: 7 };
+    let buf = gobx::to_vec_typed(&keys).unwrap();
+    let Value::Map(map) = gobx::from_slice(&buf).unwrap() else { panic!("expected a map") };
+    let names: Vec<_> = map.keys().cloned().collect();
+    assert_eq!(names, [Value::from("UID"), Value::from("hasTwoFactor"), Value::from("userName")]);
+    assert_eq!(gobx::from_slice_typed::<SessionKeys>(&buf).unwrap(), keys);
+
+    assert_eq!(Tier::FreeTrial.gob_str(), "FREE_TRIAL");
+    assert_eq!(Tier::gob_from_str("PAID").unwrap(), Tier::Paid);
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Profile {
+    #[gob(name = "Settings")]
+    settings: std::collections::HashMap<String, String>,
+    #[gob(name = "Scores")]
+    scores: BTreeMap<String, i64>,
+    #[gob(name = "Names")]
+    names: BTreeMap<i64, String>,
+}
+
+#[test]
+fn test_map_fields() {
+    let mut profile = Profile::default();
+    profile.settings.insert("theme".to_string(), "dark".to_string());
+    profile.settings.insert("lang".to_string(), "en".to_string());
+    profile.scores.insert("ann".to_string(), 3);
+    profile.names.insert(-1, "none".to_string());
+    profile.names.insert(7, "seven".to_string());
+
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_typed(&profile).unwrap();
+    let maps: Vec<_> = writer.defined_types().values().filter(|s| matches!(s, gobx::decode::TypeSchema::Map(..))).cloned().collect();
+    use gobx::decode::TypeSchema::Map;
+    assert_eq!(maps, [Map(6, 6), Map(6, 2), Map(2, 6)]);
+    let buf = writer.into_inner().unwrap();
+
+    assert_eq!(Decoder::new(buf.as_slice()).decode_into::<Profile>().unwrap(), profile);
+    let Some(Value::Struct(_, fields)) = Decoder::new(buf.as_slice()).read_next().unwrap() else { panic!("expected a struct") };
+    let Value::Map(names) = &fields["Names"] else { panic!("expected a map") };
+    assert_eq!(names[&Value::Int(7)], Value::from("seven"));
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Contact {
+    #[gob(name = "Name")]
+    name: String,
+    #[gob(name = "Nick")]
+    nick: Option<String>,
+    #[gob(name = "Age")]
+    age: Option<i64>,
+    #[gob(name = "Home")]
+    home: Option<Point>,
+    #[gob(name = "Note", interface)]
+    note: Option<String>,
+}
+
+#[test]
+fn test_option_fields_act_as_pointers() {
+    let bare = Contact { name: "ann".to_string(), ..Default::default() };
+    let mut body = Vec::new();
+    bare.encode(&mut Encoder::new(&mut body)).unwrap();
+    // Only Name is sent; None fields are left off like nil pointers.
+    assert_eq!(body, b"\x01\x03ann\x00");
+    assert_eq!(Contact::decode(&mut Decoder::new_unframed(body.as_slice())).unwrap(), bare);
+
+    // Some(zero) is still sent, and comes back as Some.
+    let full = Contact {
+        name: "ann".to_string(),
+        nick: Some(String::new()),
+        age: Some(30),
+        home: Some(Point { x: 1, y: 2, label: "h".to_string() }),
+        note: Some("hi".to_string()),
+    };
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_typed(&full).unwrap();
+    writer.encode_typed(&bare).unwrap();
+    let buf = writer.into_inner().unwrap();
+    let mut dec = Decoder::new(buf.as_slice());
+    assert_eq!(dec.decode_into::<Contact>().unwrap(), full);
+    assert_eq!(dec.decode_into::<Contact>().unwrap(), bare);
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+enum Status {
+    #[default]
+    Active = 1,
+    Suspended = 2,
+    #[gob(other)]
+    Unknown = -1,
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+enum Level {
+    #[default]
+    Low = 0,
+    High = 10,
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Member {
+    #[gob(name = "Name")]
+    name: String,
+    #[gob(name = "Status")]
+    status: Status,
+    #[gob(name = "Level")]
+    level: Level,
+}
+
+// What a Go producer with newer Status constants might send.
+#[Gob(name = "Member")]
+#[derive(Debug, Default)]
+struct MemberWire {
+    #[gob(name = "Name")]
+    name: String,
+    #[gob(name = "Status")]
+    status: i64,
+    #[gob(name = "Level")]
+    level: i64,
+}
+
+#[test]
+fn test_int_enums() {
+    let member = Member { name: "ann".to_string(), status: Status::Suspended, level: Level::High };
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_typed(&member).unwrap();
+    let buf = writer.into_inner().unwrap();
+
+    let mut dec = Decoder::new(buf.as_slice());
+    let Some(Value::Struct(_, fields)) = dec.read_next().unwrap() else { panic!("expected a struct") };
+    // Declared as int fields on the wire.
+    assert_eq!(fields["Status"], Value::Int(2));
+    assert_eq!(fields["Level"], Value::Int(10));
+    assert_eq!(Decoder::new(buf.as_slice()).decode_into::<Member>().unwrap(), member);
+
+    // Unknown discriminants go to the #[gob(other)] variant, or fail without one.
+    let stray = MemberWire { name: String::new(), status: 7, level: 10 };
+    let buf = gobx::to_vec_typed(&stray).unwrap();
+    assert_eq!(Decoder::new(buf.as_slice()).decode_into::<Member>().unwrap().status, Status::Unknown);
+    assert_eq!(Status::try_from(Value::Int(1)).unwrap(), Status::Active);
+    assert!(Level::try_from(Value::Int(3)).is_err());
+}
+
+#[Gob(repr = "string")]
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+enum Role {
+    #[gob(name = "admin")]
+    Admin,
+    #[default]
+    #[gob(name = "viewer")]
+    Viewer,
+}
+
+#[Gob(repr = "string")]
+#[derive(Debug, Default, PartialEq)]
+enum Plan {
+    #[default]
+    Free,
+    Pro,
+    #[gob(other)]
+    Legacy,
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Account2 {
+    #[gob(name = "Name")]
+    name: String,
+    #[gob(name = "Role")]
+    role: Role,
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Team {
+    #[gob(name = "Roles")]
+    roles: BTreeMap<String, Role>,
+    #[gob(name = "Lead", interface)]
+    lead: Role,
+    #[gob(name = "Plan")]
+    plan: Plan,
+}
+
+#[test]
+fn test_string_enums() {
+    // Go's bytes for Account{Name: "ann", Role: Admin}, where
+    // `type Role string; const Admin Role = "admin"`.
+    let mut go = b"\x26\x7f\x03\x01\x01\x07Account\x01\xff\x80\x00\x01\x02".to_vec();
+    go.extend_from_slice(b"\x01\x04Name\x01\x0c\x00\x01\x04Role\x01\x0c\x00\x00\x00");
+    go.extend_from_slice(b"\x0f\xff\x80\x01\x03ann\x01\x05admin\x00");
+    let account: Account2 = Decoder::new(go.as_slice()).decode_into().unwrap();
+    assert_eq!(account, Account2 { name: "ann".to_string(), role: Role::Admin });
+
+    let mut writer = GobWriter::new(Vec::new());
+    writer.define_struct("Account", 0, &[("Name".to_string(), 6), ("Role".to_string(), 6)]).unwrap();
+    writer.encode_struct_with(64, |enc| account.encode(enc)).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), go);
+
+    let mut team = Team { lead: Role::Admin, plan: Plan::Pro, ..Default::default() };
+    team.roles.insert("bob".to_string(), Role::Viewer);
+    let buf = gobx::to_vec_typed(&team).unwrap();
+    let Some(Value::Struct(_, fields)) = Decoder::new(buf.as_slice()).read_next().unwrap() else { panic!("expected a struct") };
+    assert_eq!(fields["Lead"], Value::from("admin"));
+    assert_eq!(fields["Plan"], Value::from("Pro"));
+    assert_eq!(Decoder::new(buf.as_slice()).decode_into::<Team>().unwrap(), team);
+
+    assert_eq!(Plan::try_from(Value::from("Enterprise")).unwrap(), Plan::Legacy);
+    assert!(Role::try_from(Value::from("owner")).is_err());
+}
+
+#[Gob(name = "Envelope")]
+#[derive(Debug, Default, PartialEq)]
+struct GenericEnvelope<T> {
+    #[gob(name = "Kind")]
+    kind: String,
+    #[gob(name = "Payload")]
+    payload: T,
+}
+
+#[Gob(name = "Batch")]
+#[gob(bound = "T: gobx::GobEncodable + gobx::GobDecodable + Default")]
+#[derive(Debug, Default, PartialEq)]
+struct Batch<T> {
+    #[gob(name = "Items")]
+    items: Vec<T>,
+    #[gob(name = "First")]
+    first: Option<T>,
+}
+
+#[test]
+fn test_generic_structs() {
+    let tags = GenericEnvelope { kind: "tags".to_string(), payload: vec!["a".to_string(), "b".to_string()] };
+    let buf = gobx::to_vec_typed(&tags).unwrap();
+    assert_eq!(gobx::from_slice_typed::<GenericEnvelope<Vec<String>>>(&buf).unwrap(), tags);
+
+    let point = GenericEnvelope { kind: "point".to_string(), payload: Point { x: 1, y: -2, ..Default::default() } };
+    let buf = gobx::to_vec_typed(&point).unwrap();
+    let Value::Struct(_, fields) = gobx::from_slice(&buf).unwrap() else { panic!("expected a struct") };
+    assert_eq!(fields["Kind"], Value::from("point"));
+    assert!(matches!(&fields["Payload"], Value::Struct(_, inner) if inner["X"] == Value::Int(1)));
+    assert_eq!(gobx::from_slice_typed::<GenericEnvelope<Point>>(&buf).unwrap(), point);
+
+    let batch = Batch { items: vec![1i64, 2], first: Some(1i64) };
+    let buf = gobx::to_vec_typed(&batch).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Batch<i64>>(&buf).unwrap(), batch);
+}
+
+#[Gob(interpret_as = "[]string")]
+#[derive(Debug, Default, PartialEq)]
+struct Tags(Vec<String>);
+
+#[Gob(interpret_as = "[]int64")]
+#[derive(Debug, Default, PartialEq)]
+struct Samples {
+    values: Vec<i64>,
+}
+
+#[test]
+fn test_slice_mode() {
+    // Go's bytes for []string{"a", "b"}.
+    let go = b"\x15\x7f\x02\x01\x01\x08[]string\x01\xff\x80\x00\x01\x0c\x00\x00\x08\xff\x80\x00\x02\x01a\x01b";
+    let tags: Tags = Decoder::new(&go[..]).decode_into().unwrap();
+    assert_eq!(tags, Tags(vec!["a".to_string(), "b".to_string()]));
+
+    let mut writer = GobWriter::new(Vec::new());
+    tags.encode_message(&mut writer).unwrap();
+    let buf = writer.into_inner().unwrap();
+    assert_eq!(gobx::from_slice(&buf).unwrap(), Value::Array(vec![Value::from("a"), Value::from("b")]));
+    assert_eq!(gobx::from_slice_typed::<Tags>(&buf).unwrap(), tags);
+
+    let samples = Samples { values: vec![3, -1] };
+    let buf = gobx::to_vec_typed(&samples).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Samples>(&buf).unwrap(), samples);
+    assert_eq!(gobx::from_slice_typed::<Vec<i64>>(&buf).unwrap(), vec![3, -1]);
+}
+
+#[Gob(interpret_as = "map[string]string")]
+#[derive(Debug, Default, PartialEq)]
+struct SessionValues {
+    user: String,
+    #[gob(name = "csrf")]
+    token: String,
+}
+
+#[Gob(interpret_as = "map[interface{}]int64")]
+#[derive(Debug, Default, PartialEq)]
+struct Counters {
+    hits: i64,
+    misses: i64,
+}
+
+#[test]
+fn test_concrete_map_types() {
+    // Go's bytes for map[string]string{"user": "ann", "csrf": "x", "extra": "y"}: plain
+    // strings, no interface wrapping.
+    let mut go = b"\x0d\x7f\x04\x01\x02\xff\x80\x00\x01\x0c\x01\x0c\x00\x00".to_vec();
+    go.extend_from_slice(b"\x1c\xff\x80\x00\x03\x04user\x03ann\x04csrf\x01x\x05extra\x01y");
+    let values: SessionValues = Decoder::new(go.as_slice()).decode_into().unwrap();
+    assert_eq!(values, SessionValues { user: "ann".to_string(), token: "x".to_string() });
+
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_map_with(6, 6, |enc| values.encode(enc)).unwrap();
+    let buf = writer.into_inner().unwrap();
+    let mut expected = BTreeMap::new();
+    expected.insert(Value::from("csrf"), Value::from("x"));
+    expected.insert(Value::from("user"), Value::from("ann"));
+    assert_eq!(gobx::from_slice(&buf).unwrap(), Value::Map(expected));
+    assert_eq!(gobx::from_slice_typed::<SessionValues>(&buf).unwrap(), values);
+    let mut writer = GobWriter::new(Vec::new());
+    values.encode_message(&mut writer).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), buf);
+
+    let counters = Counters { hits: 3, misses: -1 };
+    let buf = gobx::to_vec_typed(&counters).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Counters>(&buf).unwrap(), counters);
+}
+
+#[Gob(interpret_as = "map[string]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+struct Claims {
+    uid: i64,
+    #[gob(lenient)]
+    level: i64,
+    exp: Option<u64>,
+}
+
+#[test]
+fn test_map_mode_conversion_errors() {
+    let decode = |value: Value| gobx::from_slice_typed::<Claims>(&gobx::to_vec(&value).unwrap());
+
+    let err = decode(Value::from([("uid", Value::from("7")), ("level", Value::Int(2))])).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "Claims field uid: expected i64, got String");
+
+    // An in-range Uint fits an int field, a lenient field takes a numeric string, and a
+    // nil pointer is None.
+    let claims = decode(Value::from([("uid", Value::Uint(7)), ("level", Value::from("3")), ("exp", Value::Nil)])).unwrap();
+    assert_eq!(claims, Claims { uid: 7, level: 3, exp: None });
+
+    let err = decode(Value::from([("uid", Value::Int(1)), ("level", Value::from("high"))])).unwrap_err();
+    assert_eq!(err.to_string(), "Claims field level: expected i64, got String");
+    let err = decode(Value::from([("level", Value::from("4")), ("exp", Value::Int(-1))])).unwrap_err();
+    assert_eq!(err.to_string(), "Claims field exp: expected u64, got Int");
+}
+
+#[Gob(interpret_as = "map[interface{}]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+struct PartialSession {
+    uid: i64,
+    uname: String,
+    #[gob(extra)]
+    rest: BTreeMap<Value, Value>,
+}
+
+#[Gob(interpret_as = "map[string]string")]
+#[derive(Debug, Default, PartialEq)]
+struct Labels {
+    app: String,
+    #[gob(extra)]
+    other: gobx::value::GobMap,
+}
+
+#[test]
+fn test_extra_field_keeps_unknown_keys() {
+    // The session also holds email, _old_uid and userHasTwoFactorAuth.
+    let go = include_bytes!("../normal-session-2.bin");
+    let mut session: PartialSession = Decoder::new(&go[..]).decode_into().unwrap();
+    assert_eq!(session.uname, "dsotsen");
+    assert_eq!(session.rest.len(), 3);
+    assert_eq!(session.rest[&Value::from("email")], Value::from("dsotsen@qq.com"));
+
+    session.uname = "dso".to_string();
+    let mut writer = GobWriter::new(Vec::new());
+    session.encode_message(&mut writer).unwrap();
+    let buf = writer.into_inner().unwrap();
+    let Value::Map(mut expected) = gobx::from_slice(go).unwrap() else { panic!("expected a map") };
+    expected.insert(Value::from("uname"), Value::from("dso"));
+    assert_eq!(gobx::from_slice(&buf).unwrap(), Value::Map(expected));
+    assert_eq!(gobx::from_slice_typed::<PartialSession>(&buf).unwrap(), session);
+
+    let mut labels = Labels { app: "web".to_string(), ..Default::default() };
+    labels.other.insert(Value::from("tier"), Value::from("db"));
+    let buf = gobx::to_vec_typed(&labels).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Labels>(&buf).unwrap(), labels);
+    labels.other.insert(Value::from("replicas"), Value::Int(3));
+    assert_eq!(gobx::to_vec_typed(&labels).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[Gob(interpret_as = "map[interface{}]interface{}", match_fields = "case_insensitive")]
+#[derive(Debug, Default, PartialEq)]
+struct LooseSession {
+    uid: i64,
+    uname: String,
+    old_uid: String,
+    user_has_two_factor_auth: bool,
+}
+
+#[Gob(interpret_as = "map[string]interface{}", match_fields = "case_insensitive")]
+#[derive(Debug, Default, PartialEq)]
+struct LooseUser {
+    user_id: i64,
+    #[gob(name = "nick")]
+    display_name: String,
+}
+
+#[test]
+fn test_case_insensitive_map_keys() {
+    // "_old_uid" and "userHasTwoFactorAuth" fold to the same keys as the field names.
+    let go = include_bytes!("../normal-session-2.bin");
+    let Value::Map(map) = gobx::from_slice(go).unwrap() else { panic!("expected a map") };
+    let session: LooseSession = Decoder::new(&go[..]).decode_into().unwrap();
+    assert_eq!(session.uname, "dsotsen");
+    assert_eq!(Value::from(session.old_uid.as_str()), map[&Value::from("_old_uid")]);
+    assert_eq!(Value::Bool(session.user_has_two_factor_auth), map[&Value::from("userHasTwoFactorAuth")]);
+
+    let go = gobx::to_vec(&Value::from([("UserID", Value::Int(5)), ("NICK", Value::from("ann"))])).unwrap();
+    let user = gobx::from_slice_typed::<LooseUser>(&go).unwrap();
+    assert_eq!(user, LooseUser { user_id: 5, display_name: "ann".to_string() });
+    // Encoding still writes the names as declared.
+    let buf = gobx::to_vec_typed(&user).unwrap();
+    assert_eq!(gobx::from_slice(&buf).unwrap(), Value::from([("nick", Value::from("ann")), ("user_id", Value::Int(5))]));
+}
+
+#[Gob(id = 96)]
+#[derive(Debug, Default, PartialEq)]
+struct Deployment {
+    name: String,
+    labels: Labels,
+}
+
+#[test]
+fn test_map_mode_decode_paths() {
+    // A Go map[interface{}]interface{} reaches the map arm through decode_into, and the
+    // inherent and trait decode read its body the same way.
+    let go = include_bytes!("../normal-session-2.bin");
+    let session: PartialSession = Decoder::new(&go[..]).decode_into().unwrap();
+    let mut body = Vec::new();
+    session.encode(&mut Encoder::new(&mut body)).unwrap();
+    assert_eq!(PartialSession::decode(&mut Decoder::new_unframed(body.as_slice())).unwrap(), session);
+    let decoded: PartialSession = gobx::GobDecodable::decode(&mut Decoder::new_unframed(body.as_slice())).unwrap();
+    assert_eq!(decoded, session);
+
+    // Nested in a struct, the map-mode field goes through the same generic flow.
+    let mut labels = Labels { app: "api".to_string(), ..Default::default() };
+    labels.other.insert(Value::from("tier"), Value::from("web"));
+    let deployment = Deployment { name: "edge".to_string(), labels };
+    let buf = gobx::to_vec_typed(&deployment).unwrap();
+    assert_eq!(Decoder::new(buf.as_slice()).decode_into::<Deployment>().unwrap(), deployment);
+}
+
+#[Gob(id = 97)]
+#[derive(Debug, Default, PartialEq)]
+struct Pixel {
+    x: i32,
+    y: i16,
+    alpha: u8,
+    hue: u32,
+    index: usize,
+    gamma: f32,
+    level: Option<i8>,
+}
+
+#[Gob(id = 98, name = "Pixel")]
+#[derive(Debug, Default, PartialEq)]
+struct WidePixel {
+    x: i64,
+    y: i64,
+}
+
+#[Gob(interpret_as = "map[string]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+struct Viewport {
+    width: u16,
+    zoom: i32,
+}
+
+#[test]
+fn test_narrow_numeric_fields() {
+    let pixel = Pixel { x: -5, y: 300, alpha: 255, hue: 70000, index: 9, gamma: 2.2, level: Some(-3) };
+    let buf = gobx::to_vec_typed(&pixel).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Pixel>(&buf).unwrap(), pixel);
+    let Value::Struct(_, fields) = gobx::from_slice(&buf).unwrap() else { panic!("expected a struct") };
+    assert_eq!(fields["x"], Value::Int(-5));
+    assert_eq!(fields["hue"], Value::Uint(70000));
+
+    // A wire value too large for the field is an error, not a wrapped number.
+    let buf = gobx::to_vec_typed(&WidePixel { x: 1, y: 40000 }).unwrap();
+    let err = gobx::from_slice_typed::<Pixel>(&buf).unwrap_err();
+    assert_eq!(err.to_string(), "value 40000 out of range for int16");
+
+    // Mixed value types, so the map goes out as map[string]interface{}.
+    let go = gobx::to_vec(&Value::from([("width", Value::Int(800)), ("zoom", Value::Int(-2)), ("mode", Value::from("fit"))])).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Viewport>(&go).unwrap(), Viewport { width: 800, zoom: -2 });
+    let go = gobx::to_vec(&Value::from([("width", Value::Int(-1)), ("mode", Value::from("fit"))])).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Viewport>(&go).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Device {
+    #[gob(name = "ID")]
+    id: [u8; 16],
+    #[gob(name = "Name")]
+    name: String,
+}
+
+#[test]
+fn test_byte_array_field() {
+    // Go's encoding of Device{ID: [16]byte{0x6b, 0xa7, ...}, Name: "probe"} for
+    // `type Device struct { ID [16]byte; Name string }`: the struct type, then the
+    // [16]uint8 arrayType (elem uint, len 16), then the value with each byte as a uint.
+    let mut go = b"\x24\x7f\x03\x01\x01\x06Device\x01\xff\x80\x00\x01\x02\x01\x02ID\x01\xff\x82\x00\x01\x04Name\x01\x0c\x00\x00\x00".to_vec();
+    go.extend_from_slice(b"\x19\xff\x81\x01\x01\x01\x09[16]uint8\x01\xff\x82\x00\x01\x06\x01\x20\x00\x00");
+    go.extend_from_slice(b"\x26\xff\x80\x01\x10\x6b\xff\xa7\xff\xb8\x10\xff\x9d\xff\xad\x11\xff\xd1\xff\x80\xff\xb4\x00\xff\xc0\x4f\xff\xd4\x30\xff\xc8\x01\x05probe\x00");
+    let device: Device = Decoder::new(go.as_slice()).decode_into().unwrap();
+    let id = [0x6b, 0xa7, 0xb8, 0x10, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8];
+    assert_eq!(device, Device { id, name: "probe".to_string() });
+
+    // Ours defines the field as the same arrayType.
+    let buf = gobx::to_vec_typed(&device).unwrap();
+    let mut dec = Decoder::new(buf.as_slice());
+    assert_eq!(dec.decode_into::<Device>().unwrap(), device);
+    assert!(dec.types().values().any(|t| *t == gobx::decode::TypeSchema::Array(3, 16)));
+
+    // The count must match the array length exactly.
+    let at = go.len() - 0x27 + 4;
+    assert_eq!(go[at], 0x10);
+    go[at] = 0x0f;
+    let err = Decoder::new(go.as_slice()).decode_into::<Device>().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Sample {
+    digest: [u8; 16],
+    coords: [i64; 3],
+    names: [String; 2],
+}
+
+#[test]
+fn test_array_fields() {
+    let sample = Sample {
+        digest: [7; 16],
+        coords: [1, -2, 300],
+        names: ["a".to_string(), "b".to_string()],
+    };
+    let bytes = gobx::to_vec_typed(&sample).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Sample>(&bytes).unwrap(), sample);
+    let mut dec = Decoder::new(bytes.as_slice());
+    dec.read_next().unwrap();
+    let arrays: Vec<_> = dec.types().values().filter(|t| matches!(t, gobx::decode::TypeSchema::Array(..))).collect();
+    assert_eq!(arrays.len(), 3);
+    assert!(arrays.contains(&&gobx::decode::TypeSchema::Array(2, 3)));
+
+    // [3]int64 reads into an array of three, but not two or four, and a bad element fails it.
+    let bytes = gobx::to_vec_typed(&[1i64, 2, 3]).unwrap();
+    assert_eq!(gobx::from_slice_typed::<[i64; 3]>(&bytes).unwrap(), [1, 2, 3]);
+    let err = gobx::from_slice_typed::<[i64; 2]>(&bytes).unwrap_err();
+    assert_eq!(err.to_string(), "array length mismatch: type has 2, value has 3");
+    assert!(gobx::from_slice_typed::<[i64; 4]>(&bytes).is_err());
+    let bytes = gobx::to_vec_typed(&[1i64, 200, 3]).unwrap();
+    assert_eq!(gobx::from_slice_typed::<[i8; 3]>(&bytes).unwrap_err().to_string(), "value 200 out of range for int8");
+
+    // An array type has no fixed id or name, so it can't go in an interface on its own.
+    let err = gobx::encode_as_interface(&[1i64, 2, 3], &mut Encoder::new(Vec::new())).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[Gob(id = 99, name = "main.Marker")]
+#[derive(Debug, Default, PartialEq)]
+struct Marker {
+    x: i64,
+}
+
+#[Gob(id = 100)]
+#[derive(Debug, Default, PartialEq)]
+struct Route {
+    stops: Vec<Marker>,
+    last: Option<Marker>,
+}
+
+#[test]
+fn test_struct_gob_encodable() {
+    fn describe<T: gobx::GobEncodable + gobx::GobType>(value: &T) -> (i64, &'static str, bool) {
+        assert_eq!(value.type_id(), T::ID);
+        (value.type_id(), value.type_name(), value.is_struct())
+    }
+    let marker = Marker { x: 5 };
+    assert_eq!(describe(&marker), (99, "main.Marker", true));
+
+    // As an interface: name, type id, byte count, then the struct body with no singleton delta.
+    let mut buf = Vec::new();
+    gobx::encode_as_interface(&marker, &mut Encoder::new(&mut buf)).unwrap();
+    assert_eq!(buf, b"\x0bmain.Marker\xff\xc6\x03\x01\x0a\x00");
+
+    let route = Route { stops: vec![Marker { x: 1 }, Marker { x: 2 }], last: Some(Marker { x: 2 }) };
+    let buf = gobx::to_vec_typed(&route).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Route>(&buf).unwrap(), route);
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+struct Perms(u32);
+
+// Perms travel as a Go int.
+mod perms_as_int {
+    use super::Perms;
+    use gobx::{Decoder, Encoder};
+
+    pub fn encode<W: std::io::Write>(perms: &Perms, encoder: &mut Encoder<W>) -> std::io::Result<()> {
+        encoder.write_int(perms.0 as i64)
+    }
+
+    pub fn decode<R: std::io::Read>(decoder: &mut Decoder<R>) -> std::io::Result<Perms> {
+        u32::try_from(decoder.read_int()?).map(Perms).map_err(std::io::Error::other)
+    }
+
+    pub fn wire_type_id() -> i64 {
+        2
+    }
+}
+
+fn upper_case<R: std::io::Read>(decoder: &mut Decoder<R>) -> std::io::Result<String> {
+    decoder.read_string().map(|s| s.to_uppercase())
+}
+
+fn trimmed<W: std::io::Write>(name: &str, encoder: &mut Encoder<W>) -> std::io::Result<()> {
+    encoder.write_string(name.trim())
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct FileEntry {
+    #[gob(name = "Name", encode_with = "trimmed", decode_with = "upper_case")]
+    name: String,
+    #[gob(name = "Mode", with = "perms_as_int")]
+    mode: Perms,
+}
+
+#[test]
+fn test_custom_field_coding() {
+    let entry = FileEntry { name: " a.txt ".to_string(), mode: Perms(0o644) };
+    let buf = gobx::to_vec_typed(&entry).unwrap();
+    let Value::Struct(_, fields) = gobx::from_slice(&buf).unwrap() else { panic!("expected a struct") };
+    assert_eq!(fields["Name"], Value::from("a.txt"));
+    assert_eq!(fields["Mode"], Value::Int(0o644));
+
+    let decoded: FileEntry = gobx::from_slice_typed(&buf).unwrap();
+    assert_eq!(decoded, FileEntry { name: "A.TXT".to_string(), mode: Perms(0o644) });
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct AuthToken {
+    #[gob(required)]
+    token: String,
+    #[gob(name = "UserID", required)]
+    user_id: i64,
+    note: String,
+}
+
+#[Gob(interpret_as = "map[string]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+struct Grant {
+    #[gob(required)]
+    scope: String,
+    ttl: i64,
+}
+
+#[test]
+fn test_required_fields() {
+    // Only field 3 (note) was sent.
+    let err = AuthToken::decode(&mut Decoder::new_unframed(&b"\x03\x01x\x00"[..])).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "AuthToken is missing required fields: token, UserID");
+    let token = AuthToken::decode(&mut Decoder::new_unframed(&b"\x01\x03abc\x01\x0e\x00"[..])).unwrap();
+    assert_eq!(token, AuthToken { token: "abc".to_string(), user_id: 7, note: String::new() });
+
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_map_as(&Value::map().insert("ttl", 60).build(), 6, 8).unwrap();
+    let buf = writer.into_inner().unwrap();
+    let err = gobx::from_slice_typed::<Grant>(&buf).unwrap_err();
+    assert_eq!(err.to_string(), "Grant is missing required fields: scope");
+
+    let grant = Grant { scope: "read".to_string(), ttl: 60 };
+    let buf = gobx::to_vec_typed(&grant).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Grant>(&buf).unwrap(), grant);
+}
+
+// The same labels as Labels, sent as a Go struct.
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct LabelStruct {
+    app: String,
+    tier: String,
+}
+
+#[Gob(name = "Deployment")]
+#[derive(Debug, Default, PartialEq)]
+struct StructDeployment {
+    name: String,
+    labels: LabelStruct,
+}
+
+#[test]
+fn test_map_mode_decodes_go_structs() {
+    // A Go struct with the same fields decodes into a map-mode struct; fields the Rust
+    // struct doesn't declare go to the extra field under their names.
+    let go = Value::struct_("Session").field("uid", 7i64).field("uname", "ann").field("email", "a@b.c").build();
+    let buf = gobx::to_vec(&go).unwrap();
+    let mut decoder = Decoder::new(buf.as_slice());
+    let session: PartialSession = decoder.decode_into().unwrap();
+    assert_eq!(session.uid, 7);
+    assert_eq!(session.uname, "ann");
+    assert_eq!(session.rest.len(), 1);
+    assert_eq!(session.rest[&Value::from("email")], Value::from("a@b.c"));
+    let go_map = gobx::to_vec_typed(&session).unwrap();
+    assert_eq!(gobx::from_slice_typed::<PartialSession>(&go_map).unwrap(), session);
+
+    // Nested, the field's wire type says which form it is.
+    let wire = StructDeployment { name: "edge".to_string(), labels: LabelStruct { app: "api".to_string(), tier: "web".to_string() } };
+    let buf = gobx::to_vec_typed(&wire).unwrap();
+    let deployment: Deployment = gobx::from_slice_typed(&buf).unwrap();
+    assert_eq!(deployment.name, "edge");
+    assert_eq!(deployment.labels.app, "api");
+    assert_eq!(deployment.labels.other[&Value::from("tier")], Value::from("web"));
+
+    // Without an extra field, undeclared fields are skipped.
+    let go = Value::struct_("Grant").field("scope", "read").field("owner", "ann").field("ttl", 60i64).build();
+    let grant: Grant = gobx::from_slice_typed(&gobx::to_vec(&go).unwrap()).unwrap();
+    assert_eq!(grant, Grant { scope: "read".to_string(), ttl: 60 });
+}
+
+// Has its own to_gob_vec, so it opts out of the generated one.
+#[Gob(no_convenience)]
+#[derive(Debug, Default, PartialEq)]
+struct Checksum {
+    sum: u64,
+}
+
+impl Checksum {
+    fn to_gob_vec(&self) -> Vec<u8> {
+        self.sum.to_be_bytes().to_vec()
+    }
+}
+
+#[test]
+fn test_convenience_methods() {
+    let deployment = Deployment { name: "edge".to_string(), labels: Labels { app: "api".to_string(), ..Default::default() } };
+    let bytes = deployment.to_gob_vec().unwrap();
+    assert_eq!(bytes, gobx::to_vec_typed(&deployment).unwrap());
+    assert_eq!(Deployment::from_gob_slice(&bytes).unwrap(), deployment);
+
+    let grant = Grant { scope: "read".to_string(), ttl: 60 };
+    assert_eq!(Grant::from_gob_slice(&grant.to_gob_vec().unwrap()).unwrap(), grant);
+    let tags = Tags(vec!["a".to_string()]);
+    assert_eq!(Tags::from_gob_slice(&tags.to_gob_vec().unwrap()).unwrap(), tags);
+
+    assert_eq!(Checksum { sum: 1 }.to_gob_vec(), [0, 0, 0, 0, 0, 0, 0, 1]);
+    let bytes = gobx::to_vec_typed(&Checksum { sum: 2 }).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Checksum>(&bytes).unwrap(), Checksum { sum: 2 });
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct LegacyIds {
+    #[gob(as_string)]
+    uid: i64,
+    #[gob(as_string)]
+    quota: u64,
+    #[gob(as_string)]
+    ratio: f64,
+    #[gob(as_string)]
+    active: bool,
+}
+
+// What Go sends: every field a string.
+#[Gob(name = "LegacyIds")]
+#[derive(Debug, Default, PartialEq)]
+struct LegacyStrings {
+    uid: String,
+    quota: String,
+    ratio: String,
+    active: String,
+}
+
+#[Gob(interpret_as = "map[interface{}]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+struct LegacySession {
+    uname: String,
+    #[gob(name = "_old_uid", as_string)]
+    old_uid: i64,
+}
+
+#[test]
+fn test_as_string_fields() {
+    let go = LegacyStrings { uid: "-7".to_string(), quota: "1024".to_string(), ratio: "0.5".to_string(), active: "true".to_string() };
+    let bytes = gobx::to_vec_typed(&go).unwrap();
+    let ids: LegacyIds = gobx::from_slice_typed(&bytes).unwrap();
+    assert_eq!(ids, LegacyIds { uid: -7, quota: 1024, ratio: 0.5, active: true });
+    // Encoding formats them back into the same strings.
+    assert_eq!(gobx::to_vec_typed(&ids).unwrap(), bytes);
+
+    let go = LegacyStrings { uid: "abc".to_string(), ..go };
+    let err = gobx::from_slice_typed::<LegacyIds>(&gobx::to_vec_typed(&go).unwrap()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "LegacyIds field uid: cannot parse \"abc\" as i64: invalid digit found in string");
+
+    // The Go session keeps _old_uid as the string "1".
+    let go = include_bytes!("../normal-session-2.bin");
+    let session: LegacySession = Decoder::new(&go[..]).decode_into().unwrap();
+    assert_eq!(session.old_uid, 1);
+    let buf = gobx::to_vec_typed(&session).unwrap();
+    assert_eq!(gobx::from_slice(&buf).unwrap(), Value::from([("_old_uid", "1"), ("uname", "dsotsen")]));
+    assert_eq!(gobx::from_slice_typed::<LegacySession>(&buf).unwrap(), session);
+
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_map_as(&Value::map().insert("_old_uid", 1).insert("uname", "ann").build(), 8, 8).unwrap();
+    let err = gobx::from_slice_typed::<LegacySession>(&writer.into_inner().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "LegacySession field _old_uid: expected a string, got Int");
+}
+
+type Uid = u64;
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Typed {
+    uid: Uid,
+    name: std::string::String,
+    count: i64,
+}
+
+#[test]
+fn test_field_types_are_checked() {
+    let typed = Typed { uid: 7, name: "a".to_string(), count: 3 };
+    assert_eq!(gobx::from_slice_typed::<Typed>(&gobx::to_vec_typed(&typed).unwrap()).unwrap(), typed);
+
+    // A field whose GOB_FIELDS id is known must come with that wire type.
+    let go = Value::OrderedStruct("Typed".to_string(), vec![
+        ("uid".to_string(), Value::Uint(7)),
+        ("name".to_string(), Value::from("a")),
+        ("count".to_string(), Value::from("3")),
+    ]);
+    let err = gobx::from_slice_typed::<Typed>(&gobx::to_vec(&go).unwrap()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "Typed field count: expected type id 2, got 6");
+}
+
+#[test]
+fn test_schema_metadata() {
+    use gobx::types::FieldType;
+    use std::borrow::Cow;
+    let field = |name: &'static str, id| FieldType { name: Cow::Borrowed(name), id };
+
+    // Own fields only; a flattened member lists its fields itself. A bare `String` could be
+    // any type of that name, so only the schema knows its id.
+    assert_eq!(Account::GOB_FIELDS, [field("name", 0)]);
+    assert_eq!(CommonFields::GOB_FIELDS, [field("created", 0), field("id", 2)]);
+    assert_eq!(Deployment::GOB_FIELDS, [field("name", 0), field("labels", 0)]);
+    assert_eq!(LegacyIds::GOB_FIELDS[0], field("uid", 6));
+    assert_eq!(Typed::GOB_FIELDS, [field("uid", 0), field("name", 6), field("count", 2)]);
+
+    let schema = Account::gob_schema().unwrap();
+    assert_eq!((schema.common.name.as_str(), schema.common.id), ("Account", 64));
+    assert_eq!(schema.fields, [field("created", 6), field("id", 2), field("name", 6)]);
+
+    // The same definition encode_typed sends.
+    let schema = Deployment::gob_schema().unwrap();
+    assert_eq!(schema.common.id, 96);
+    let bytes = Deployment::default().to_gob_vec().unwrap();
+    let mut dec = Decoder::new(bytes.as_slice());
+    dec.decode_into::<Deployment>().unwrap();
+    let gobx::decode::TypeSchema::Struct(sent) = &dec.types()[&96] else { panic!("expected a struct") };
+    let sent: Vec<_> = sent.iter().map(|(_, id, name)| FieldType { name: name.clone().into(), id: *id }).collect();
+    assert_eq!(schema.fields, sent);
+}
+
+#[test]
+fn test_encode_interface_typed() {
+    // Go: var v interface{} = Marker{X: 5}; enc.Encode(&v), with Marker registered as
+    // "main.Marker". The definition comes first, then an interface{} (id 8) message.
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_interface_typed(&Marker { x: 5 }).unwrap();
+    // A struct without a fixed id gets one from the stream.
+    writer.encode_interface_typed(&CommonFields { created: "today".to_string(), id: 3 }).unwrap();
+    writer.encode_interface_typed(&7i64).unwrap();
+    let buf = writer.into_inner().unwrap();
+    let message = b"\x10\x00\x0bmain.Marker\xff\xc6\x03\x01\x0a\x00";
+    let at = buf.windows(message.len()).position(|w| w == message).unwrap();
+    assert_eq!(buf[at - 1] as usize, message.len());
+
+    let mut dec = Decoder::new(buf.as_slice());
+    assert_eq!(dec.read_next().unwrap().unwrap(), Value::struct_("main.Marker").field("x", 5i64).build());
+    let common = dec.read_next().unwrap().unwrap();
+    assert_eq!(common, Value::struct_("CommonFields").field("created", "today").field("id", 3i64).build());
+    assert_eq!(dec.read_next().unwrap(), Some(Value::Int(7)));
+    assert_eq!(dec.types().keys().filter(|&&id| id >= 64).count(), 2);
+
+    let err = GobWriter::new(Vec::new()).encode_interface_typed(&Value::from("x")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[Gob(name = "Wrapped")]
+#[derive(Debug, Default, PartialEq)]
+struct Wrapped {
+    marker: Box<Marker>,
+    label: std::sync::Arc<String>,
+    count: std::rc::Rc<i64>,
+    note: std::borrow::Cow<'static, str>,
+    parent: Option<Box<Marker>>,
+}
+
+#[Gob(name = "Wrapped")]
+#[derive(Debug, Default, PartialEq)]
+struct Unwrapped {
+    marker: Marker,
+    label: String,
+    count: i64,
+    note: String,
+    parent: Option<Marker>,
+}
+
+#[test]
+fn test_wrapper_fields() {
+    use gobx::GobEncodable;
+    use std::borrow::Cow;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    // Pointers and Cow are invisible on the wire.
+    let wrapped = Wrapped {
+        marker: Box::new(Marker { x: 1 }),
+        label: Arc::new("ann".to_string()),
+        count: Rc::new(-3),
+        note: Cow::Borrowed("hi"),
+        parent: Some(Box::new(Marker { x: 2 })),
+    };
+    let plain = Unwrapped {
+        marker: Marker { x: 1 },
+        label: "ann".to_string(),
+        count: -3,
+        note: "hi".to_string(),
+        parent: Some(Marker { x: 2 }),
+    };
+    let bytes = gobx::to_vec_typed(&wrapped).unwrap();
+    assert_eq!(bytes, gobx::to_vec_typed(&plain).unwrap());
+    assert_eq!(gobx::from_slice_typed::<Wrapped>(&bytes).unwrap(), wrapped);
+    assert_eq!(Wrapped::GOB_FIELDS.iter().map(|f| f.id).collect::<Vec<_>>(), [0; 5]);
+
+    // A nil pointer stays off the wire.
+    let wrapped = Wrapped { parent: None, ..wrapped };
+    let bytes = gobx::to_vec_typed(&wrapped).unwrap();
+    assert_eq!(bytes, gobx::to_vec_typed(&Unwrapped { parent: None, ..plain }).unwrap());
+    assert_eq!(gobx::from_slice_typed::<Wrapped>(&bytes).unwrap(), wrapped);
+
+    // Standing alone, each is its pointee.
+    assert_eq!(gobx::to_vec_typed(&Box::new(7i64)).unwrap(), gobx::to_vec_typed(&7i64).unwrap());
+    assert_eq!(gobx::from_slice_typed::<Arc<Marker>>(&gobx::to_vec_typed(&Marker { x: 4 }).unwrap()).unwrap(),
+        Arc::new(Marker { x: 4 }));
+    let bytes = gobx::to_vec_typed(&Cow::Borrowed("go")).unwrap();
+    assert_eq!(gobx::from_slice(&bytes).unwrap(), Value::from("go"));
+    let cow: Cow<str> = gobx::from_slice_typed(&bytes).unwrap();
+    assert!(matches!(cow, Cow::Owned(ref s) if s == "go"));
+    assert_eq!(GobEncodable::type_name(&Rc::new("x".to_string())), "string");
+    assert_eq!(GobEncodable::type_id(&Box::new(Marker::default())), 99);
+}
+
+#[Gob(rename_all = "PascalCase")]
+#[derive(Debug, Default, PartialEq)]
+struct Glyph {
+    r: char,
+    names: BTreeMap<char, String>,
+}
+
+#[test]
+fn test_runes() {
+    // Go: type Glyph struct { R rune; Names map[rune]string }, sent as
+    // Glyph{R: 'é', Names: map[rune]string{'a': "alpha"}}. A rune is an int32 on the wire.
+    let go = b"\x24\xff\x81\x03\x01\x01\x05Glyph\x01\xff\x82\x00\x01\x02\x01\x01R\x01\x04\x00\x01\x05Names\x01\xff\x84\x00\x00\x00\
+        \x20\xff\x83\x04\x01\x01\x10map[int32]string\x01\xff\x84\x00\x01\x04\x01\x0c\x00\x00\
+        \x11\xff\x82\x01\xfe\x01\xd2\x01\x01\xff\xc2\x05alpha\x00";
+    let glyph: Glyph = Decoder::new(&go[..]).decode_into().unwrap();
+    assert_eq!(glyph, Glyph { r: 'é', names: BTreeMap::from([('a', "alpha".to_string())]) });
+    let bytes = gobx::to_vec_typed(&glyph).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Glyph>(&bytes).unwrap(), glyph);
+    assert_eq!(gobx::from_slice(&bytes).unwrap(), gobx::from_slice(go).unwrap());
+
+    // Go accepts any int32 as a rune; a char must be a Unicode scalar value.
+    for bad in [0xd800i64, 0x110000, -1] {
+        let err = gobx::from_slice_typed::<char>(&gobx::to_vec_typed(&bad).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), format!("value {} is not a valid char", bad));
+    }
+    assert_eq!(char::try_from(Value::Int(0x1f600)).unwrap(), '😀');
+    assert!(char::try_from(Value::Int(0xdfff)).is_err());
+}
+
+#[Gob(rename_all = "PascalCase")]
+#[derive(Debug, PartialEq)]
+struct Login {
+    user: String,
+    at: std::time::SystemTime,
+}
+
+impl Default for Login {
+    fn default() -> Self {
+        Login { user: String::new(), at: std::time::UNIX_EPOCH }
+    }
+}
+
+#[test]
+fn test_time_field() {
+    let login = Login { user: "ann".to_string(), at: std::time::UNIX_EPOCH + std::time::Duration::new(1_704_164_645, 6) };
+    let bytes = gobx::to_vec_typed(&login).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Login>(&bytes).unwrap(), login);
+    let Value::Struct(_, fields) = gobx::from_slice(&bytes).unwrap() else { panic!("expected a struct") };
+    assert_eq!(fields["At"].as_system_time(), Some(login.at));
+}
+
+#[Gob(interpret_as = "map[string]interface{}")]
+#[derive(Debug, Default, PartialEq)]
+struct Timeouts {
+    read: std::time::Duration,
+    write: Option<std::time::Duration>,
+}
+
+#[Gob(rename_all = "PascalCase")]
+#[derive(Debug, Default, PartialEq)]
+struct Retry {
+    backoff: std::time::Duration,
+}
+
+mod chrono_like {
+    // Another type named Duration, sent as an int too but not one the macro can know about.
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Duration(pub i64);
+
+    impl gobx::GobEncodable for Duration {
+        fn encode<W: std::io::Write>(&self, encoder: &mut gobx::Encoder<W>) -> std::io::Result<()> {
+            encoder.write_int(self.0)
+        }
+        fn type_id(&self) -> i64 { 2 }
+        fn write_type_definition<W: std::io::Write>(_: &mut gobx::GobWriter<W>) -> std::io::Result<i64> { Ok(2) }
+    }
+
+    impl gobx::GobDecodable for Duration {
+        fn decode<R: std::io::Read>(decoder: &mut gobx::Decoder<R>) -> std::io::Result<Self> {
+            decoder.read_int().map(Duration)
+        }
+    }
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Pause {
+    length: chrono_like::Duration,
+}
+
+#[test]
+fn test_duration_fields() {
+    use std::time::Duration;
+
+    // Go: map[string]interface{}{"read": 2 * time.Second, "write": 500 * time.Millisecond}.
+    // Each value goes into the interface as "time.Duration", an int.
+    let mut writer = GobWriter::new(Vec::new());
+    writer.encode_map_with(6, 8, |enc| {
+        enc.write_uint(2)?;
+        enc.write_string("read")?;
+        gobx::encode_as_interface(&Duration::from_secs(2), enc)?;
+        enc.write_string("write")?;
+        gobx::encode_as_interface(&Duration::from_millis(500), enc)
+    }).unwrap();
+    let go = writer.into_inner().unwrap();
+    assert!(go.windows(14).any(|w| w == b"\x0dtime.Duration"));
+    let timeouts: Timeouts = gobx::from_slice_typed(&go).unwrap();
+    assert_eq!(timeouts, Timeouts { read: Duration::from_secs(2), write: Some(Duration::from_millis(500)) });
+    let bytes = gobx::to_vec_typed(&timeouts).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Timeouts>(&bytes).unwrap(), timeouts);
+    assert_eq!(gobx::from_slice(&bytes).unwrap(), Value::from([("read", 2_000_000_000i64), ("write", 500_000_000)]));
+
+    assert_eq!(Retry::GOB_FIELDS.iter().map(|f| f.id).collect::<Vec<_>>(), [2]);
+    assert_eq!(Pause::GOB_FIELDS.iter().map(|f| f.id).collect::<Vec<_>>(), [0]);
+    let retry = Retry { backoff: Duration::from_micros(250) };
+    let bytes = gobx::to_vec_typed(&retry).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Retry>(&bytes).unwrap(), retry);
+}
+
+#[Gob(rename_all = "PascalCase")]
+#[derive(Debug, Default, PartialEq)]
+struct Toggle {
+    enabled: bool,
+}
+
+#[test]
+fn test_false_bools_are_omitted() {
+    // Go: type Toggle struct { Enabled bool }. Toggle{false} is the definition and then a
+    // value message holding only the struct's terminating 0.
+    let bytes = gobx::to_vec_typed(&Toggle { enabled: false }).unwrap();
+    assert!(bytes.ends_with(b"\x03\xff\x80\x00"));
+    assert_eq!(gobx::from_slice_typed::<Toggle>(&bytes).unwrap(), Toggle { enabled: false });
+    let Value::Struct(_, fields) = gobx::from_slice(&bytes).unwrap() else { panic!("expected a struct") };
+    assert!(fields.is_empty());
+
+    let bytes = gobx::to_vec_typed(&Toggle { enabled: true }).unwrap();
+    assert!(bytes.ends_with(b"\x05\xff\x80\x01\x01\x00"));
+    assert_eq!(gobx::from_slice_typed::<Toggle>(&bytes).unwrap(), Toggle { enabled: true });
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Flags {
+    #[gob(default = "yes")]
+    enabled: bool,
+    #[gob(required)]
+    ok: bool,
+}
+
+#[test]
+fn test_false_bools_with_default_or_required_are_sent() {
+    // Leaving them out would read back as the default, or fail as missing.
+    let flags = Flags { enabled: false, ok: false };
+    let bytes = gobx::to_vec_typed(&flags).unwrap();
+    assert_eq!(gobx::from_slice_typed::<Flags>(&bytes).unwrap(), flags);
+    let Value::Struct(_, fields) = gobx::from_slice(&bytes).unwrap() else { panic!("expected a struct") };
+    assert_eq!(fields.len(), 2);
+}
+
+#[Gob]
+#[derive(Debug, Default, PartialEq)]
+struct Node {
+    next: Option<Box<Node>>,
+}
+
+// Go's `type Node struct { Next *Node }` holding a chain `links` deep. The type refers to
+// itself, and each link is one more field delta before the struct ends are all sent.
+fn node_chain_stream(links: usize) -> Vec<u8> {
+    let mut def = Vec::new();
+    let mut enc = Encoder::new(&mut def);
+    enc.write_int(-64).unwrap();
+    for n in [3, 1, 1] { enc.write_uint(n).unwrap(); } // StructT, CommonType, Name
+    enc.write_string("Node").unwrap();
+    enc.write_uint(1).unwrap();
+    enc.write_int(64).unwrap();
+    for n in [0, 1, 1, 1] { enc.write_uint(n).unwrap(); } // Field: one fieldType, Name
+    enc.write_string("Next").unwrap();
+    enc.write_uint(1).unwrap();
+    enc.write_int(64).unwrap();
+    for n in [0, 0, 0] { enc.write_uint(n).unwrap(); }
+
+    let mut value = Vec::new();
+    Encoder::new(&mut value).write_int(64).unwrap();
+    value.extend(std::iter::repeat_n(1u8, links));
+    value.extend(std::iter::repeat_n(0u8, links + 1));
+
+    let mut stream = Vec::new();
+    for body in [def, value] {
+        Encoder::new(&mut stream).write_uint(body.len() as u64).unwrap();
+        stream.extend(body);
+    }
+    stream
+}
+
+#[test]
+fn test_recursive_struct_depth_limit() {
+    let chain = |links: usize| (0..links).fold(Node::default(), |next, _| Node { next: Some(Box::new(next)) });
+    assert_eq!(gobx::from_slice_typed::<Node>(&node_chain_stream(3)).unwrap(), chain(3));
+
+    // A hostile stream a million links deep fails cleanly instead of overflowing the stack.
+    let err = gobx::from_slice_typed::<Node>(&node_chain_stream(1_000_000)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "gob value nested more than 128 levels deep");
+
+    let stream = node_chain_stream(60);
+    let mut dec = Decoder::new(stream.as_slice());
+    dec.set_max_depth(50);
+    assert_eq!(dec.decode_into::<Node>().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    let mut dec = Decoder::new(stream.as_slice());
+    dec.set_max_depth(60);
+    assert_eq!(dec.decode_into::<Node>().unwrap(), chain(60));
+}
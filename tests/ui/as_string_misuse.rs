@@ -0,0 +1,24 @@
+use gobx::Gob;
+
+#[Gob]
+#[derive(Debug, Default)]
+struct Account {
+    #[gob(as_string, interface)]
+    uid: i64,
+}
+
+#[Gob]
+#[derive(Debug, Default)]
+struct Quota {
+    #[gob(as_string)]
+    limit: Option<u64>,
+}
+
+#[Gob(interpret_as = "map[string]int64")]
+#[derive(Debug, Default)]
+struct Counters {
+    #[gob(as_string)]
+    hits: i64,
+}
+
+fn main() {}
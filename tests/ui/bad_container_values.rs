@@ -0,0 +1,16 @@
+use gobx::Gob;
+
+#[Gob(rename_all = "kebab-case")]
+#[derive(Debug, Default)]
+struct User {
+    name: String,
+}
+
+#[Gob(repr = "str")]
+#[derive(Debug, Default)]
+enum Role {
+    #[default]
+    Admin,
+}
+
+fn main() {}
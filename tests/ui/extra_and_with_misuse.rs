@@ -0,0 +1,21 @@
+use gobx::Gob;
+use std::collections::BTreeMap;
+
+#[Gob]
+#[derive(Debug, Default)]
+struct Labels {
+    env: String,
+    #[gob(extra)]
+    rest: BTreeMap<gobx::Value, gobx::Value>,
+}
+
+mod as_text {}
+
+#[Gob]
+#[derive(Debug, Default)]
+struct Entry {
+    #[gob(with = "as_text", decode_with = "as_text::decode")]
+    mode: u32,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use gobx::Gob;
+
+#[Gob]
+#[derive(Debug, Default)]
+struct Inner {
+    a: i64,
+}
+
+#[Gob]
+#[derive(Debug, Default)]
+struct Outer {
+    #[gob(flatten, interface)]
+    inner: Inner,
+}
+
+fn main() {}
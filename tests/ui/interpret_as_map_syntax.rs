@@ -0,0 +1,9 @@
+use gobx::Gob;
+
+#[Gob(interpret_as = "map[interface{}interface{}]")]
+#[derive(Debug, Default)]
+struct Session {
+    user: String,
+}
+
+fn main() {}
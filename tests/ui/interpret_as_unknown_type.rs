@@ -0,0 +1,15 @@
+use gobx::Gob;
+
+#[Gob(interpret_as = "map[string]Strng")]
+#[derive(Debug, Default)]
+struct Session {
+    user: String,
+}
+
+#[Gob(interpret_as = "vec<string>")]
+#[derive(Debug, Default)]
+struct Names {
+    items: Vec<String>,
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use gobx::Gob;
+
+#[Gob(interpret_as = "map[string]string", match_fields = "ignore_case")]
+#[derive(Debug, Default)]
+struct Labels {
+    app: String,
+}
+
+#[Gob(match_fields = "case_insensitive")]
+#[derive(Debug, Default)]
+struct User {
+    name: String,
+}
+
+#[Gob(interpret_as = "map[string]interface{}", match_fields = "case_insensitive")]
+#[derive(Debug, Default)]
+struct Token {
+    user_id: i64,
+    #[gob(name = "UserID")]
+    uid: i64,
+}
+
+fn main() {}
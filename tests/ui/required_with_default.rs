@@ -0,0 +1,10 @@
+use gobx::Gob;
+
+#[Gob]
+#[derive(Debug, Default)]
+struct Session {
+    #[gob(required, default)]
+    token: String,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use gobx::Gob;
+
+#[Gob(idd = 70)]
+#[derive(Debug, Default)]
+struct User {
+    name: String,
+}
+
+#[derive(gobx::derive::Gob, Debug, Default)]
+#[gob(interpret_as = "map[string]string", self_encode)]
+struct Labels {
+    env: String,
+}
+
+fn main() {}
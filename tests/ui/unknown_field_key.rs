@@ -0,0 +1,10 @@
+use gobx::Gob;
+
+#[Gob]
+#[derive(Debug, Default)]
+struct User {
+    #[gob(nmae = "Name")]
+    name: String,
+}
+
+fn main() {}